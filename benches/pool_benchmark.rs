@@ -30,7 +30,7 @@ Content-Length: 0
     group.bench_function("regular_allocation", |b| {
         b.iter(|| {
             let mut msg = SipMessage::new_from_str(black_box(&sip_invite));
-            black_box(msg.parse()).unwrap();
+            black_box(msg.parse_headers()).unwrap();
             black_box(msg.call_id()).unwrap();
         })
     });
@@ -129,7 +129,7 @@ Content-Length: 0
                     for i in 0..size {
                         let msg_data = black_box(&sip_messages[i % sip_messages.len()]);
                         let mut msg = SipMessage::new_from_str(msg_data);
-                        black_box(msg.parse()).unwrap();
+                        black_box(msg.parse_headers()).unwrap();
                         messages.push(msg);
                     }
                     black_box(messages.len());
@@ -119,7 +119,7 @@ fn bench_parsing_performance(c: &mut Criterion) {
         group.bench_with_input(BenchmarkId::new("parse", name), &message, |b, msg| {
             b.iter(|| {
                 let mut sip_msg = SipMessage::new_from_str(black_box(msg));
-                black_box(sip_msg.parse()).unwrap();
+                black_box(sip_msg.parse_headers()).unwrap();
             })
         });
     }
@@ -133,7 +133,7 @@ fn bench_header_access(c: &mut Criterion) {
     
     // Pre-parse the message for header access benchmarks
     let mut parsed_msg = SipMessage::new_from_str(&invite_msg);
-    parsed_msg.parse().unwrap();
+    parsed_msg.parse_headers().unwrap();
 
     let mut group = c.benchmark_group("header_access");
     
@@ -197,7 +197,7 @@ fn bench_high_volume_parsing(c: &mut Criterion) {
                 for i in 0..size {
                     let msg = &messages[i % messages.len()];
                     let mut sip_msg = SipMessage::new_from_str(black_box(msg));
-                    black_box(sip_msg.parse()).unwrap();
+                    black_box(sip_msg.parse_headers()).unwrap();
                 }
             })
         });
@@ -217,7 +217,7 @@ fn bench_memory_allocation(c: &mut Criterion) {
     group.bench_function("fresh_parse", |b| {
         b.iter(|| {
             let mut sip_msg = SipMessage::new_from_str(black_box(&invite_msg));
-            black_box(sip_msg.parse()).unwrap();
+            black_box(sip_msg.parse_headers()).unwrap();
             // Message is dropped here, measuring allocation/deallocation cost
         })
     });
@@ -228,7 +228,42 @@ fn bench_memory_allocation(c: &mut Criterion) {
         b.iter(|| {
             // Reset and reparse with the same instance
             sip_msg = SipMessage::new_from_str(black_box(&invite_msg));
-            black_box(sip_msg.parse()).unwrap();
+            black_box(sip_msg.parse_headers()).unwrap();
+        })
+    });
+
+    group.finish();
+}
+
+/// Benchmark a header folded over thousands of lines, with and without the fold limit
+fn bench_folded_header_abuse(c: &mut Criterion) {
+    let folds = 4000;
+    let folded = format!(
+        "OPTIONS sip:bob@example.com SIP/2.0\r\nVia: SIP/2.0/UDP pc33.example.com;branch=z9hG4bK1\r\n\
+         Subject: x{}\r\nContent-Length: 0\r\n\r\n",
+        "\r\n x".repeat(folds)
+    );
+    let unbounded = ParserLimits {
+        max_folds_per_header: usize::MAX,
+        max_header_line_length: usize::MAX,
+        max_header_value_length: usize::MAX,
+        ..ParserLimits::default()
+    };
+
+    let mut group = c.benchmark_group("folded_header_abuse");
+    group.throughput(Throughput::Bytes(folded.len() as u64));
+
+    group.bench_function("rejected_at_fold_limit", |b| {
+        b.iter(|| {
+            let mut sip_msg = SipMessage::new_from_str(black_box(&folded));
+            black_box(sip_msg.parse_without_validation()).unwrap_err();
+        })
+    });
+
+    group.bench_function("unbounded_folds", |b| {
+        b.iter(|| {
+            let mut sip_msg = SipMessage::new_from_str_with_limits(black_box(&folded), unbounded.clone());
+            black_box(sip_msg.parse_without_validation()).unwrap();
         })
     });
 
@@ -338,6 +373,7 @@ criterion_group!(
     bench_header_access,
     bench_high_volume_parsing,
     bench_memory_allocation,
+    bench_folded_header_abuse,
     bench_routing_logic
 );
 criterion_main!(benches);
\ No newline at end of file
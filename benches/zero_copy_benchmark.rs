@@ -43,7 +43,7 @@ a=rtpmap:116 telephone-event/8000
     group.bench_function("regular_ssbc", |b| {
         b.iter(|| {
             let mut sip_msg = SipMessage::new_from_str(black_box(&invite_msg));
-            black_box(sip_msg.parse()).unwrap();
+            black_box(sip_msg.parse_headers()).unwrap();
         })
     });
 
@@ -73,7 +73,7 @@ Content-Length: 0
 
     // Pre-parse both message types
     let mut regular_msg = SipMessage::new_from_str(&invite_msg);
-    regular_msg.parse().unwrap();
+    regular_msg.parse_headers().unwrap();
     
     let mut zero_copy_msg = ZeroCopySipMessage::new(&invite_msg);
     zero_copy_msg.parse().unwrap();
@@ -230,7 +230,7 @@ Content-Length: 0
                 for i in 0..size {
                     let msg = &messages[i % messages.len()];
                     let mut sip_msg = SipMessage::new_from_str(black_box(msg));
-                    black_box(sip_msg.parse()).unwrap();
+                    black_box(sip_msg.parse_headers()).unwrap();
                 }
             })
         });
@@ -5,9 +5,66 @@
 
 use crate::error::{SsbcError, SsbcResult};
 use crate::sdp::SessionDescription;
+use std::collections::hash_map::RandomState;
 use std::collections::HashMap;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
 
+/// Entropy behind generated identifiers: tags, Call-IDs and MIME boundaries
+///
+/// [`B2buaManager`] and the message builders use [`OsRandom`] unless given
+/// another source. Tests plug in [`CounterRandom`] so generated identifiers,
+/// and thus whole messages, are reproducible:
+///
+/// ```
+/// use std::sync::Arc;
+/// use ssbc::{B2buaManager, CounterRandom};
+///
+/// let b2bua = B2buaManager::new(100, 3600, 32).with_random_source(Arc::new(CounterRandom::new(7)));
+/// ```
+///
+/// A seeded PRNG plugs in the same way; keep its state behind a `Mutex`, since
+/// the source is shared.
+pub trait RandomSource: Send + Sync {
+    /// Next 32 random bits
+    fn next_u32(&self) -> u32;
+}
+
+/// Default source, keyed by the OS-seeded hash keys of the standard library
+#[derive(Debug, Default, Clone, Copy)]
+pub struct OsRandom;
+
+impl RandomSource for OsRandom {
+    fn next_u32(&self) -> u32 {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+        hasher.finish() as u32
+    }
+}
+
+/// Deterministic source for tests, yielding `seed`, `seed + 1`, ...
+#[derive(Debug, Default)]
+pub struct CounterRandom {
+    next: AtomicU32,
+}
+
+impl CounterRandom {
+    pub fn new(seed: u32) -> Self {
+        Self {
+            next: AtomicU32::new(seed),
+        }
+    }
+}
+
+impl RandomSource for CounterRandom {
+    fn next_u32(&self) -> u32 {
+        self.next.fetch_add(1, Ordering::Relaxed)
+    }
+}
+
 /// Call state in B2BUA
 #[derive(Debug, Clone, PartialEq)]
 pub enum CallState {
@@ -100,6 +157,7 @@ pub struct B2buaManager {
     max_calls: usize,
     call_timeout_seconds: u64,
     _transaction_timeout_seconds: u64,
+    random: Arc<dyn RandomSource>,
 }
 
 impl B2buaManager {
@@ -112,9 +170,16 @@ impl B2buaManager {
             max_calls,
             call_timeout_seconds,
             _transaction_timeout_seconds: transaction_timeout_seconds,
+            random: Arc::new(OsRandom),
         }
     }
 
+    /// Draw generated tags and Call-IDs from `random` instead of [`OsRandom`]
+    pub fn with_random_source(mut self, random: Arc<dyn RandomSource>) -> Self {
+        self.random = random;
+        self
+    }
+
     /// Process incoming INVITE (create new call)
     pub fn handle_invite(&mut self, 
                         call_id: &str, 
@@ -138,7 +203,7 @@ impl B2buaManager {
         // Create incoming call leg
         let dialog = Dialog {
             call_id: call_id.to_string(),
-            local_tag: generate_tag(),
+            local_tag: generate_tag(self.random.as_ref()),
             remote_tag: Some(from_tag.to_string()),
             local_uri: to_uri.to_string(),
             remote_uri: from_uri.to_string(),
@@ -170,7 +235,7 @@ impl B2buaManager {
                                sdp: Option<SessionDescription>) -> SsbcResult<String> {
         
         // Generate new call ID for outgoing leg
-        let outgoing_call_id = generate_call_id();
+        let outgoing_call_id = generate_call_id(self.random.as_ref());
         let now = current_timestamp();
 
         // Get incoming call info
@@ -184,7 +249,7 @@ impl B2buaManager {
         // Create outgoing dialog
         let dialog = Dialog {
             call_id: outgoing_call_id.clone(),
-            local_tag: generate_tag(),
+            local_tag: generate_tag(self.random.as_ref()),
             remote_tag: None, // Will be set when response received
             local_uri: "sip:b2bua@localhost".to_string(), // B2BUA identity
             remote_uri: destination_uri.to_string(),
@@ -496,12 +561,12 @@ fn current_timestamp() -> u64 {
         .as_secs()
 }
 
-fn generate_tag() -> String {
-    format!("tag-{}-{}", current_timestamp(), rand::random::<u32>())
+pub(crate) fn generate_tag(random: &dyn RandomSource) -> String {
+    format!("tag-{:08x}{:08x}", random.next_u32(), random.next_u32())
 }
 
-fn generate_call_id() -> String {
-    format!("call-{}-b2b-{}", current_timestamp(), rand::random::<u32>())
+fn generate_call_id(random: &dyn RandomSource) -> String {
+    format!("call-{:08x}{:08x}-b2b", random.next_u32(), random.next_u32())
 }
 
 fn extract_media_info(sdp: &SessionDescription) -> SsbcResult<(String, u16)> {
@@ -530,22 +595,6 @@ fn extract_media_info(sdp: &SessionDescription) -> SsbcResult<(String, u16)> {
     })
 }
 
-// Add rand dependency for ID generation
-mod rand {
-    use std::collections::hash_map::DefaultHasher;
-    use std::hash::{Hash, Hasher};
-    use std::time::SystemTime;
-
-    pub fn random<T>() -> T 
-    where 
-        T: From<u32>
-    {
-        let mut hasher = DefaultHasher::new();
-        SystemTime::now().hash(&mut hasher);
-        T::from(hasher.finish() as u32)
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -616,4 +665,21 @@ mod tests {
         let result = b2bua.handle_invite("call3", "sip:e@test.com", "sip:f@test.com", "tag3", 1, None);
         assert!(result.is_err());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_counter_random_source() {
+        let new_b2bua = || B2buaManager::new(100, 3600, 32).with_random_source(Arc::new(CounterRandom::new(7)));
+        let mut b2bua = new_b2bua();
+        b2bua.handle_invite("call1", "sip:a@test.com", "sip:b@test.com", "tag1", 1, None).unwrap();
+        let outgoing_id = b2bua.create_outgoing_call("call1", "sip:c@test.com", None).unwrap();
+
+        assert_eq!(b2bua.get_call("call1").unwrap().dialog.local_tag, "tag-0000000700000008");
+        assert_eq!(outgoing_id, "call-000000090000000a-b2b");
+        assert_eq!(b2bua.get_call(&outgoing_id).unwrap().dialog.local_tag, "tag-0000000b0000000c");
+
+        // Same seed, same identifiers
+        let mut replay = new_b2bua();
+        replay.handle_invite("call1", "sip:a@test.com", "sip:b@test.com", "tag1", 1, None).unwrap();
+        assert_eq!(replay.create_outgoing_call("call1", "sip:c@test.com", None).unwrap(), outgoing_id);
+    }
+}
@@ -2,6 +2,11 @@
 //! 
 //! Unified error system that consolidates all error types into a simple, 
 //! easy-to-use interface suitable for high-performance SIP parsing.
+//!
+//! [`SsbcError`] is the crate's only error type and the one every public API
+//! returns. The legacy standalone `ParseError` type is gone; parse failures are
+//! the [`SsbcError::ParseError`] variant, so no conversion between the two is
+//! needed.
 
 use std::fmt;
 use std::error::Error as StdError;
@@ -36,6 +41,12 @@ pub enum SsbcError {
         reason: String,
         context: Option<String>,
     },
+
+    /// Request must not be forwarded (proxy checks of RFC 3261 Section 16.3)
+    RoutingError {
+        kind: RoutingErrorKind,
+        context: Option<String>,
+    },
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -46,6 +57,24 @@ pub enum ResourceType {
     Connections,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoutingErrorKind {
+    /// Max-Forwards reached zero
+    TooManyHops,
+    /// The request already passed through this element with the same branch
+    LoopDetected,
+}
+
+impl RoutingErrorKind {
+    /// Status code to reject the request with
+    pub fn status_code(&self) -> u16 {
+        match self {
+            RoutingErrorKind::TooManyHops => 483,
+            RoutingErrorKind::LoopDetected => 482,
+        }
+    }
+}
+
 impl fmt::Display for SsbcError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -73,6 +102,13 @@ impl fmt::Display for SsbcError {
                 }
                 Ok(())
             },
+            SsbcError::RoutingError { kind, context } => {
+                write!(f, "Routing error: {:?} ({})", kind, kind.status_code())?;
+                if let Some(ctx) = context {
+                    write!(f, " ({})", ctx)?;
+                }
+                Ok(())
+            },
         }
     }
 }
@@ -116,6 +152,11 @@ impl SsbcError {
         }
     }
 
+    /// Create a routing error
+    pub fn routing_error(kind: RoutingErrorKind, context: Option<String>) -> Self {
+        SsbcError::RoutingError { kind, context }
+    }
+
     /// Check if this error is recoverable
     pub fn is_recoverable(&self) -> bool {
         match self {
@@ -123,6 +164,7 @@ impl SsbcError {
             SsbcError::TransportError { recoverable, .. } => *recoverable,
             SsbcError::ResourceError { .. } => true,
             SsbcError::StateError { .. } => false,
+            SsbcError::RoutingError { .. } => true,
         }
     }
 
@@ -133,6 +175,7 @@ impl SsbcError {
             SsbcError::TransportError { .. } => "transport",
             SsbcError::ResourceError { .. } => "resource",
             SsbcError::StateError { .. } => "state",
+            SsbcError::RoutingError { .. } => "routing",
         }
     }
 }
@@ -4,7 +4,62 @@
 //! extraction of header values and parameters with support for RFC 3261
 //! compact forms.
 
-use crate::main_impl::SipMessage;
+use crate::main_impl::{header_utils, SipMessage};
+
+/// Compact header forms (RFC 3261 Section 7.3.3 and extensions) and their
+/// full lowercase names. Single source of truth for the
+/// parser, `header_utils` and [`expand_compact_header_name`].
+pub(crate) const COMPACT_HEADER_FORMS: &[(u8, &str)] = &[
+    (b'v', "via"),
+    (b'i', "call-id"),
+    (b'm', "contact"),              // RFC 3261 (not Max-Forwards, which has no compact form)
+    (b'e', "content-encoding"),
+    (b'l', "content-length"),
+    (b'c', "content-type"),
+    (b'f', "from"),
+    (b't', "to"),
+    (b'r', "refer-to"),
+    (b'b', "referred-by"),
+    (b'k', "supported"),
+    (b'o', "event"),                // o -> event (as per RFC 3265)
+    (b'u', "allow-events"),         // u -> allow-events (as per RFC 3265)
+    (b'a', "accept-contact"),       // RFC 3841
+    (b'j', "reject-contact"),       // RFC 3841
+    (b'd', "request-disposition"),  // RFC 3841
+    (b'x', "session-expires"),      // RFC 4028
+    (b'y', "identity"),             // RFC 4474
+    (b'n', "identity-info"),        // RFC 4474
+    (b'h', "date"),                 // deprecated but documented
+    (b's', "subject"),              // deprecated but documented
+];
+
+/// Expand a compact header name to its canonical long form
+///
+/// Single-letter compact forms map case-insensitively to their lowercase full
+/// name (`"m"` and `"M"` become `"contact"`); any other name is returned as is.
+///
+/// # Examples
+/// ```
+/// use ssbc::headers::expand_compact_header_name;
+///
+/// assert_eq!(expand_compact_header_name("f"), "from");
+/// assert_eq!(expand_compact_header_name("I"), "call-id");
+/// assert_eq!(expand_compact_header_name("Max-Forwards"), "Max-Forwards");
+/// ```
+pub fn expand_compact_header_name(name: &str) -> &str {
+    compact_header_full_name(name).unwrap_or(name)
+}
+
+/// Full lowercase header name for a single-letter compact form, case-insensitively
+pub(crate) fn compact_header_full_name(name: &str) -> Option<&'static str> {
+    match name.as_bytes() {
+        [letter] => COMPACT_HEADER_FORMS
+            .iter()
+            .find(|(compact, _)| *compact == letter.to_ascii_lowercase())
+            .map(|(_, full)| *full),
+        _ => None,
+    }
+}
 
 /// Extract header value by name, supporting both long and compact forms
 /// 
@@ -24,40 +79,7 @@ use crate::main_impl::SipMessage;
 /// assert!(from_value.is_some());
 /// ```
 pub fn extract_header_value(sip_message: &SipMessage, header_name: &str) -> Option<String> {
-    let raw_message = sip_message.raw_message();
-    let header_lower = header_name.to_lowercase();
-    
-    // RFC 3261 compact form mapping
-    let compact_form = match header_lower.as_str() {
-        "from" => Some("f"),
-        "to" => Some("t"), 
-        "via" => Some("v"),
-        "contact" => Some("m"),
-        "call-id" => Some("i"),
-        "content-length" => Some("l"),
-        "content-type" => Some("c"),
-        "subject" => Some("s"),
-        _ => None,
-    };
-    
-    let prefixes = if let Some(compact) = compact_form {
-        vec![format!("{}:", header_lower), format!("{}:", compact)]
-    } else {
-        vec![format!("{}:", header_lower)]
-    };
-    
-    for line in raw_message.lines() {
-        let line_lower = line.to_lowercase();
-        
-        for prefix in &prefixes {
-            if line_lower.starts_with(prefix) {
-                if let Some(colon_pos) = line.find(':') {
-                    return Some(line[colon_pos + 1..].trim().to_string());
-                }
-            }
-        }
-    }
-    None
+    header_utils::extract_header_value(sip_message, header_name)
 }
 
 /// Extract parameter value from a header value string
@@ -106,41 +128,5 @@ pub fn extract_header_parameter(header_value: &str, param_name: &str) -> Option<
 /// Returns a vector of all header values that match the given name,
 /// useful for headers that can appear multiple times (like Via).
 pub fn get_header_values(sip_message: &SipMessage, header_name: &str) -> Vec<String> {
-    let raw_message = sip_message.raw_message();
-    let header_lower = header_name.to_lowercase();
-    let mut values = Vec::new();
-    
-    // RFC 3261 compact form mapping  
-    let compact_form = match header_lower.as_str() {
-        "from" => Some("f"),
-        "to" => Some("t"),
-        "via" => Some("v"), 
-        "contact" => Some("m"),
-        "call-id" => Some("i"),
-        "content-length" => Some("l"),
-        "content-type" => Some("c"),
-        "subject" => Some("s"),
-        _ => None,
-    };
-    
-    let prefixes = if let Some(compact) = compact_form {
-        vec![format!("{}:", header_lower), format!("{}:", compact)]
-    } else {
-        vec![format!("{}:", header_lower)]
-    };
-    
-    for line in raw_message.lines() {
-        let line_lower = line.to_lowercase();
-        
-        for prefix in &prefixes {
-            if line_lower.starts_with(prefix) {
-                if let Some(colon_pos) = line.find(':') {
-                    values.push(line[colon_pos + 1..].trim().to_string());
-                    break; // Found match for this line, move to next line
-                }
-            }
-        }
-    }
-    
-    values
-}
\ No newline at end of file
+    header_utils::get_header_values(sip_message, header_name)
+}
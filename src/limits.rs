@@ -1,5 +1,8 @@
 // Security limits for SIP parsing to prevent DoS attacks
 
+use crate::validation::HeaderValidator;
+use std::sync::Arc;
+
 /// Maximum allowed size for a complete SIP message
 pub const MAX_MESSAGE_SIZE: usize = 64 * 1024 * 1024; // 64MB
 
@@ -30,6 +33,18 @@ pub const MAX_START_LINE_LENGTH: usize = 8192; // 8KB
 /// Maximum allowed body size
 pub const MAX_BODY_SIZE: usize = 16 * 1024 * 1024; // 16MB
 
+/// Maximum allowed size of the whole header block (all header lines together)
+pub const MAX_HEADERS_BLOCK_SIZE: usize = 256 * 1024; // 256KB
+
+/// Maximum allowed continuation lines in one folded header
+///
+/// Generous so lenient parsing accepts any real-world folding and only stops
+/// pathological inputs; `strict()` and `carrier_grade()` cap it far lower.
+pub const MAX_FOLDS_PER_HEADER: usize = 1024;
+
+/// Maximum allowed nesting of multipart bodies
+pub const MAX_MULTIPART_DEPTH: usize = 8;
+
 /// Configuration for parser limits
 #[derive(Debug, Clone)]
 pub struct ParserLimits {
@@ -43,6 +58,32 @@ pub struct ParserLimits {
     pub max_header_params: usize,
     pub max_start_line_length: usize,
     pub max_body_size: usize,
+    pub max_headers_block_size: usize,
+    /// Multipart bodies nested deeper than this are rejected; the message
+    /// body itself is depth 1
+    pub max_multipart_depth: usize,
+    /// Folded headers with more continuation lines than this are rejected
+    pub max_folds_per_header: usize,
+    /// Reject CRLFs preceding the start line instead of skipping them
+    pub strict_start_line: bool,
+    /// Treat folded (continuation) header lines as an error
+    pub reject_folded_headers: bool,
+    /// The message arrived as a single datagram (UDP), so nothing may follow
+    /// the body declared by Content-Length
+    pub datagram: bool,
+    /// Reject control characters other than CR, LF and HTAB before the body
+    pub reject_control_chars: bool,
+    /// Reject whitespace between a header name and its colon (`From : ...`)
+    /// instead of trimming it
+    pub reject_space_before_colon: bool,
+    /// Reject a `%` not followed by two hex digits in URI hosts, parameters
+    /// and headers; user parts are always checked
+    pub reject_invalid_uri_escapes: bool,
+    /// Extension methods accepted in requests; `None` accepts any method token.
+    /// Methods known to [`crate::Method`] are always accepted.
+    pub allowed_unknown_methods: Option<Vec<String>>,
+    /// Optional policy hook invoked for every parsed header
+    pub header_validator: Option<Arc<dyn HeaderValidator>>,
 }
 
 impl Default for ParserLimits {
@@ -58,6 +99,17 @@ impl Default for ParserLimits {
             max_header_params: MAX_HEADER_PARAMS,
             max_start_line_length: MAX_START_LINE_LENGTH,
             max_body_size: MAX_BODY_SIZE,
+            max_headers_block_size: MAX_HEADERS_BLOCK_SIZE,
+            max_multipart_depth: MAX_MULTIPART_DEPTH,
+            max_folds_per_header: MAX_FOLDS_PER_HEADER,
+            strict_start_line: false,
+            reject_folded_headers: false,
+            datagram: false,
+            reject_control_chars: false,
+            reject_space_before_colon: false,
+            reject_invalid_uri_escapes: false,
+            allowed_unknown_methods: None,
+            header_validator: None,
         }
     }
 }
@@ -76,6 +128,17 @@ impl ParserLimits {
             max_header_params: 16,
             max_start_line_length: 2048,          // 2KB
             max_body_size: 512 * 1024,            // 512KB
+            max_headers_block_size: 32 * 1024,    // 32KB
+            max_multipart_depth: 3,
+            max_folds_per_header: 4,
+            strict_start_line: true,
+            reject_folded_headers: true,
+            datagram: false,
+            reject_control_chars: true,
+            reject_space_before_colon: true,
+            reject_invalid_uri_escapes: true,
+            allowed_unknown_methods: None,
+            header_validator: None,
         }
     }
     
@@ -92,6 +155,17 @@ impl ParserLimits {
             max_header_params: 24,
             max_start_line_length: 4096,          // 4KB
             max_body_size: 5 * 1024 * 1024,       // 5MB
+            max_headers_block_size: 128 * 1024,   // 128KB
+            max_multipart_depth: 5,
+            max_folds_per_header: 8,
+            strict_start_line: false,
+            reject_folded_headers: false,
+            datagram: false,
+            reject_control_chars: false,
+            reject_space_before_colon: false,
+            reject_invalid_uri_escapes: false,
+            allowed_unknown_methods: None,
+            header_validator: None,
         }
     }
 }
\ No newline at end of file
@@ -4,14 +4,70 @@
 
 // benchmark module is now at crate level
 
+use crate::b2bua::RandomSource;
 use crate::error::{SsbcError, SsbcResult};
+use crate::headers::{compact_header_full_name, COMPACT_HEADER_FORMS};
 use crate::limits::*;
 use crate::types::*;
 use crate::validation;
 use crate::{
     check_duplicate_and_set, validate_required_option_header, validate_required_vec_header,
 };
+use std::borrow::Cow;
+use std::io::{BufRead, Read};
 use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Headers defined by RFC 3261 Section 20 and the extensions this crate handles,
+/// in addition to the full names listed in `COMPACT_HEADER_FORMS`
+const STANDARD_HEADER_NAMES: &[&str] = &[
+    "accept",
+    "accept-encoding",
+    "accept-language",
+    "alert-info",
+    "allow",
+    "authentication-info",
+    "authorization",
+    "call-info",
+    "content-disposition",
+    "content-language",
+    "cseq",
+    "error-info",
+    "expires",
+    "feature-caps",
+    "in-reply-to",
+    "join",
+    "max-forwards",
+    "mime-version",
+    "min-expires",
+    "min-se",
+    "organization",
+    "priority",
+    "proxy-authenticate",
+    "proxy-authorization",
+    "proxy-require",
+    "rack",
+    "record-route",
+    "recv-info",
+    "refer-sub",
+    "replaces",
+    "reply-to",
+    "require",
+    "retry-after",
+    "route",
+    "rseq",
+    "server",
+    "subscription-state",
+    "target-dialog",
+    "timestamp",
+    "unsupported",
+    "user-agent",
+    "warning",
+    "www-authenticate",
+];
+
+/// Header parameters as (name, unquoted value), in order
+type QuotedParams<'a> = Vec<(&'a str, Option<String>)>;
 
 /// Macro to create a clone of a SipMessage for parsing
 /// This helps avoid borrowing issues when working with headers
@@ -206,10 +262,14 @@ macro_rules! find_headers_by_name {
 }
 
 /// Represents a parsed SIP Message
+///
+/// The message text is immutable and shared, so `clone()` copies only the
+/// parsed header ranges, never the text itself. B2BUA legs that clone the
+/// message before modifying it stay cheap.
 #[derive(Debug, Clone)]
 pub struct SipMessage {
-    /// Original message text
-    raw_message: String,
+    /// Original message text, shared between clones
+    raw_message: Arc<str>,
 
     // Booleans and small values first to optimize memory layout
     /// Whether the message is a request (vs response)
@@ -230,6 +290,12 @@ pub struct SipMessage {
     /// Message body if present
     body: Option<TextRange>,
 
+    /// Bytes following the body declared by Content-Length
+    trailing: Option<TextRange>,
+
+    /// Body that isn't valid UTF-8, kept out of `raw_message`
+    binary_body: Option<Vec<u8>>,
+
     // Required headers with dedicated fields (all Option types grouped together)
     /// To header
     to: Option<HeaderValue>,
@@ -252,13 +318,15 @@ pub struct SipMessage {
     pub refer_to: Option<HeaderValue>,
 
     // Vectors last (as they're larger and have varying sizes)
-    /// Contact headers
+    /// Contact headers, parsed lazily; each line is also in `headers`
     contact_headers: Vec<HeaderValue>,
 
-    /// Via headers
+    /// Via headers, parsed lazily; each line is also in `headers`
     via_headers: Vec<HeaderValue>,
 
-    /// All other headers
+    /// Every header line not held in a dedicated `Option` field. This is the
+    /// source of truth for enumerating and counting; `via_headers` and
+    /// `contact_headers` are caches and must not be added on top.
     headers: Vec<(TextRange, HeaderValue)>,
 }
 
@@ -268,26 +336,154 @@ impl SipMessage {
         Self::with_limits(message, ParserLimits::default())
     }
 
-    /// Parse a SIP message from bytes (static method for backward compatibility)
+    /// Parse a SIP message from raw bytes, as received from the wire
+    ///
+    /// The bytes must be valid UTF-8; no lossy conversion is attempted. An
+    /// invalid sequence is reported with the line and column where it starts.
+    /// Headers are parsed and validated before the message is returned.
     pub fn parse(data: &[u8]) -> Result<Self, SsbcError> {
-        let message_str = std::str::from_utf8(data)
-            .map_err(|e| SsbcError::parse_error(&format!("Invalid UTF-8: {}", e), None, None))?;
+        let message_str = std::str::from_utf8(data).map_err(|e| {
+            let valid = &data[..e.valid_up_to()];
+            let line = valid.iter().filter(|&&b| b == b'\n').count() + 1;
+            let column = valid.len() - valid.iter().rposition(|&b| b == b'\n').map_or(0, |pos| pos + 1);
+            SsbcError::parse_error(
+                format!("Invalid UTF-8 at byte {}", e.valid_up_to()),
+                Some((line, column)),
+                Some(e.to_string()),
+            )
+        })?;
 
         let mut msg = Self::new_from_str(message_str);
         msg.parse_headers()?;
         Ok(msg)
     }
 
+    /// Parse a SIP message whose body may be binary, such as ISUP (RFC 3204)
+    ///
+    /// The start line and headers must be UTF-8 as with [`SipMessage::parse`].
+    /// A body that isn't valid UTF-8 is kept as bytes, bounded by Content-Length
+    /// (any bytes past it are dropped), and is only available through
+    /// [`SipMessage::body_bytes`]; `body()` and `raw_message()` then cover the
+    /// text part alone. Any other message parses exactly as with `parse`.
+    pub fn parse_bytes(data: &[u8]) -> Result<Self, SsbcError> {
+        let head_end = data.windows(4).position(|window| window == b"\r\n\r\n");
+        let (Some(head_end), Err(_)) = (head_end, std::str::from_utf8(data)) else {
+            return Self::parse(data);
+        };
+        let (head, body) = data.split_at(head_end + 4);
+        if std::str::from_utf8(head).is_err() {
+            return Self::parse(data);
+        }
+
+        let mut msg = Self::parse(head)?;
+        let body_len = msg.declared_content_length().map_or(body.len(), |length| length.min(body.len()));
+        if body_len > msg.limits().max_body_size {
+            return Err(SsbcError::parse_error(
+                format!("Body size {} exceeds maximum {}", body_len, msg.limits().max_body_size),
+                None,
+                Some("Body too large".to_string()),
+            ));
+        }
+        msg.binary_body = Some(body[..body_len].to_vec());
+        Ok(msg)
+    }
+
+    /// Read and parse the next message from a buffered reader
+    ///
+    /// Frames the message as on a stream transport: CRLF keep-alives are
+    /// skipped, the header block runs to the first empty line and the body is
+    /// exactly Content-Length bytes (none without the header). Returns
+    /// `Ok(None)` at end of input; end of input inside a message is an error.
+    /// Every read is bounded by `limits` before it is buffered, so a peer that
+    /// never ends a line or declares a huge body can't grow memory without
+    /// bound; the returned message keeps `limits`.
+    pub fn read_from(mut reader: impl BufRead, limits: &ParserLimits) -> Result<Option<Self>, SsbcError> {
+        let io_error = |e: std::io::Error| SsbcError::transport_error("reader", e.to_string(), false);
+        let truncated = || SsbcError::parse_error("Message truncated at end of input", None, None);
+        // Longest line including its CRLF
+        let max_line = limits.max_header_line_length.max(limits.max_start_line_length) + 2;
+
+        let mut data = Vec::new();
+        loop {
+            let line_start = data.len();
+            let read = (&mut reader)
+                .take(max_line as u64)
+                .read_until(b'\n', &mut data)
+                .map_err(io_error)?;
+            if read == 0 {
+                return if data.is_empty() { Ok(None) } else { Err(truncated()) };
+            }
+            if read == max_line && data.last() != Some(&b'\n') {
+                return Err(SsbcError::ParseError {
+                    message: format!("Line exceeds maximum length {}", max_line - 2),
+                    position: None,
+                    context: Some("DoS protection".to_string()),
+                });
+            }
+            let line = &data[line_start..];
+            if line == b"\r\n" || line == b"\n" {
+                if line_start == 0 {
+                    // Keep-alive before the start line
+                    data.clear();
+                    continue;
+                }
+                break;
+            }
+            if data.len() > limits.max_headers_block_size {
+                return Err(SsbcError::parse_error(
+                    format!("Header block exceeds maximum {}", limits.max_headers_block_size),
+                    None,
+                    None,
+                ));
+            }
+        }
+
+        let head = std::str::from_utf8(&data)
+            .map_err(|e| SsbcError::parse_error("Invalid UTF-8 in header block", None, Some(e.to_string())))?;
+        let mut head = Self::new_from_str_with_limits(head, limits.clone());
+        head.parse_without_validation()?;
+        let body_len = head.declared_content_length().unwrap_or(0);
+        if body_len > limits.max_body_size {
+            return Err(SsbcError::parse_error(
+                format!("Body size {} exceeds maximum {}", body_len, limits.max_body_size),
+                None,
+                None,
+            ));
+        }
+
+        let head_len = data.len();
+        if head_len + body_len > limits.max_message_size {
+            return Err(SsbcError::parse_error(
+                format!("Message size {} exceeds maximum {}", head_len + body_len, limits.max_message_size),
+                None,
+                Some("Message too large".to_string()),
+            ));
+        }
+        data.resize(head_len + body_len, 0);
+        reader.read_exact(&mut data[head_len..]).map_err(|e| match e.kind() {
+            std::io::ErrorKind::UnexpectedEof => truncated(),
+            _ => io_error(e),
+        })?;
+
+        let text = String::from_utf8(data)
+            .map_err(|e| SsbcError::parse_error("Invalid UTF-8 in message", None, Some(e.to_string())))?;
+        let mut message = Self::with_limits(text, limits.clone());
+        message.parse_headers()?;
+        Ok(Some(message))
+    }
+
     /// Create a new SIP message with custom parser limits
     pub fn with_limits(message: String, limits: ParserLimits) -> Self {
         Self {
-            raw_message: message,
+            raw_message: message.into(),
             is_request: false,
             headers_parsed: false,
             contact_has_multiple_entries: false,
             limits,
             start_line: TextRange::new(0, 0),
             body: None,
+            trailing: None,
+            binary_body: None,
             to: None,
             from: None,
             cseq: None,
@@ -354,18 +550,48 @@ impl SipMessage {
         // Cache the message length to avoid multiple calls
         let message_len = self.raw_message.len();
 
+        // NUL and other control bytes in the header section hint at smuggling;
+        // scan for them before any detailed parsing. The body is exempt.
+        if self.limits().reject_control_chars {
+            // CRLFs before the start line must not be taken for the blank line
+            let head = &self.raw_message.as_bytes()[..self.header_block().end];
+            if let Some(offset) = head
+                .iter()
+                .position(|&b| b.is_ascii_control() && !matches!(b, b'\r' | b'\n' | b'\t'))
+            {
+                let line = head[..offset].iter().filter(|&&b| b == b'\n').count() + 1;
+                let column = offset - head[..offset].iter().rposition(|&b| b == b'\n').map_or(0, |pos| pos + 1);
+                return Err(SsbcError::parse_error(
+                    format!("Control character 0x{:02X} at byte {}", head[offset], offset),
+                    Some((line, column)),
+                    Some("Control characters are not allowed in headers".to_string()),
+                ));
+            }
+        }
+
+        // RFC 3261 Section 7.5: CRLFs before the start line (e.g. keep-alives on
+        // stream transports) are ignored unless strict parsing is requested
+        let start_line_start = message_len - self.raw_message.trim_start_matches("\r\n").len();
+        if start_line_start > 0 && self.limits().strict_start_line {
+            return Err(SsbcError::ParseError {
+                message: "Unexpected CRLF before start line".to_string(),
+                position: Some((1, 0)),
+                context: Some("Strict start line parsing".to_string()),
+            });
+        }
+
         // Find the end of the start line
-        let start_line_end =
-            self.raw_message
-                .find("\r\n")
-                .ok_or_else(|| SsbcError::ParseError {
-                    message: "No CRLF after start line".to_string(),
-                    position: Some((1, 0)),
-                    context: None,
-                })?;
+        let start_line_end = self.raw_message[start_line_start..]
+            .find("\r\n")
+            .map(|end| start_line_start + end)
+            .ok_or_else(|| SsbcError::ParseError {
+                message: "No CRLF after start line".to_string(),
+                position: Some((1, 0)),
+                context: None,
+            })?;
 
         // Set the start line range
-        self.start_line = TextRange::from_usize(0, start_line_end);
+        self.start_line = TextRange::from_usize(start_line_start, start_line_end);
 
         // Check start line length limit
         if self.start_line.len() > self.limits().max_start_line_length {
@@ -381,7 +607,20 @@ impl SipMessage {
         }
 
         // Determine if it's a request or response
-        self.is_request = !self.raw_message.starts_with("SIP/");
+        self.is_request = !self.start_line().starts_with("SIP/");
+
+        // Extension methods must be whitelisted when a policy is configured
+        if let (Some(allowed), Some(Method::UNKNOWN(method))) =
+            (&self.limits().allowed_unknown_methods, self.request_method())
+        {
+            if !allowed.contains(&method) {
+                return Err(SsbcError::ParseError {
+                    message: format!("Method {} is not allowed", method),
+                    position: Some((1, 0)),
+                    context: Some("Unknown method rejected by policy".to_string()),
+                });
+            }
+        }
 
         // Find the end of headers (double CRLF)
         let headers_section = &self.raw_message[start_line_end + 2..];
@@ -396,10 +635,25 @@ impl SipMessage {
         let mut pos = start_line_end + 2;
         let mut current_header_start = pos;
         let mut header_count = 0;
+        let mut folds = 0;
 
         // Pre-compute the ending position for the loop condition to avoid repeated calculations
         let headers_end = body_start - 2;
 
+        // Check the combined size of all header lines
+        let headers_block_size = headers_end.saturating_sub(start_line_end);
+        if headers_block_size > self.limits().max_headers_block_size {
+            return Err(SsbcError::ParseError {
+                message: format!(
+                    "Header block size {} exceeds maximum {}",
+                    headers_block_size,
+                    self.limits().max_headers_block_size
+                ),
+                position: None,
+                context: Some("DoS protection".to_string()),
+            });
+        }
+
         while pos < headers_end {
             // Look ahead to see if the next line is a continuation (folded header)
             // Optimize by using a slice of the message for finding the next line end
@@ -412,6 +666,28 @@ impl SipMessage {
                 && (self.raw_message.as_bytes().get(next_line_start) == Some(&b' ')
                     || self.raw_message.as_bytes().get(next_line_start) == Some(&b'\t'))
             {
+                if self.limits().reject_folded_headers {
+                    return Err(SsbcError::ParseError {
+                        message: "Folded header line not allowed".to_string(),
+                        position: Some((0, next_line_start)),
+                        context: Some("Line folding rejected by parser limits".to_string()),
+                    });
+                }
+
+                // Each continuation costs an unfolding pass, so bound them
+                folds += 1;
+                if folds > self.limits().max_folds_per_header {
+                    return Err(SsbcError::ParseError {
+                        message: format!(
+                            "Header with {} continuation lines exceeds maximum {}",
+                            folds,
+                            self.limits().max_folds_per_header
+                        ),
+                        position: Some((0, next_line_start)),
+                        context: Some("DoS protection".to_string()),
+                    });
+                }
+
                 // This is a folded line, continue to next line
                 pos = next_line_start;
                 continue;
@@ -446,11 +722,61 @@ impl SipMessage {
             // Move to next header
             pos = line_end + 2;
             current_header_start = pos;
+            folds = 0;
+        }
+
+        // Content-Length headers that disagree, long or compact form, let two
+        // parsers frame the message differently: a request smuggling vector
+        let mut content_lengths = self.header_value_ranges("content-length").into_iter();
+        if let Some(first) = content_lengths.next() {
+            let first_value = self.get_str(first).trim();
+            for other in content_lengths {
+                let other_value = self.get_str(other).trim();
+                let agree = match (first_value.parse::<usize>(), other_value.parse::<usize>()) {
+                    (Ok(first_length), Ok(other_length)) => first_length == other_length,
+                    _ => false,
+                };
+                if !agree {
+                    return Err(SsbcError::ParseError {
+                        message: format!(
+                            "Conflicting Content-Length values {} and {}",
+                            first_value, other_value
+                        ),
+                        position: Some((0, other.start)),
+                        context: Some("Request smuggling".to_string()),
+                    });
+                }
+            }
+        }
+
+        // Content-Length: 0 followed by more bytes is the next message on a stream
+        // transport, but a malformed datagram on UDP (RFC 3261 Section 18.3)
+        let declared_length = self.declared_content_length();
+        if declared_length == Some(0)
+            && self.limits().datagram
+            && !self.raw_message[body_start..].trim().is_empty()
+        {
+            return Err(SsbcError::ParseError {
+                message: "Content-Length is 0 but the datagram carries a body".to_string(),
+                position: None,
+                context: Some(format!("{} trailing bytes", message_len - body_start)),
+            });
+        }
+
+        // A valid Content-Length bounds the body; anything past it is kept apart
+        // as trailing bytes. A length overrunning the message is left to
+        // validation and the body runs to the end.
+        let body_end = match declared_length {
+            Some(length) if length <= message_len - body_start => body_start + length,
+            _ => message_len,
+        };
+        if body_end < message_len {
+            self.trailing = Some(TextRange::from_usize(body_end, message_len));
         }
 
         // Set body if present
-        if body_start < message_len {
-            let body_range = TextRange::from_usize(body_start, message_len);
+        if body_start < body_end {
+            let body_range = TextRange::from_usize(body_start, body_end);
 
             // Check body size limit
             if body_range.len() > self.limits().max_body_size {
@@ -479,6 +805,13 @@ impl SipMessage {
         Ok(())
     }
 
+    /// Body length declared by a valid Content-Length header (compact `l`)
+    fn declared_content_length(&self) -> Option<usize> {
+        self.header_value_ranges("content-length")
+            .first()
+            .and_then(|range| self.get_str(*range).trim().parse::<usize>().ok())
+    }
+
     /// Validate that all required headers are present
     fn validate_required_headers(&self) -> Result<(), SsbcError> {
         // Per RFC 3261 Section 8.1.1, these headers are required in requests
@@ -536,8 +869,16 @@ impl SipMessage {
                 context: None,
             })?;
 
-        // Get the header name and normalize to lowercase for comparisons
-        let raw_name = &unfolded_line[0..colon_pos];
+        // Get the header name and normalize to lowercase for comparisons.
+        // Some peers send `From : value`; trim that unless the limits forbid it.
+        let raw_name = unfolded_line[0..colon_pos].trim_end_matches([' ', '\t']);
+        if raw_name.len() < colon_pos && self.limits.reject_space_before_colon {
+            return Err(SsbcError::ParseError {
+                message: format!("Whitespace before colon in header '{}'", raw_name),
+                position: Some((0, range.start + raw_name.len())),
+                context: Some("Header name must be followed directly by ':'".to_string()),
+            });
+        }
 
         // Validate header name
         validation::validate_header_name(raw_name)?;
@@ -556,6 +897,11 @@ impl SipMessage {
         // Validate and sanitize header value
         let _validated_value = validation::sanitize_header_value(value_str)?;
 
+        // Apply any site-specific policy
+        if let Some(validator) = &self.limits.header_validator {
+            validator.validate(normalized_name, value_str)?;
+        }
+
         // Create a raw range for the value part in the original message
         // For folded headers, this is approximate but works for our zero-copy approach
         // since we'll normalize whitespace in the getter methods anyway
@@ -573,10 +919,7 @@ impl SipMessage {
         }
 
         let value_range = TextRange::from_usize(value_start, range_end);
-        let name_range = TextRange::from_usize(
-            range.start as usize,
-            (range.start as usize) + original_colon_pos,
-        );
+        let name_range = TextRange::from_usize(range.start as usize, (range.start as usize) + raw_name.len());
 
         // Store the header in the appropriate field, checking for duplicates of required single-occurrence headers
         match normalized_name {
@@ -650,30 +993,71 @@ impl SipMessage {
 
     /// Expand compact header form to full form if necessary
     fn expand_compact_header<'b>(&self, name: &'b str) -> &'b str {
-        match name {
-            "v" => "via",
-            "i" => "call-id",
-            "m" => "max-forwards",
-            "e" => "content-encoding",
-            "l" => "content-length",
-            "c" => "content-type",
-            "f" => "from",
-            "t" => "to",
-            "r" => "refer-to",
-            "b" => "referred-by",
-            "k" => "supported",
-            "o" => "event",               // o -> event (as per RFC 3265)
-            "u" => "allow-events",        // u -> allow-events (as per RFC 3265)
-            "a" => "accept-contact",      // RFC 3841
-            "j" => "reject-contact",      // RFC 3841
-            "d" => "request-disposition", // RFC 3841
-            "x" => "session-expires",     // RFC 4028
-            "y" => "identity",            // RFC 4474
-            "n" => "identity-info",       // RFC 4474
-            "h" => "date",                // deprecated but documented
-            "s" => "subject",             // deprecated but documented
-            _ => name,                    // Not a compact form
+        compact_header_full_name(name).unwrap_or(name)
+    }
+
+    /// Report which headers have been parsed beyond their raw ranges so far
+    pub fn lazy_parse_state(&self) -> LazyState {
+        let is_parsed = |value: &HeaderValue| !matches!(value, HeaderValue::Raw(_));
+        LazyState {
+            headers_parsed: self.headers_parsed,
+            to: self.to.as_ref().is_some_and(is_parsed),
+            from: self.from.as_ref().is_some_and(is_parsed),
+            via_parsed: self.via_headers.iter().filter(|value| is_parsed(value)).count(),
+            via_total: self.via_headers.len(),
+            contact_parsed: self.contact_headers.iter().filter(|value| is_parsed(value)).count(),
+            contact_total: self.contact_headers.len(),
+        }
+    }
+
+    /// Replace the body and keep Content-Length consistent with it
+    ///
+    /// Rewrites the backing string with the new body, updating the existing
+    /// Content-Length header (long or compact form) or appending one. All parsed
+    /// state is discarded, so call [`SipMessage::parse_headers`] again before
+    /// using the accessors.
+    pub fn set_body(&mut self, new_body: &str) {
+        let head = self.header_block().as_str(&self.raw_message);
+
+        let content_length = format!("Content-Length: {}", new_body.len());
+        let mut lines: Vec<&str> = Vec::new();
+        let mut content_length_written = false;
+        for (index, line) in head.split("\r\n").enumerate() {
+            let is_content_length = index > 0
+                && line.split_once(':').is_some_and(|(name, _)| {
+                    let name = name.trim();
+                    name.eq_ignore_ascii_case("content-length") || name.eq_ignore_ascii_case("l")
+                });
+            if !is_content_length {
+                lines.push(line);
+            } else if !content_length_written {
+                // Duplicates are dropped, only the first one is rewritten
+                lines.push(&content_length);
+                content_length_written = true;
+            }
         }
+        if !content_length_written {
+            lines.push(&content_length);
+        }
+
+        let mut message = lines.join("\r\n");
+        message.push_str("\r\n\r\n");
+        message.push_str(new_body);
+
+        let limits = self.limits.clone();
+        *self = Self::with_limits(message, limits);
+    }
+
+    /// Locate the start line and header lines, without the CRLFs allowed
+    /// before the start line or the blank line ending the headers
+    pub(crate) fn header_block(&self) -> TextRange {
+        let message_len = self.raw_message.len();
+        let start = message_len - self.raw_message.trim_start_matches("\r\n").len();
+        let end = match self.raw_message[start..].find("\r\n\r\n") {
+            Some(pos) => start + pos,
+            None => self.raw_message.trim_end_matches("\r\n").len().max(start),
+        };
+        TextRange::from_usize(start, end)
     }
 
     /// Access the raw message text
@@ -686,16 +1070,306 @@ impl SipMessage {
         self.start_line.as_str(&self.raw_message)
     }
 
+    /// Get the request line as its method, Request-URI and version
+    ///
+    /// Unlike [`SipMessage::request_method`], malformed lines are errors: there
+    /// must be exactly three tokens, a valid method and a SIP/2.0 version.
+    pub fn request_line(&self) -> Result<RequestLine<'_>, SsbcError> {
+        if !self.is_request {
+            return Err(SsbcError::parse_error("Not a request message", None, None));
+        }
+
+        let line = self.start_line();
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let (method_str, uri_str, version) = match tokens.as_slice() {
+            [method, uri, version] => (*method, *uri, *version),
+            _ => {
+                return Err(SsbcError::parse_error(
+                    format!("Request line must have 3 tokens, found {}", tokens.len()),
+                    Some((1, 0)),
+                    Some(line.to_string()),
+                ))
+            }
+        };
+
+        validation::validate_method(method_str)?;
+        validation::validate_sip_version(version)?;
+
+        let method = method_str
+            .parse::<Method>()
+            .unwrap_or_else(|_| Method::UNKNOWN(method_str.to_string()));
+        let uri_start = self.start_line.start + (uri_str.as_ptr() as usize - line.as_ptr() as usize);
+        let uri = self.parse_uri(TextRange::from_usize(uri_start, uri_start + uri_str.len()))?;
+
+        Ok(RequestLine {
+            method,
+            uri,
+            version,
+        })
+    }
+
+    /// Get the status line of a response as its version, code and reason
+    ///
+    /// Requires a SIP/2.0 version and a three-digit code in 100-699, each
+    /// followed by SP; the reason phrase is everything after the code and may
+    /// be empty (RFC 3261 Section 25.1).
+    pub fn status_line(&self) -> Result<StatusLine<'_>, SsbcError> {
+        if self.is_request {
+            return Err(SsbcError::parse_error("Not a response message", None, None));
+        }
+
+        let line = self.start_line();
+        let mut parts = line.splitn(3, ' ');
+        let (version, code_str, reason) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(version), Some(code), Some(reason)) => (version, code, reason.trim()),
+            _ => {
+                return Err(SsbcError::parse_error(
+                    "Status line must have version, code and reason",
+                    Some((1, 0)),
+                    Some(line.to_string()),
+                ))
+            }
+        };
+
+        validation::validate_sip_version(version)?;
+        if code_str.len() != 3 || !code_str.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(SsbcError::parse_error(
+                format!("Status code must be three digits: {}", code_str),
+                Some((1, version.len() + 1)),
+                Some(line.to_string()),
+            ));
+        }
+        let code: u16 = code_str.parse().unwrap_or(0);
+        validation::validate_status_code(code)?;
+
+        Ok(StatusLine {
+            version,
+            code,
+            reason,
+        })
+    }
+
     /// Check if the message is a request
     pub fn is_request(&self) -> bool {
         self.is_request
     }
 
     /// Get the body text if present
+    ///
+    /// `None` for a binary body, which only [`SipMessage::body_bytes`] returns.
     pub fn body(&self) -> Option<&str> {
         self.body.map(|range| range.as_str(&self.raw_message))
     }
 
+    /// Get the body as bytes, including a binary body kept by [`SipMessage::parse_bytes`]
+    pub fn body_bytes(&self) -> Option<&[u8]> {
+        match &self.binary_body {
+            Some(body) => Some(body),
+            None => self.body().map(str::as_bytes),
+        }
+    }
+
+    /// Bytes following the body declared by Content-Length, if any
+    ///
+    /// On a stream transport this is the start of the next pipelined message;
+    /// over UDP it is to be discarded (RFC 3261 Section 18.3). Always `None`
+    /// without a valid Content-Length, since the body then runs to the end.
+    pub fn trailing_bytes(&self) -> Option<&str> {
+        self.trailing.map(|range| range.as_str(&self.raw_message))
+    }
+
+    /// Split a multipart body into its parts (RFC 2046 Section 5.1)
+    ///
+    /// Nested multipart parts are split as well, up to the parser limits'
+    /// `max_multipart_depth`; deeper nesting is rejected. Returns no parts when
+    /// the body isn't multipart. A missing boundary parameter, or a body
+    /// without its delimiters, is an error.
+    pub fn body_parts(&self) -> Result<Vec<BodyPart<'_>>, SsbcError> {
+        let content_type = self.header_value_ranges("content-type").first().map(|range| self.get_str(*range));
+        match (content_type, self.body()) {
+            (Some(content_type), Some(body)) if Self::is_multipart(content_type) => {
+                self.split_multipart(body, content_type, 1)
+            }
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    fn is_multipart(content_type: &str) -> bool {
+        content_type.trim_start().get(..10).is_some_and(|media| media.eq_ignore_ascii_case("multipart/"))
+    }
+
+    fn split_multipart<'a>(
+        &self,
+        body: &'a str,
+        content_type: &str,
+        depth: usize,
+    ) -> Result<Vec<BodyPart<'a>>, SsbcError> {
+        if depth > self.limits().max_multipart_depth {
+            return Err(SsbcError::parse_error(
+                format!("Multipart nesting exceeds maximum {}", self.limits().max_multipart_depth),
+                None,
+                Some("DoS protection".to_string()),
+            ));
+        }
+
+        let params = content_type.split_once(';').map_or("", |(_, params)| params);
+        let boundary = Self::quoted_params(params)?
+            .into_iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("boundary"))
+            .and_then(|(_, value)| value)
+            .ok_or_else(|| {
+                SsbcError::parse_error("Multipart body without boundary", None, Some(content_type.to_string()))
+            })?;
+        let missing_delimiter =
+            || SsbcError::parse_error("Multipart delimiter not found", None, Some(boundary.clone()));
+
+        // The CRLF before a delimiter belongs to it; the first may open the body
+        let delimiter = format!("\r\n--{}", boundary);
+        let mut rest = match body.strip_prefix(&delimiter[2..]) {
+            Some(rest) => rest,
+            None => {
+                let start = body.find(&delimiter).ok_or_else(missing_delimiter)?;
+                &body[start + delimiter.len()..]
+            }
+        };
+
+        let mut parts = Vec::new();
+        while !rest.starts_with("--") {
+            // Skip transport padding after the delimiter
+            let line_end = rest.find("\r\n").ok_or_else(missing_delimiter)?;
+            rest = &rest[line_end + 2..];
+            let end = rest.find(&delimiter).ok_or_else(missing_delimiter)?;
+            let part = &rest[..end];
+            rest = &rest[end + delimiter.len()..];
+
+            let (head, part_body) = match part.strip_prefix("\r\n") {
+                Some(part_body) => ("", part_body),
+                None => part.split_once("\r\n\r\n").unwrap_or((part, "")),
+            };
+            let mut part = BodyPart {
+                headers: head
+                    .split("\r\n")
+                    .filter_map(|line| line.split_once(':'))
+                    .map(|(name, value)| (name.trim(), value.trim()))
+                    .collect(),
+                body: part_body,
+                parts: Vec::new(),
+            };
+            if let Some(part_type) = part.content_type().filter(|part_type| Self::is_multipart(part_type)) {
+                part.parts = self.split_multipart(part_body, part_type, depth + 1)?;
+            }
+            parts.push(part);
+        }
+        Ok(parts)
+    }
+
+    /// Classify the SDP body as an offer or answer (RFC 3264, RFC 3261 Section 13.2.1)
+    ///
+    /// INVITE and UPDATE requests carry offers; ACK and PRACK bodies answer an
+    /// offer made in the preceding response. Provisional and 2xx responses to
+    /// INVITE, UPDATE and PRACK are treated as answers, so a 2xx to an INVITE
+    /// sent without SDP (delayed offer) must be reclassified by the caller that
+    /// tracks the exchange. Returns `None` when there is no SDP body or the
+    /// message takes no part in offer/answer.
+    pub fn sdp_role(&mut self) -> Result<Option<SdpRole>, SsbcError> {
+        self.parse_without_validation()?;
+        if !self.has_sdp_body() {
+            return Ok(None);
+        }
+
+        if self.is_request {
+            return Ok(match self.request_method() {
+                Some(Method::INVITE | Method::UPDATE) => Some(SdpRole::Offer),
+                Some(Method::ACK | Method::PRACK) => Some(SdpRole::Answer),
+                _ => None,
+            });
+        }
+
+        let code = self.status_line()?.code;
+        Ok(match self.cseq_method()? {
+            Some(Method::INVITE) if (101..300).contains(&code) => Some(SdpRole::Answer),
+            Some(Method::UPDATE | Method::PRACK) if (200..300).contains(&code) => Some(SdpRole::Answer),
+            _ => None,
+        })
+    }
+
+    /// Check whether the original bytes can be forwarded behind one extra Via
+    ///
+    /// A fast-path check for trusted traffic: the message parses, the start
+    /// line comes first, every line ends in CRLF with no folding, the core
+    /// headers and a Via are present, a single Content-Length matches the body
+    /// exactly and a request still has hops left in Max-Forwards. Anything
+    /// else returns `false` and belongs on the validating path.
+    pub fn is_forward_safe(&mut self) -> bool {
+        if self.parse_without_validation().is_err() {
+            return false;
+        }
+
+        let Some(head_end) = self.raw_message.find("\r\n\r\n") else {
+            return false;
+        };
+        let head = &self.raw_message[..head_end + 2];
+        let well_framed = self.start_line.start == 0
+            && head.matches('\n').count() == head.matches("\r\n").count()
+            && !head.contains("\r\n ")
+            && !head.contains("\r\n\t");
+        if !well_framed {
+            return false;
+        }
+
+        let core_headers = self.to.is_some()
+            && self.from.is_some()
+            && self.call_id.is_some()
+            && self.cseq.is_some()
+            && self.via_list().is_ok_and(|vias| !vias.is_empty());
+        if !core_headers || (self.is_request && self.max_forwards().is_none_or(|hops| hops == 0)) {
+            return false;
+        }
+
+        self.header_count("content-length") == 1
+            && self.declared_content_length() == Some(self.body().map_or(0, str::len))
+            && self.trailing.is_none()
+    }
+
+    /// Check if this response must be acknowledged with an ACK
+    ///
+    /// Only final responses (200-699) to INVITE are ACKed; provisional
+    /// responses and responses to any other method never are. The INVITE client
+    /// transaction ACKs 3xx-6xx itself (RFC 3261 Section 17.1.1.3), while the
+    /// ACK for a 2xx is sent by the UAC core (Section 13.2.2.4). Requests
+    /// yield `false`.
+    pub fn requires_ack(&mut self) -> Result<bool, SsbcError> {
+        self.parse_without_validation()?;
+        if self.is_request || self.status_line()?.code < 200 {
+            return Ok(false);
+        }
+        Ok(self.cseq_method()? == Some(Method::INVITE))
+    }
+
+    /// Check for early media: a provisional response to INVITE carrying SDP
+    ///
+    /// Covers `183 Session Progress` and any other 1xx above 100 whose body
+    /// answers the INVITE offer, so media can flow before the call is answered.
+    pub fn is_early_media(&mut self) -> Result<bool, SsbcError> {
+        if self.is_request {
+            return Ok(false);
+        }
+        self.parse_without_validation()?;
+        if !(101..200).contains(&self.status_line()?.code) {
+            return Ok(false);
+        }
+        Ok(self.sdp_role()? == Some(SdpRole::Answer))
+    }
+
+    /// Check for a non-empty body declared as `application/sdp`
+    fn has_sdp_body(&self) -> bool {
+        let is_sdp = self.header_value_ranges("content-type").first().is_some_and(|range| {
+            let media_type = self.get_str(*range).split(';').next().unwrap_or_default();
+            media_type.trim().eq_ignore_ascii_case("application/sdp")
+        });
+        is_sdp && self.body().is_some_and(|body| !body.trim().is_empty())
+    }
+
     /// Get the Via header, parsing it on demand
     pub fn via(&mut self) -> Result<Option<&Via>, SsbcError> {
         if self.via_headers.is_empty() {
@@ -736,6 +1410,19 @@ impl SipMessage {
         parse_address_header!(self, to, "To")
     }
 
+    /// Get the To header if it has no tag yet, parsing it on demand
+    ///
+    /// A UAS answering a dialog-creating request must add a tag to this To
+    /// (RFC 3261 Section 8.2.6.2). `None` when To is absent or already tagged.
+    pub fn to_without_tag(&mut self) -> Result<Option<&Address>, SsbcError> {
+        self.parse_without_validation()?;
+        self.to()?;
+        match &self.to {
+            Some(HeaderValue::Address(to)) if self.find_param(&to.params, "tag").is_none() => Ok(Some(to)),
+            _ => Ok(None),
+        }
+    }
+
     /// Get the From header, parsing it on demand
     pub fn from(&mut self) -> Result<Option<&Address>, SsbcError> {
         parse_address_header!(self, from, "From")
@@ -791,29 +1478,587 @@ impl SipMessage {
         Ok(result)
     }
 
-    /// Check if this message has multiple contacts
-    /// Returns true if there are multiple contact headers or a single contact header with multiple entries
-    pub fn has_multiple_contacts(&self) -> bool {
-        self.contact_headers.len() > 1 || self.contact_has_multiple_entries
+    /// Get all Contact headers that parse, along with the errors for those that don't
+    ///
+    /// Lenient counterpart of [`SipMessage::contacts`]: one malformed Contact no
+    /// longer hides the valid ones, so a B2BUA can carry on with what it has.
+    pub fn contacts_lossy(&mut self) -> (Vec<&Address>, Vec<SsbcError>) {
+        let mut errors = Vec::new();
+        for i in 0..self.contact_headers.len() {
+            if let Err(e) = self.ensure_contact_header_parsed(i) {
+                errors.push(e);
+            }
+        }
+
+        let contacts = self
+            .contact_headers
+            .iter()
+            .filter_map(|value| match value {
+                HeaderValue::Address(address) => Some(address),
+                _ => None,
+            })
+            .collect();
+
+        (contacts, errors)
     }
 
-    /// Get all headers with a specific name
-    /// This is a public interface that uses the internal find_headers_by_name method
-    pub fn get_headers_by_name(&self, name: &str) -> Vec<&HeaderValue> {
-        let headers = self.find_headers_by_name(name);
-        headers.iter().map(|(_, value)| *value).collect()
+    /// Get the targets of a 3xx redirect ordered by descending `q` value
+    ///
+    /// Every Contact header, including comma-separated lists, contributes a
+    /// target. Contacts without `q` count as 1.0; ties keep message order.
+    pub fn redirect_targets(&mut self) -> Result<Vec<(Address, f32)>, SsbcError> {
+        let mut targets = Vec::new();
+        for address in self.contact_list()? {
+            let q = match self.find_param(&address.params, "q") {
+                Some(Some(q_range)) => {
+                    let q_str = self.get_str(q_range);
+                    Self::parse_qvalue(q_str).ok_or_else(|| {
+                        SsbcError::parse_error(
+                            format!("Invalid q value in Contact: {}", q_str),
+                            None,
+                            None,
+                        )
+                    })?
+                }
+                _ => 1.0,
+            };
+            targets.push((address, q));
+        }
+
+        targets.sort_by(|a, b| b.1.total_cmp(&a.1));
+        Ok(targets)
     }
 
-    /// Find all headers with the given name
-    fn find_headers_by_name<'a>(&'a self, name: &str) -> Vec<(usize, &'a HeaderValue)> {
-        let mut result = Vec::new();
-        for (i, (name_range, value)) in self.headers.iter().enumerate() {
-            let header_name = name_range.as_str(&self.raw_message).to_lowercase();
-            if header_name == name.to_lowercase() {
-                result.push((i, value));
-            }
+    /// Parse a qvalue: "0" or "1" with at most three decimals, never above 1
+    /// (RFC 3261 Section 25.1)
+    fn parse_qvalue(text: &str) -> Option<f32> {
+        let (int_part, frac_part) = match text.split_once('.') {
+            Some((int_part, frac_part)) => (int_part, frac_part),
+            None => (text, ""),
+        };
+        if frac_part.len() > 3 || !frac_part.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        match int_part {
+            "0" => text.parse().ok(),
+            "1" if frac_part.bytes().all(|b| b == b'0') => Some(1.0),
+            _ => None,
+        }
+    }
+
+    /// Get the expiry a registrar granted to the contact at `contact_index`
+    ///
+    /// Only a 2xx to REGISTER grants an expiry; any other message is an error.
+    /// The Contact `expires` parameter takes precedence over the Expires header
+    /// (RFC 3261 Section 10.2.4); `None` means the response states neither.
+    /// Contacts are indexed across all Contact headers, including
+    /// comma-separated lists, in message order.
+    pub fn granted_expiry(&mut self, contact_index: usize) -> Result<Option<u32>, SsbcError> {
+        if self.is_request {
+            return Err(SsbcError::parse_error("Not a response message", None, None));
+        }
+        if !(200..300).contains(&self.status_line()?.code) || self.cseq_method()? != Some(Method::REGISTER) {
+            return Err(SsbcError::parse_error("Not a 2xx response to REGISTER", None, None));
+        }
+
+        let contacts = self.contact_list()?;
+        let contact = contacts.get(contact_index).ok_or_else(|| {
+            SsbcError::parse_error(
+                format!("No Contact at index {} ({} present)", contact_index, contacts.len()),
+                None,
+                None,
+            )
+        })?;
+
+        let expires = match self.find_param(&contact.params, "expires") {
+            Some(Some(range)) => Some(range),
+            _ => self.header_value_ranges("expires").first().copied(),
+        };
+        expires
+            .map(|range| {
+                let expires_str = self.get_str(range).trim();
+                expires_str.parse::<u32>().map_err(|_| {
+                    SsbcError::parse_error(format!("Invalid expires value: {}", expires_str), None, None)
+                })
+            })
+            .transpose()
+    }
+
+    /// Get the Min-Expires header, the shortest registration a registrar accepts
+    ///
+    /// Carried by `423 Interval Too Brief` (RFC 3261 Section 10.3); a UAC
+    /// retries with at least this interval.
+    pub fn min_expires(&self) -> Result<Option<u32>, SsbcError> {
+        self.header_value_ranges("min-expires")
+            .first()
+            .map(|range| {
+                let min_expires = self.get_str(*range).trim();
+                min_expires.parse::<u32>().map_err(|_| {
+                    SsbcError::parse_error(format!("Invalid Min-Expires value: {}", min_expires), None, None)
+                })
+            })
+            .transpose()
+    }
+
+    /// Get the Min-SE header, the shortest session interval the sender accepts
+    ///
+    /// Sent with session-timer requests and in `422 Session Interval Too Small`
+    /// (RFC 4028 Section 5); parameters after the delta-seconds are ignored.
+    pub fn min_se(&self) -> Result<Option<u32>, SsbcError> {
+        self.header_value_ranges("min-se")
+            .first()
+            .map(|range| {
+                let min_se = self.get_str(*range).split(';').next().unwrap_or_default().trim();
+                min_se.parse::<u32>().map_err(|_| {
+                    SsbcError::parse_error(format!("Invalid Min-SE value: {}", min_se), None, None)
+                })
+            })
+            .transpose()
+    }
+
+    /// Every Contact element in message order, splitting comma-separated lists
+    fn contact_list(&self) -> Result<Vec<Address>, SsbcError> {
+        let mut contacts = Vec::new();
+        for value in &self.contact_headers {
+            let range = Self::header_value_range(value);
+            for (start, end) in Self::split_list_elements(self.get_str(range)) {
+                let element = TextRange::from_usize(range.start + start, range.start + end);
+                contacts.push(self.parse_address(element)?);
+            }
+        }
+        Ok(contacts)
+    }
+
+    /// Get the Reply-To address (RFC 3261 Section 20.31), parsing it on demand
+    pub fn reply_to(&mut self) -> Result<Option<Address>, SsbcError> {
+        self.parse_without_validation()?;
+        self.header_value_ranges("reply-to")
+            .first()
+            .map(|range| self.parse_address(*range))
+            .transpose()
+    }
+
+    /// Get the Call-IDs listed in In-Reply-To (RFC 3261 Section 20.21)
+    ///
+    /// Looks at the headers found by the last parse, like [`SipMessage::has_header`].
+    pub fn in_reply_to(&self) -> Vec<&str> {
+        self.list_element_ranges("in-reply-to")
+            .into_iter()
+            .map(|range| self.get_str(range).trim())
+            .collect()
+    }
+
+    /// Collect every URI the message points at, for auditing and logging
+    ///
+    /// In order: the Request-URI, then To, From, Contact, Route, Record-Route,
+    /// Refer-To and P-Asserted-Identity, one entry per list element. Absent
+    /// headers and a wildcard Contact are skipped; a malformed URI is an error.
+    pub fn all_uris(&mut self) -> Result<Vec<SipUri>, SsbcError> {
+        self.parse_without_validation()?;
+        let mut uris = Vec::new();
+        if self.is_request {
+            uris.push(self.request_uri()?);
+        }
+
+        let headers = [
+            "to",
+            "from",
+            "contact",
+            "route",
+            "record-route",
+            "refer-to",
+            "p-asserted-identity",
+        ];
+        for name in headers {
+            for element in self.list_element_ranges(name) {
+                if self.get_str(element).trim() != "*" {
+                    uris.push(self.parse_address(element)?.uri);
+                }
+            }
+        }
+        Ok(uris)
+    }
+
+    /// Get the remote target the Contact header sets up (RFC 3261 Section 12.1)
+    ///
+    /// Requests sent within the dialog go to this URI. It comes from the first
+    /// Contact of the dialog-creating request or of a 101-299 response; other
+    /// responses, such as 3xx redirects, set no target and yield `None`, as
+    /// does a message without Contact. A wildcard Contact is an error.
+    pub fn remote_target(&mut self) -> Result<Option<SipUri>, SsbcError> {
+        self.parse_without_validation()?;
+        if !self.is_request && !(101..300).contains(&self.status_line()?.code) {
+            return Ok(None);
+        }
+
+        let Some(element) = self.list_element_ranges("contact").first().copied() else {
+            return Ok(None);
+        };
+        if self.get_str(element).trim() == "*" {
+            return Err(SsbcError::parse_error("Wildcard Contact is not a remote target", None, None));
+        }
+        Ok(Some(self.parse_address(element)?.uri))
+    }
+
+    /// Build the ACK for a 2xx response to this INVITE (RFC 3261 Section 13.2.2.4)
+    ///
+    /// The ACK goes to the response's Contact along the route set from its
+    /// Record-Route, taken in reverse; a strict next hop (no `lr`) becomes the
+    /// Request-URI, with the Contact appended as the last Route. Call-ID, From,
+    /// the CSeq number and any credentials come from the INVITE, To (with its
+    /// tag) from the response. The Via keeps the INVITE's sent-by under a fresh
+    /// branch drawn from `random`, since the ACK is a transaction of its own.
+    pub fn build_ack_for_2xx(
+        &mut self,
+        response: &mut SipMessage,
+        random: &dyn RandomSource,
+    ) -> Result<Vec<u8>, SsbcError> {
+        self.parse_without_validation()?;
+        response.parse_without_validation()?;
+        if self.request_method() != Some(Method::INVITE) {
+            return Err(SsbcError::parse_error("ACK is only built for an INVITE", None, None));
+        }
+        if response.is_request || !(200..300).contains(&response.status_line()?.code) {
+            return Err(SsbcError::parse_error("ACK is only built for a 2xx response", None, None));
+        }
+        let (seq, _) = self.cseq_parts()?;
+        if response.call_id_str() != self.call_id_str() || response.cseq_parts()?.0 != seq {
+            return Err(SsbcError::parse_error("Response does not belong to this INVITE", None, None));
+        }
+        if response.remote_target()?.is_none() {
+            return Err(SsbcError::parse_error("2xx response has no Contact", None, None));
+        }
+
+        let addr_spec = |element: &str| -> String {
+            let element = element.trim();
+            let uri = match element.split_once('<') {
+                Some((_, rest)) => rest.split_once('>').map_or(rest, |(uri, _)| uri),
+                None => element.split(';').next().unwrap_or_default(),
+            };
+            uri.trim().to_string()
+        };
+        let target = addr_spec(response.get_str(response.list_element_ranges("contact")[0]));
+        let mut route_set = Vec::new();
+        for element in response.list_element_ranges("record-route").into_iter().rev() {
+            let strict = response.find_param(&response.parse_address(element)?.uri.params, "lr").is_none();
+            route_set.push((addr_spec(response.get_str(element)), strict));
+        }
+        let (request_uri, routes) = match route_set.split_first() {
+            Some(((next_hop, true), rest)) => {
+                let mut routes: Vec<_> = rest.iter().map(|(uri, _)| uri.clone()).collect();
+                routes.push(target);
+                (next_hop.clone(), routes)
+            }
+            _ => (target, route_set.into_iter().map(|(uri, _)| uri).collect()),
+        };
+
+        let top_via = self
+            .via_list()?
+            .into_iter()
+            .next()
+            .ok_or_else(|| SsbcError::parse_error("INVITE has no Via", None, None))?;
+        let header = |message: &SipMessage, name: &str| {
+            message.header_value_ranges(name).first().map(|range| message.get_str(*range).to_string())
+        };
+
+        let mut ack = format!("ACK {} SIP/2.0\r\n", request_uri);
+        ack.push_str(&format!(
+            "Via: {} {};branch=z9hG4bK{:08x}{:08x}\r\n",
+            self.get_str(top_via.sent_protocol),
+            self.get_str(top_via.sent_by),
+            random.next_u32(),
+            random.next_u32()
+        ));
+        ack.push_str("Max-Forwards: 70\r\n");
+        for route in routes {
+            ack.push_str(&format!("Route: <{}>\r\n", route));
+        }
+        let from = header(self, "from").unwrap_or_default();
+        let to = header(response, "to").unwrap_or_default();
+        ack.push_str(&format!("From: {}\r\nTo: {}\r\n", from, to));
+        ack.push_str(&format!("Call-ID: {}\r\nCSeq: {} ACK\r\n", self.call_id_str().unwrap_or_default(), seq));
+        for name in ["Authorization", "Proxy-Authorization"] {
+            for range in self.header_value_ranges(name) {
+                ack.push_str(&format!("{}: {}\r\n", name, self.get_str(range)));
+            }
+        }
+        ack.push_str("Content-Length: 0\r\n\r\n");
+        Ok(ack.into_bytes())
+    }
+
+    /// Check if this message has multiple contacts
+    /// Returns true if there are multiple contact headers or a single contact header with multiple entries
+    pub fn has_multiple_contacts(&self) -> bool {
+        self.contact_headers.len() > 1 || self.contact_has_multiple_entries
+    }
+
+    /// Get all headers with a specific name
+    /// Matches case-insensitively and treats compact forms as their full names;
+    /// Via and Contact yield one value per header line.
+    pub fn get_headers_by_name(&self, name: &str) -> Vec<&HeaderValue> {
+        self.headers_named(name).collect()
+    }
+
+    /// Find all headers with the given name
+    fn find_headers_by_name<'a>(&'a self, name: &str) -> Vec<(usize, &'a HeaderValue)> {
+        let mut result = Vec::new();
+        for (i, (name_range, value)) in self.headers.iter().enumerate() {
+            let header_name = name_range.as_str(&self.raw_message).to_lowercase();
+            if header_name == name.to_lowercase() {
+                result.push((i, value));
+            }
+        }
+        result
+    }
+
+    /// Value ranges of every header called `name`, in message order
+    ///
+    /// Matches case-insensitively and treats compact forms as their full names,
+    /// covering headers kept in dedicated fields as well as the generic list.
+    pub(crate) fn header_value_ranges(&self, name: &str) -> Vec<TextRange> {
+        self.headers_named(name).map(Self::header_value_range).collect()
+    }
+
+    /// Ranges of the comma-separated elements of every header with this name
+    fn list_element_ranges(&self, name: &str) -> Vec<TextRange> {
+        self.headers_named(name)
+            .map(Self::header_value_range)
+            .flat_map(|range| {
+                Self::split_list_elements(self.get_str(range))
+                    .into_iter()
+                    .map(move |(start, end)| TextRange::from_usize(range.start + start, range.start + end))
+            })
+            .collect()
+    }
+
+    /// Check whether a header is present, without allocating
+    ///
+    /// Matches case-insensitively and treats compact forms as their full names,
+    /// so `has_header("m")` finds a `Contact` line. Looks at the headers found by
+    /// the last parse.
+    pub fn has_header(&self, name: &str) -> bool {
+        self.headers_named(name).next().is_some()
+    }
+
+    /// Count the header lines called `name`, without allocating
+    ///
+    /// Same matching as [`SipMessage::has_header`]. Each line counts once, so a
+    /// Via line listing two hops comma-separated counts as one.
+    pub fn header_count(&self, name: &str) -> usize {
+        self.headers_named(name).count()
+    }
+
+    /// Values of every header called `name`, dedicated field or generic list
+    fn headers_named<'a: 'n, 'n>(&'a self, name: &'n str) -> impl Iterator<Item = &'a HeaderValue> + 'n {
+        let canonical = compact_header_full_name(name).unwrap_or(name);
+        let dedicated = [
+            ("to", &self.to),
+            ("from", &self.from),
+            ("call-id", &self.call_id),
+            ("cseq", &self.cseq),
+            ("max-forwards", &self.max_forwards),
+            ("subscription-state", &self.subscription_state),
+            ("refer-to", &self.refer_to),
+        ]
+        .into_iter()
+        .find(|(field_name, _)| field_name.eq_ignore_ascii_case(canonical))
+        .map(|(_, value)| value);
+
+        let generic = dedicated
+            .is_none()
+            .then_some(&self.headers)
+            .into_iter()
+            .flatten()
+            .filter(move |(name_range, _)| {
+                let header_name = name_range.as_str(&self.raw_message);
+                compact_header_full_name(header_name)
+                    .unwrap_or(header_name)
+                    .eq_ignore_ascii_case(canonical)
+            })
+            .map(|(_, value)| value);
+
+        dedicated.into_iter().flatten().chain(generic)
+    }
+
+    /// Iterate (name, value) ranges of every header in parse order
+    ///
+    /// Includes the headers held in dedicated fields (To, From, Call-ID, CSeq,
+    /// Max-Forwards, ...) alongside the generic list. Via and Contact appear once
+    /// per header line.
+    pub fn header_ranges(&self) -> impl Iterator<Item = (TextRange, TextRange)> + '_ {
+        let dedicated = [
+            &self.to,
+            &self.from,
+            &self.call_id,
+            &self.cseq,
+            &self.max_forwards,
+            &self.subscription_state,
+            &self.refer_to,
+        ]
+        .into_iter()
+        .flatten()
+        .map(|value| {
+            let value_range = Self::header_value_range(value);
+            (self.header_name_range(value_range), value_range)
+        });
+
+        let mut ranges: Vec<(TextRange, TextRange)> = self
+            .headers
+            .iter()
+            .map(|(name_range, value)| (*name_range, Self::header_value_range(value)))
+            .chain(dedicated)
+            .collect();
+        ranges.sort_by_key(|(name_range, _)| name_range.start);
+        ranges.into_iter()
+    }
+
+    /// Every header as (name, value) in wire order, for header-agnostic consumers
+    ///
+    /// Repeated headers such as Via yield one entry per header line. Values are
+    /// borrowed from the message unless they were folded across lines, in which
+    /// case the logical single-line value is allocated.
+    pub fn as_header_pairs(&self) -> Vec<(&str, Cow<'_, str>)> {
+        self.header_ranges()
+            .map(|(name_range, value_range)| {
+                (self.get_str(name_range), header_utils::unfold(self.get_str(value_range)))
+            })
+            .collect()
+    }
+
+    /// Check if the Require header lists the `100rel` option tag (RFC 3262)
+    ///
+    /// A UAS must then send its provisional responses reliably. Parses the
+    /// headers first if needed; an unparseable message requires nothing.
+    pub fn requires_100rel(&mut self) -> bool {
+        self.has_option_tag("require", "100rel")
+    }
+
+    /// Check if the Supported header lists the `100rel` option tag (RFC 3262)
+    ///
+    /// Parses the headers first if needed; an unparseable message supports nothing.
+    pub fn supports_100rel(&mut self) -> bool {
+        self.has_option_tag("supported", "100rel")
+    }
+
+    /// Check if the Supported header lists the `path` option tag (RFC 3327)
+    ///
+    /// An edge proxy may only add a Path entry to a REGISTER that supports it.
+    /// Parses the headers first if needed; an unparseable message supports nothing.
+    pub fn supports_path(&mut self) -> bool {
+        self.has_option_tag("supported", "path")
+    }
+
+    /// Match the peer's Require, Proxy-Require and Supported tags against ours
+    ///
+    /// Tags compare case-insensitively and keep the peer's spelling and order,
+    /// without duplicates. Parses the headers first if needed; an unparseable
+    /// message yields an empty result.
+    pub fn negotiate_options(&mut self, our_supported: &[&str]) -> NegotiationResult {
+        let mut result = NegotiationResult::default();
+        if self.parse_without_validation().is_err() {
+            return result;
+        }
+
+        let we_support = |tag: &str| our_supported.iter().any(|ours| ours.eq_ignore_ascii_case(tag));
+        let push_unique = |list: &mut Vec<String>, tag: &str| {
+            if !list.iter().any(|listed| listed.eq_ignore_ascii_case(tag)) {
+                list.push(tag.to_string());
+            }
+        };
+
+        for (header, unsupported) in [
+            ("require", &mut result.unsupported),
+            ("proxy-require", &mut result.unsupported_proxy),
+        ] {
+            for tag in self.option_tags(header) {
+                if !we_support(tag) {
+                    push_unique(unsupported, tag);
+                }
+            }
+        }
+        for tag in self.option_tags("require").into_iter().chain(self.option_tags("supported")) {
+            if we_support(tag) {
+                push_unique(&mut result.common, tag);
+            }
+        }
+        result
+    }
+
+    /// Join every instance of a list-valued header into one comma-separated value
+    ///
+    /// Multiple lines of a header such as Allow, Supported, Via or Route are
+    /// equivalent to a single comma-joined line (RFC 3261 Section 7.3.1).
+    /// Values are unfolded and empty ones skipped; compact names match their
+    /// full form. Returns `None` when the header is absent. Only meaningful for
+    /// headers whose grammar is a comma-separated list.
+    pub fn coalesced_header(&self, name: &str) -> Option<String> {
+        let values: Vec<Cow<'_, str>> = self
+            .header_value_ranges(name)
+            .into_iter()
+            .map(|range| header_utils::unfold(self.get_str(range)))
+            .filter(|value| !value.is_empty())
+            .collect();
+        (!values.is_empty()).then(|| values.join(", "))
+    }
+
+    /// Option tags listed across every `header` (Require, Supported, ...) line
+    pub(crate) fn option_tags(&self, header: &str) -> Vec<&str> {
+        self.header_value_ranges(header)
+            .into_iter()
+            .flat_map(|range| self.get_str(range).split(','))
+            .map(str::trim)
+            .filter(|tag| !tag.is_empty())
+            .collect()
+    }
+
+    /// Check for an option tag, case-insensitively, after making sure headers are parsed
+    fn has_option_tag(&mut self, header: &str, tag: &str) -> bool {
+        if self.parse_without_validation().is_err() {
+            return false;
+        }
+        self.option_tags(header)
+            .iter()
+            .any(|listed| listed.eq_ignore_ascii_case(tag))
+    }
+
+    /// Iterate headers the crate doesn't recognize as standard, in parse order
+    ///
+    /// Yields (name, value) for every header outside RFC 3261 and the known
+    /// extensions, which includes all `X-` and `P-` headers.
+    pub fn extension_headers(&self) -> impl Iterator<Item = (&str, &str)> + '_ {
+        self.header_ranges().filter_map(move |(name_range, value_range)| {
+            let name = self.get_str(name_range);
+            let is_standard = compact_header_full_name(name).is_some()
+                || COMPACT_HEADER_FORMS
+                    .iter()
+                    .any(|(_, full)| full.eq_ignore_ascii_case(name))
+                || STANDARD_HEADER_NAMES
+                    .iter()
+                    .any(|standard| standard.eq_ignore_ascii_case(name));
+            (!is_standard).then(|| (name, self.get_str(value_range)))
+        })
+    }
+
+    /// Recover the name range of a header from its value range
+    ///
+    /// Dedicated fields only keep the value, but the name always runs from the
+    /// start of the line to the colon preceding the value, less any whitespace
+    /// a lenient parse tolerated before the colon.
+    fn header_name_range(&self, value_range: TextRange) -> TextRange {
+        let before_value = &self.raw_message[..value_range.start];
+        let colon = before_value.rfind(':').unwrap_or(0);
+        let line_start = before_value[..colon].rfind("\r\n").map_or(0, |pos| pos + 2);
+        let name = before_value[line_start..colon].trim_end_matches([' ', '\t']);
+        TextRange::from_usize(line_start, line_start + name.len())
+    }
+
+    /// Text range covered by a header value, parsed or not
+    fn header_value_range(value: &HeaderValue) -> TextRange {
+        match value {
+            HeaderValue::Raw(range) => *range,
+            HeaderValue::Address(address) => address.full_range,
+            HeaderValue::Via(via) => via.full_range,
         }
-        result
     }
 
     /// Check if a raw header value contains a specific character
@@ -845,6 +2090,7 @@ impl SipMessage {
             .find(';')
             .unwrap_or(via_str.len() - space_pos - 1);
         let sent_by_range = TextRange::from_usize(rest_start, rest_start + sent_by_end);
+        validation::parse_host(sent_by_range.as_str(&self.raw_message))?;
 
         // Parse parameters if present
         let mut params = HashMap::new();
@@ -1020,6 +2266,9 @@ impl SipMessage {
                     (rest_start as usize) + semicolon_pos,
                     range.end as usize,
                 );
+                if self.limits().reject_invalid_uri_escapes {
+                    validation::validate_percent_escapes(params_range.as_str(raw_message), "parameter")?;
+                }
                 self.parse_params_with_message(raw_message, params_range, &mut uri.params)?;
             } else {
                 // No parameters, the whole rest is the phone number
@@ -1212,23 +2461,31 @@ impl SipMessage {
 
         let host_port = host_port_range.as_str(raw_message);
 
-        // Parse host and optional port
-        if let Some(colon_pos) = host_port.find(':') {
-            uri.host = Some(TextRange::from_usize(
-                host_port_range.start as usize,
-                (host_port_range.start as usize) + colon_pos,
-            ));
+        if self.limits().reject_invalid_uri_escapes {
+            validation::validate_percent_escapes(host_port, "host")?;
+            if let Some((rest_range, delimiter)) = rest {
+                let rest_str = rest_range.as_str(raw_message);
+                let (params, headers) = match (delimiter, rest_str.split_once('?')) {
+                    ('?', _) => ("", rest_str),
+                    (_, Some((params, headers))) => (params, headers),
+                    (_, None) => (rest_str, ""),
+                };
+                validation::validate_percent_escapes(params, "parameter")?;
+                validation::validate_percent_escapes(headers, "header")?;
+            }
+        }
 
-            // Parse port
-            let port_str = &host_port[colon_pos + 1..];
-            uri.port = Some(port_str.parse::<u16>().map_err(|_| SsbcError::ParseError {
-                message: format!("Invalid port: {}", port_str),
-                position: None,
-                context: None,
-            })?);
-        } else {
-            uri.host = Some(host_port_range);
+        // Parse host and optional port; the range drops IPv6 brackets
+        let (host, port) = validation::parse_host(host_port)?;
+        let (host_str, _) = validation::split_host_port(host_port);
+        let mut host_start = host_port_range.start + (host_port.len() - host_port.trim_start().len());
+        let mut host_len = host_str.len();
+        if matches!(host, Host::Ipv6(_)) {
+            host_start += 1;
+            host_len -= 2;
         }
+        uri.host = Some(TextRange::from_usize(host_start, host_start + host_len));
+        uri.port = port;
 
         // Parse parameters and headers if present
         if let Some((rest_range, delimiter)) = rest {
@@ -1314,8 +2571,25 @@ impl SipMessage {
     }
 
     /// Helper to get string value from TextRange
+    ///
+    /// Debug builds validate the range against this message first so that a
+    /// range borrowed from another message fails with a descriptive panic
+    /// instead of a bare slice index error; use [`SipMessage::try_get_str`]
+    /// to avoid panicking altogether.
     pub fn get_str(&self, range: TextRange) -> &str {
-        range.as_str(&self.raw_message)
+        if cfg!(debug_assertions) {
+            match range.try_as_str(&self.raw_message) {
+                Ok(value) => value,
+                Err(e) => panic!("{}", e),
+            }
+        } else {
+            range.as_str(&self.raw_message)
+        }
+    }
+
+    /// Non-panicking variant of [`SipMessage::get_str`]
+    pub fn try_get_str(&self, range: TextRange) -> Result<&str, SsbcError> {
+        range.try_as_str(&self.raw_message)
     }
 
     /// Helper to get string value from optional TextRange
@@ -1341,6 +2615,19 @@ impl SipMessage {
             .collect()
     }
 
+    /// Get parameters as name/value pairs in the order they appear in the message
+    ///
+    /// Parameter keys are ranges into the raw message, so sorting by their
+    /// offset restores the original order that the map itself doesn't keep.
+    pub fn params_ordered(&self, params: &ParamMap) -> Vec<(&str, Option<&str>)> {
+        let mut entries: Vec<_> = params.iter().collect();
+        entries.sort_by_key(|(key, _)| key.start);
+        entries
+            .into_iter()
+            .map(|(key, value)| (self.get_param_key(key), self.get_param_value(value)))
+            .collect()
+    }
+
     /// Parse the CSeq header and extract the method
     pub fn cseq_method(&mut self) -> Result<Option<Method>, SsbcError> {
         if let Some(HeaderValue::Raw(range)) = self.cseq {
@@ -1535,6 +2822,168 @@ impl SipMessage {
         self.parse_uri(uri_range)
     }
 
+    /// Check whether the Request-URI host is one of the domains we serve
+    ///
+    /// `local_domains` holds domain names, IPv4 addresses and IPv6 addresses
+    /// with or without brackets; a port in an entry is ignored. Names match
+    /// case-insensitively and IP literals by value, so `[::1]` serves a
+    /// Request-URI of `sip:[0:0::1]`. A `tel:` URI has no host and is never local.
+    pub fn request_uri_is_local(&mut self, local_domains: &[&str]) -> Result<bool, SsbcError> {
+        self.parse_without_validation()?;
+        let Some(host) = self.request_uri()?.host else {
+            return Ok(false);
+        };
+        let Some(host) = Self::classify_host(self.get_str(host)) else {
+            return Ok(false);
+        };
+        Ok(local_domains
+            .iter()
+            .filter_map(|domain| Self::classify_host(domain.trim()))
+            .any(|domain| domain.matches(&host)))
+    }
+
+    /// Host of a URI or configured domain, accepting IPv6 without brackets
+    fn classify_host(text: &str) -> Option<Host<'_>> {
+        let unbracketed = text.strip_prefix('[').and_then(|text| text.strip_suffix(']')).unwrap_or(text);
+        match unbracketed.parse::<std::net::Ipv6Addr>() {
+            Ok(address) => Some(Host::Ipv6(address)),
+            Err(_) => validation::parse_host(text).ok().map(|(host, _)| host),
+        }
+    }
+
+    /// Get the route set from all Route headers, in order
+    /// Comma-separated elements within a single Route header are returned individually
+    pub fn routes(&self) -> Result<Vec<Address>, SsbcError> {
+        let mut routes = Vec::new();
+        for (_, value) in self.find_headers_by_name("route") {
+            if let HeaderValue::Raw(range) = value {
+                for (start, end) in Self::split_list_elements(self.get_str(*range)) {
+                    let element = TextRange::from_usize(range.start + start, range.start + end);
+                    routes.push(self.parse_address(element)?);
+                }
+            }
+        }
+        Ok(routes)
+    }
+
+    /// Remove the topmost Route element and return it (RFC 3261 Section 16.4)
+    ///
+    /// Handles one element per Route header as well as comma-joined values; the
+    /// remaining route set keeps its order. Only the parsed view is updated, so
+    /// the returned address stays valid against [`SipMessage::raw_message`]; use
+    /// `ZeroCopyModifier::pop_local_route` to drop the element on the wire.
+    pub fn pop_route(&mut self) -> Result<Option<Address>, SsbcError> {
+        let Some((index, range)) = self.find_headers_by_name("route").into_iter().find_map(
+            |(index, value)| match value {
+                HeaderValue::Raw(range) => Some((index, *range)),
+                _ => None,
+            },
+        ) else {
+            return Ok(None);
+        };
+
+        let elements = Self::split_list_elements(self.get_str(range));
+        let Some(&(start, end)) = elements.first() else {
+            return Err(SsbcError::parse_error("Empty Route header", None, None));
+        };
+        let route = self.parse_address(TextRange::from_usize(range.start + start, range.start + end))?;
+
+        match elements.get(1) {
+            Some(&(next_start, _)) => {
+                self.headers[index].1 = HeaderValue::Raw(TextRange::from_usize(range.start + next_start, range.end));
+            }
+            None => {
+                self.headers.remove(index);
+            }
+        }
+        Ok(Some(route))
+    }
+
+    /// Check if a `sips` Request-URI is being routed through a non-secure route element
+    ///
+    /// A `sips` request must stay on TLS for every hop, so any `sip` entry in the
+    /// route set indicates a downgrade. Responses and non-secure Request-URIs
+    /// always return false.
+    pub fn is_scheme_downgraded(&self) -> Result<bool, SsbcError> {
+        if !self.is_request || !self.request_uri()?.scheme.is_secure() {
+            return Ok(false);
+        }
+
+        Ok(self.routes()?.iter().any(|route| !route.uri.scheme.is_secure()))
+    }
+
+    /// Enforce that a `sips` Request-URI isn't downgraded by the next hop or Contact
+    ///
+    /// Compares the Request-URI scheme against the topmost Route element and the
+    /// Contact. Returns an error naming the offending header when either uses a
+    /// non-secure scheme. Responses and non-`sips` requests always pass.
+    pub fn scheme_consistency(&mut self) -> Result<(), SsbcError> {
+        if !self.is_request || !self.request_uri()?.scheme.is_secure() {
+            return Ok(());
+        }
+
+        if let Some(route) = self.routes()?.first() {
+            if !route.uri.scheme.is_secure() {
+                return Err(SsbcError::parse_error(
+                    format!("sips Request-URI routed via {} Route", route.uri.scheme),
+                    None,
+                    Some(format!("scheme downgrade: {}", self.get_str(route.full_range))),
+                ));
+            }
+        }
+
+        let contact = match self.contact()? {
+            Some(contact) if !contact.uri.scheme.is_secure() => {
+                Some((contact.uri.scheme.clone(), contact.full_range))
+            }
+            _ => None,
+        };
+        if let Some((scheme, range)) = contact {
+            return Err(SsbcError::parse_error(
+                format!("sips Request-URI with {} Contact", scheme),
+                None,
+                Some(format!("scheme downgrade: {}", self.get_str(range))),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Split a comma-separated header value into its elements
+    /// Commas inside quoted strings or angle brackets do not split. Returned offsets
+    /// are relative to `value` and exclude surrounding whitespace.
+    pub(crate) fn split_list_elements(value: &str) -> Vec<(usize, usize)> {
+        let bytes = value.as_bytes();
+        let mut boundaries = Vec::new();
+        let mut in_quotes = false;
+        let mut in_brackets = false;
+
+        for (i, &c) in bytes.iter().enumerate() {
+            match c {
+                b'"' if i == 0 || bytes[i - 1] != b'\\' => in_quotes = !in_quotes,
+                b'<' if !in_quotes => in_brackets = true,
+                b'>' if !in_quotes => in_brackets = false,
+                b',' if !in_quotes && !in_brackets => boundaries.push(i),
+                _ => {}
+            }
+        }
+        boundaries.push(bytes.len());
+
+        let mut elements = Vec::new();
+        let mut element_start = 0;
+        for boundary in boundaries {
+            let element = &value[element_start..boundary];
+            let trimmed = element.trim();
+            if !trimmed.is_empty() {
+                let start = element_start + (element.len() - element.trim_start().len());
+                elements.push((start, start + trimmed.len()));
+            }
+            element_start = boundary + 1;
+        }
+
+        elements
+    }
+
     /// Extract From tag parameter efficiently
     pub fn from_tag(&self) -> Option<&str> {
         let from_range = match self.from.as_ref()? {
@@ -1581,1680 +3030,5234 @@ impl SipMessage {
         }
     }
 
-    /// Get Call-ID as string
-    pub fn call_id_str(&self) -> Option<&str> {
-        match self.call_id.as_ref()? {
-            HeaderValue::Raw(range) => Some(self.get_str(*range)),
-            _ => None,
-        }
+    /// Compute the server transaction key of a received request (RFC 3261 Section 17.2.3)
+    ///
+    /// Combines the top Via branch and sent-by with the request method.
+    pub fn server_transaction_id(&mut self) -> Result<TransactionId, SsbcError> {
+        let method = self.request_method().ok_or_else(|| {
+            SsbcError::state_error(
+                "server_transaction_id",
+                "Server transactions are keyed on requests",
+                None,
+            )
+        })?;
+        let branch = self.required_transaction_branch()?;
+        let sent_by = match self.via_headers.first() {
+            Some(HeaderValue::Via(via)) => self.get_str(via.sent_by).trim().to_ascii_lowercase(),
+            _ => String::new(),
+        };
+
+        Ok(TransactionId {
+            branch,
+            sent_by: Some(sent_by),
+            method: Self::transaction_method(method),
+        })
     }
 
-    /// Add this method to parse Event header for SUBSCRIBE/NOTIFY
-    pub fn parse_event(&mut self) -> Result<Option<&EventPackageData>, SsbcError> {
-        // Find the Event header
-        let event_header = self.headers.iter().find(|(name_range, _)| {
-            let name = self.get_str(*name_range).to_lowercase();
-            name == "event" || name == "o" // 'o' is compact form
-        });
+    /// Compute the client transaction key (RFC 3261 Section 17.1.3)
+    ///
+    /// Uses the top Via branch and the CSeq method, so an outgoing request and
+    /// the responses it receives produce the same key.
+    pub fn client_transaction_id(&mut self) -> Result<TransactionId, SsbcError> {
+        let method = self.cseq_method()?.ok_or_else(|| {
+            SsbcError::parse_error("Missing CSeq header", None, None)
+        })?;
+        let branch = self.required_transaction_branch()?;
 
-        if let Some((_, HeaderValue::Raw(range))) = event_header {
-            let event_str = self.get_str(*range);
+        Ok(TransactionId {
+            branch,
+            sent_by: None,
+            method: Self::transaction_method(method),
+        })
+    }
 
-            // Split by semicolon to separate event type from parameters
-            let (event_type, params_str) = if let Some(semi_pos) = event_str.find(';') {
-                (
-                    TextRange::from_usize(range.start as usize, (range.start as usize) + semi_pos),
-                    Some(&event_str[semi_pos + 1..]),
-                )
-            } else {
-                (*range, None)
-            };
+    /// Classify a received ACK by the transaction it belongs to (RFC 3261 Section 17.2.1)
+    ///
+    /// An ACK for a non-2xx response reuses the INVITE's branch and so matches
+    /// its server transaction, which `invite_transaction_exists` reports for the
+    /// ACK's key; an ACK for a 2xx carries a fresh branch and matches none.
+    /// Either way the top Via must carry a branch with the magic cookie, and
+    /// anything but an ACK is an error.
+    pub fn ack_type(
+        &mut self,
+        invite_transaction_exists: impl Fn(&TransactionId) -> bool,
+    ) -> Result<AckType, SsbcError> {
+        self.parse_without_validation()?;
+        if self.request_method() != Some(Method::ACK) {
+            return Err(SsbcError::parse_error("Not an ACK request", None, None));
+        }
+        let id = self.server_transaction_id()?;
+        Ok(if invite_transaction_exists(&id) {
+            AckType::NonTwoXxAck
+        } else {
+            AckType::TwoXxAck
+        })
+    }
 
-            // Create event package
-            let mut event = EventPackageData {
-                event_type,
-                event_params: HashMap::new(),
-            };
+    /// Top Via branch as an owned key, failing when it's absent
+    fn required_transaction_branch(&mut self) -> Result<String, SsbcError> {
+        match self.transaction_branch()? {
+            Some(branch) => Ok(branch.to_string()),
+            None => Err(SsbcError::parse_error(
+                "Missing Via branch parameter",
+                None,
+                None,
+            )),
+        }
+    }
 
-            // Parse parameters if present
-            if let Some(params) = params_str {
-                let params_range = TextRange::from_usize(
-                    (range.start as usize) + event_str.len() - params.len(),
-                    range.end as usize,
-                );
-                self.parse_params(params_range, &mut event.event_params)?;
-            }
+    /// ACK belongs to the INVITE transaction it acknowledges
+    fn transaction_method(method: Method) -> Method {
+        match method {
+            Method::ACK => Method::INVITE,
+            other => other,
+        }
+    }
 
-            // Store and return
-            self.event = Some(event);
-            return Ok(self.event.as_ref());
+    /// Check whether `other` belongs to the same dialog as this message
+    ///
+    /// Compares Call-ID and the From/To tags in both orientations, so a request
+    /// sent by the callee (tags swapped) matches as well. When either side has
+    /// no To tag yet (early dialog), only the Call-ID and From tag are compared.
+    pub fn in_same_dialog_as(&mut self, other: &mut SipMessage) -> Result<bool, SsbcError> {
+        match (self.call_id_str(), other.call_id_str()) {
+            (Some(call_id), Some(other_call_id)) if call_id.trim() == other_call_id.trim() => {}
+            _ => return Ok(false),
         }
 
-        Ok(None)
+        let (from_tag, to_tag) = self.dialog_tags()?;
+        let (other_from_tag, other_to_tag) = other.dialog_tags()?;
+        let from_tag = match self.get_opt_str(from_tag) {
+            Some(tag) => tag,
+            None => return Ok(false),
+        };
+        let to_tag = self.get_opt_str(to_tag);
+        let other_from_tag = other.get_opt_str(other_from_tag);
+        let other_to_tag = other.get_opt_str(other_to_tag);
+
+        // A missing To tag only means the dialog is still early
+        let remote_matches = |ours: Option<&str>, theirs: Option<&str>| match (ours, theirs) {
+            (Some(ours), Some(theirs)) => ours == theirs,
+            _ => true,
+        };
+
+        let same_direction =
+            Some(from_tag) == other_from_tag && remote_matches(to_tag, other_to_tag);
+        let reversed = Some(from_tag) == other_to_tag && remote_matches(to_tag, other_from_tag);
+
+        Ok(same_direction || reversed)
     }
 
-    /// Get the Call-ID header value from the dedicated field
-    pub fn call_id(&self) -> Option<String> {
-        if let Some(ref call_id_header) = self.call_id {
-            if let HeaderValue::Raw(range) = call_id_header {
-                Some(range.as_str(&self.raw_message).to_string())
-            } else {
-                None
-            }
-        } else {
-            None
+    /// Check the From/To tag rules of RFC 3261 Section 8.1.1
+    ///
+    /// Every request needs a From tag. A To tag marks a request as in-dialog, so
+    /// it is rejected on methods that never travel inside a dialog (REGISTER,
+    /// PUBLISH) and required on methods that only exist within one (ACK, BYE,
+    /// PRACK, UPDATE, INFO). Dialog-creating methods such as INVITE may carry
+    /// either form. For responses only a dialog-establishing 2xx (to INVITE,
+    /// SUBSCRIBE or REFER) is checked, and it must carry a To tag.
+    ///
+    /// This is opt-in and not part of [`SipMessage::parse_headers`].
+    pub fn validate_tags(&mut self) -> Result<(), SsbcError> {
+        let method = match self.request_method() {
+            Some(method) => method,
+            None => return self.validate_response_to_tag(),
+        };
+        let (from_tag, to_tag) = self.dialog_tags()?;
+
+        let has_from_tag = self.get_opt_str(from_tag).is_some_and(|tag| !tag.is_empty());
+        if !has_from_tag {
+            return Err(SsbcError::parse_error(
+                "Request is missing the From tag",
+                None,
+                Some(method.to_string()),
+            ));
         }
-    }
-    
-    /// Get the Max-Forwards header value
-    pub fn max_forwards(&self) -> Option<u32> {
-        if let Some(ref max_forwards_header) = self.max_forwards {
-            if let HeaderValue::Raw(range) = max_forwards_header {
-                range.as_str(&self.raw_message).trim().parse().ok()
-            } else {
-                None
+
+        let has_to_tag = self.get_opt_str(to_tag).is_some_and(|tag| !tag.is_empty());
+        match method {
+            Method::REGISTER | Method::PUBLISH if has_to_tag => Err(SsbcError::parse_error(
+                "To tag present on an out-of-dialog request",
+                None,
+                Some(method.to_string()),
+            )),
+            Method::ACK | Method::BYE | Method::PRACK | Method::UPDATE | Method::INFO
+                if !has_to_tag =>
+            {
+                Err(SsbcError::parse_error(
+                    "In-dialog request is missing the To tag",
+                    None,
+                    Some(method.to_string()),
+                ))
             }
-        } else {
-            None
+            _ => Ok(()),
         }
     }
-}
-
-/// Generic SIP header extraction utilities
-pub mod header_utils {
-    use crate::SipMessage;
 
-    /// Extract header value by name, supporting both long and compact forms
+    /// Check that the CSeq method equals the request method (RFC 3261 Section 8.1.1.5)
     ///
-    /// This function searches for headers by name, automatically handling
-    /// RFC 3261 compact forms (e.g., "f" for "from", "t" for "to").
-    ///
-    /// # Examples
-    /// ```
-    /// use ssbc::{SipMessage, header_utils::extract_header_value};
+    /// ACK and CANCEL are no exception: they reuse the CSeq *number* of the
+    /// INVITE they refer to, but their CSeq method is still `ACK` or `CANCEL`,
+    /// so `ACK` with `CSeq: 1 INVITE` is a mismatch. Methods compare
+    /// case-sensitively. Errors on responses and when CSeq is missing or malformed.
+    pub fn cseq_matches_request(&mut self) -> Result<bool, SsbcError> {
+        let request_method = self
+            .request_method()
+            .ok_or_else(|| SsbcError::parse_error("Not a request message", None, None))?;
+        let cseq_method = self
+            .cseq_method()?
+            .ok_or_else(|| SsbcError::parse_error("Missing CSeq header", None, None))?;
+        Ok(cseq_method == request_method)
+    }
+
+    /// Check whether this CANCEL cancels `invite` (RFC 3261 Sections 9.1 and 17.2.3)
     ///
-    /// let sip_msg = "INVITE sip:bob@example.com SIP/2.0\r\nVia: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r\nFrom: Alice <sip:alice@example.com>;tag=123\r\nTo: Bob <sip:bob@example.com>\r\nCall-ID: call123@example.com\r\nCSeq: 1 INVITE\r\n\r\n";
-    /// let mut message = SipMessage::new_from_str(sip_msg);
-    /// message.parse_without_validation().unwrap();
+    /// Matches when this is a CANCEL whose CSeq method is CANCEL, the CSeq
+    /// numbers are equal and the top Vias carry the same branch and sent-by
+    /// (compared case-insensitively). `invite` must already be parsed. Errors
+    /// when either top Via lacks a branch or a CSeq is malformed.
+    pub fn is_cancel_for(&mut self, invite: &SipMessage) -> Result<bool, SsbcError> {
+        self.parse_without_validation()?;
+        if self.request_method() != Some(Method::CANCEL) || invite.request_method() != Some(Method::INVITE) {
+            return Ok(false);
+        }
+
+        let (cancel_seq, cancel_method) = self.cseq_parts()?;
+        let (invite_seq, _) = invite.cseq_parts()?;
+        if cancel_method != "CANCEL" || cancel_seq != invite_seq {
+            return Ok(false);
+        }
+
+        let (cancel_sent_by, cancel_branch) = self.top_via_sent_by_and_branch()?;
+        let (invite_sent_by, invite_branch) = invite.top_via_sent_by_and_branch()?;
+        Ok(cancel_branch == invite_branch && cancel_sent_by.eq_ignore_ascii_case(invite_sent_by))
+    }
+
+    /// Check whether this response answers `request` (RFC 3261 Section 17.1.3)
     ///
-    /// // Works with both "From" and "f" headers
-    /// let from_value = extract_header_value(&message, "from");
-    /// assert!(from_value.is_some());
-    /// ```
-    pub fn extract_header_value(sip_message: &SipMessage, header_name: &str) -> Option<String> {
-        let raw_message = sip_message.raw_message();
-        let header_lower = header_name.to_lowercase();
-
-        // RFC 3261 compact form mapping
-        let compact_form = match header_lower.as_str() {
-            "from" => Some("f"),
-            "to" => Some("t"),
-            "via" => Some("v"),
-            "contact" => Some("m"),
-            "call-id" => Some("i"),
-            "content-length" => Some("l"),
-            "content-type" => Some("c"),
-            "subject" => Some("s"),
-            _ => None,
-        };
+    /// Matches when the Call-IDs are equal, the CSeq numbers and methods agree
+    /// and the top Vias carry the same branch. A response to a CANCEL therefore
+    /// never matches the INVITE it cancels. Errors when this isn't a response,
+    /// `request` isn't a request, either top Via lacks a branch or a CSeq is
+    /// malformed.
+    pub fn response_matches_request(&mut self, request: &mut SipMessage) -> Result<bool, SsbcError> {
+        self.parse_without_validation()?;
+        request.parse_without_validation()?;
+        if self.is_request {
+            return Err(SsbcError::parse_error("Not a response message", None, None));
+        }
+        if !request.is_request {
+            return Err(SsbcError::parse_error("Not a request message", None, None));
+        }
 
-        let prefixes = if let Some(compact) = compact_form {
-            vec![format!("{}:", header_lower), format!("{}:", compact)]
-        } else {
-            vec![format!("{}:", header_lower)]
-        };
+        if self.call_id_str().map(str::trim) != request.call_id_str().map(str::trim) {
+            return Ok(false);
+        }
+        if self.cseq_parts()? != request.cseq_parts()? {
+            return Ok(false);
+        }
 
-        for line in raw_message.lines() {
-            let line_lower = line.to_lowercase();
+        let (_, response_branch) = self.top_via_sent_by_and_branch()?;
+        let (_, request_branch) = request.top_via_sent_by_and_branch()?;
+        Ok(response_branch == request_branch)
+    }
 
-            for prefix in &prefixes {
-                if line_lower.starts_with(prefix) {
-                    if let Some(colon_pos) = line.find(':') {
-                        return Some(line[colon_pos + 1..].trim().to_string());
-                    }
-                }
-            }
+    /// CSeq sequence number and method
+    fn cseq_parts(&self) -> Result<(u32, &str), SsbcError> {
+        let range = self
+            .header_value_ranges("cseq")
+            .first()
+            .copied()
+            .ok_or_else(|| SsbcError::parse_error("Missing CSeq header", None, None))?;
+        let cseq = self.get_str(range);
+        let mut parts = cseq.split_whitespace();
+        match (parts.next().map(str::parse::<u32>), parts.next(), parts.next()) {
+            (Some(Ok(seq)), Some(method), None) => Ok((seq, method)),
+            _ => Err(SsbcError::parse_error(format!("Invalid CSeq format: {}", cseq), None, None)),
         }
-        None
     }
 
-    /// Extract parameter value from a header value string
-    ///
-    /// Parses SIP header parameters in the format "param=value" from
-    /// a header value, handling quoted and unquoted values.
-    ///
-    /// # Examples
-    /// ```
-    /// use ssbc::header_utils::extract_header_parameter;
-    ///
-    /// let from_header = "Alice <sip:alice@example.com>;tag=abc123";
-    /// let tag = extract_header_parameter(from_header, "tag"); // Some("abc123")
+    /// Parse the whole message into an owned [`ParsedMessage`]
     ///
-    /// let via_header = "SIP/2.0/UDP host.com;branch=z9hG4bK-123";
-    /// let branch = extract_header_parameter(via_header, "branch"); // Some("z9hG4bK-123")
-    /// ```
-    pub fn extract_header_parameter(header_value: &str, param_name: &str) -> Option<String> {
-        let param_lower = param_name.to_lowercase();
-        let param_prefix = format!("{}=", param_lower);
-
-        for part in header_value.split(';') {
-            let part_trimmed = part.trim();
-            let part_lower = part_trimmed.to_lowercase();
+    /// Convenient when the lazy, borrowing accessors get in the way: the result
+    /// has no ties to this message. The trade-off is cost: every standard
+    /// header is parsed and every component copied into its own `String`, which
+    /// takes several times as long as reading a few fields on demand. Keep the
+    /// zero-copy accessors on hot paths.
+    pub fn to_parsed(&mut self) -> Result<ParsedMessage, SsbcError> {
+        self.parse_without_validation()?;
+
+        let start_line = if self.is_request {
+            let line = self.request_line()?;
+            ParsedStartLine::Request {
+                method: line.method.clone(),
+                uri: self.owned_uri(&line.uri),
+            }
+        } else {
+            let line = self.status_line()?;
+            ParsedStartLine::Response {
+                code: line.code,
+                reason: line.reason.to_string(),
+            }
+        };
 
-            if part_lower.starts_with(&param_prefix) {
-                // Extract the value part after the "param=" prefix
-                let value_start = part_trimmed.find('=').unwrap() + 1;
-                let value = part_trimmed[value_start..].trim();
+        let via = self
+            .via_list()?
+            .iter()
+            .map(|via| ParsedVia {
+                transport: self.via_transport(via),
+                host: via.host(&self.raw_message).to_string(),
+                port: via.port(&self.raw_message),
+                params: self.owned_params(&via.params),
+            })
+            .collect();
 
-                // Remove quotes if present
-                let unquoted = if value.starts_with('"') && value.ends_with('"') && value.len() > 1
-                {
-                    &value[1..value.len() - 1]
-                } else {
-                    value
-                };
+        let address_header = |name: &str| -> Result<Option<ParsedAddress>, SsbcError> {
+            self.header_value_ranges(name)
+                .first()
+                .map(|range| Ok(self.owned_address(&self.parse_address(*range)?)))
+                .transpose()
+        };
+        let address_list = |name: &str| -> Result<Vec<ParsedAddress>, SsbcError> {
+            self.list_element_ranges(name)
+                .into_iter()
+                .filter(|element| self.get_str(*element) != "*")
+                .map(|element| Ok(self.owned_address(&self.parse_address(element)?)))
+                .collect()
+        };
 
-                return Some(unquoted.to_string());
+        let cseq = match self.cseq {
+            Some(_) => {
+                let (seq, method) = self.cseq_parts()?;
+                let method = method.parse().unwrap_or_else(|_| Method::UNKNOWN(method.to_string()));
+                Some(ParsedCSeq { seq, method })
             }
+            None => None,
+        };
+
+        Ok(ParsedMessage {
+            start_line,
+            via,
+            from: address_header("from")?,
+            to: address_header("to")?,
+            call_id: self.call_id(),
+            cseq,
+            max_forwards: self.max_forwards(),
+            contacts: address_list("contact")?,
+            routes: address_list("route")?,
+            record_routes: address_list("record-route")?,
+            headers: self
+                .as_header_pairs()
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), value.into_owned()))
+                .collect(),
+            body: self.body().map(str::to_string),
+        })
+    }
+
+    fn owned_params(&self, params: &ParamMap) -> Vec<(String, Option<String>)> {
+        self.params_ordered(params)
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value.map(str::to_string)))
+            .collect()
+    }
+
+    fn owned_uri(&self, uri: &SipUri) -> ParsedUri {
+        ParsedUri {
+            scheme: uri.scheme.clone(),
+            user: self.get_opt_str(uri.user_info).map(str::to_string),
+            host: self.get_opt_str(uri.host).map(str::to_string),
+            port: uri.port,
+            params: self.owned_params(&uri.params),
+            headers: self.get_opt_str(uri.headers).map(str::to_string),
         }
-        None
     }
 
-    /// Get all header values for a given header name
+    fn owned_address(&self, address: &Address) -> ParsedAddress {
+        ParsedAddress {
+            display_name: self.get_opt_str(address.display_name).map(str::to_string),
+            uri: self.owned_uri(&address.uri),
+            params: self.owned_params(&address.params),
+        }
+    }
+
+    /// Transport named by the last element of a Via sent-protocol
+    fn via_transport(&self, via: &Via) -> Transport {
+        let name = self.get_str(via.sent_protocol).rsplit('/').next().unwrap_or_default().trim();
+        name.parse().unwrap_or_else(|_| Transport::Other(name.to_string()))
+    }
+
+    /// Compute where responses to this request go (RFC 3261 Section 18.2.2, RFC 3581)
     ///
-    /// Returns a vector of all header values that match the given name,
-    /// useful for headers that can appear multiple times (like Via).
-    pub fn get_header_values(sip_message: &SipMessage, header_name: &str) -> Vec<String> {
-        let raw_message = sip_message.raw_message();
-        let header_lower = header_name.to_lowercase();
-        let mut values = Vec::new();
+    /// Reads the top Via: the host is `maddr` on an unreliable transport, else
+    /// `received`, else the sent-by host; the port is `rport` when it carries a
+    /// value, else the sent-by port, else the transport default. Over a reliable
+    /// transport the response should first be tried on the request's connection.
+    pub fn response_destination(&mut self) -> Result<ResponseDest, SsbcError> {
+        self.parse_without_validation()?;
+        if !self.is_request {
+            return Err(SsbcError::parse_error("Not a request message", None, None));
+        }
 
-        // RFC 3261 compact form mapping
-        let compact_form = match header_lower.as_str() {
-            "from" => Some("f"),
-            "to" => Some("t"),
-            "via" => Some("v"),
-            "contact" => Some("m"),
-            "call-id" => Some("i"),
-            "content-length" => Some("l"),
-            "content-type" => Some("c"),
-            "subject" => Some("s"),
-            _ => None,
+        let via = self
+            .via_list()?
+            .into_iter()
+            .next()
+            .ok_or_else(|| SsbcError::parse_error("Missing Via header", None, None))?;
+        let transport = self.via_transport(&via);
+        let param = |name| {
+            self.find_param(&via.params, name)
+                .flatten()
+                .map(|value| self.get_str(value).trim())
+                .filter(|value| !value.is_empty())
         };
 
-        let prefixes = if let Some(compact) = compact_form {
-            vec![format!("{}:", header_lower), format!("{}:", compact)]
-        } else {
-            vec![format!("{}:", header_lower)]
+        let maddr = param("maddr").filter(|_| !transport.is_reliable());
+        let host = maddr.or(param("received")).unwrap_or_else(|| via.host(&self.raw_message));
+        let host = host.strip_prefix('[').and_then(|host| host.strip_suffix(']')).unwrap_or(host);
+        let rport = param("rport").filter(|_| maddr.is_none());
+        let port = match rport {
+            Some(rport) => validation::parse_port(rport)?,
+            None => via.port(&self.raw_message).unwrap_or_else(|| transport.default_port()),
         };
 
-        for line in raw_message.lines() {
-            let line_lower = line.to_lowercase();
+        Ok(ResponseDest {
+            host: host.to_string(),
+            port,
+            transport,
+        })
+    }
+
+    /// Sent-by and branch of the top Via entry, failing when either is missing
+    fn top_via_sent_by_and_branch(&self) -> Result<(&str, &str), SsbcError> {
+        let via = self
+            .via_list()?
+            .into_iter()
+            .next()
+            .ok_or_else(|| SsbcError::parse_error("Missing Via header", None, None))?;
+        let branch = self
+            .find_param(&via.params, "branch")
+            .flatten()
+            .map(|branch| self.get_str(branch))
+            .filter(|branch| !branch.is_empty())
+            .ok_or_else(|| SsbcError::parse_error("Missing Via branch parameter", None, None))?;
+        Ok((self.get_str(via.sent_by).trim(), branch))
+    }
 
-            for prefix in &prefixes {
-                if line_lower.starts_with(prefix) {
-                    if let Some(colon_pos) = line.find(':') {
-                        values.push(line[colon_pos + 1..].trim().to_string());
-                        break; // Found match for this line, move to next line
-                    }
-                }
-            }
+    /// Run RFC 3261 conformance checks and collect every outcome
+    ///
+    /// Unlike [`SipMessage::parse_headers`] nothing aborts early: required
+    /// headers, tags, the Via branch cookie, Max-Forwards, Content-Length and the
+    /// CSeq method each get a pass, warn or fail item. A message that does not
+    /// parse at all yields a single failed `"parse"` item.
+    pub fn compliance_report(&mut self) -> validation::ComplianceReport {
+        use validation::ComplianceStatus::{Fail, Pass, Warn};
+
+        let mut report = validation::ComplianceReport::default();
+        if let Err(e) = self.parse_without_validation() {
+            report.push("parse", Fail, Some(e.to_string()));
+            return report;
         }
 
-        values
-    }
-}
+        let mut required = vec!["Via", "From", "To", "Call-ID", "CSeq"];
+        if self.is_request {
+            required.push("Max-Forwards");
+        }
+        let missing: Vec<&str> = required
+            .into_iter()
+            .filter(|name| self.header_value_ranges(name).is_empty())
+            .collect();
+        if missing.is_empty() {
+            report.push("required-headers", Pass, None);
+        } else {
+            report.push("required-headers", Fail, Some(format!("missing {}", missing.join(", "))));
+        }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+        if self.is_request {
+            match self.validate_tags() {
+                Ok(()) => report.push("tags", Pass, None),
+                Err(e) => report.push("tags", Fail, Some(e.to_string())),
+            }
+        }
 
-    #[test]
-    fn test_full_address_range() {
-        let address_str = "Alice <sip:alice@atlanta.com>;tag=1928301774";
-        let message = format!("INVITE sip:bob@example.com SIP/2.0\r\nFrom: {}\r\nTo: <sip:bob@example.com>\r\nCall-ID: 12345\r\nCSeq: 1 INVITE\r\nVia: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r\nMax-Forwards: 70\r\n\r\n", address_str);
+        // A branch without the cookie comes from an RFC 2543 element: usable, but
+        // transactions then have to be matched the legacy way
+        match self.via().map(|via| via.map(|via| via.params.clone()).unwrap_or_default()) {
+            Err(e) => report.push("branch-cookie", Fail, Some(e.to_string())),
+            Ok(via_params) => match self.find_param(&via_params, "branch") {
+                Some(value) if self.get_opt_str(value).is_some_and(|b| b.starts_with(BRANCH_MAGIC_COOKIE)) => {
+                    report.push("branch-cookie", Pass, None)
+                }
+                Some(value) => {
+                    let branch = self.get_opt_str(value).unwrap_or("");
+                    let detail = format!("branch {:?} lacks the {} cookie", branch, BRANCH_MAGIC_COOKIE);
+                    report.push("branch-cookie", Warn, Some(detail));
+                }
+                None => report.push("branch-cookie", Fail, Some("top Via has no branch".to_string())),
+            },
+        }
 
-        let mut sip_message = SipMessage::new_from_str(&message);
-        assert!(sip_message.parse_headers().is_ok());
+        if self.is_request {
+            match self.max_forwards() {
+                Some(_) => report.push("max-forwards", Pass, None),
+                None if self.header_value_ranges("max-forwards").is_empty() => {
+                    report.push("max-forwards", Fail, Some("missing".to_string()))
+                }
+                None => report.push("max-forwards", Fail, Some("not a number".to_string())),
+            }
+        }
 
-        // Get the From header and copy the ranges we need to test
-        let full_range;
-        let display_name;
-        let host;
+        let body_len = self.body().map_or(0, str::len);
+        match self.header_value_ranges("content-length").first() {
+            None => report.push("content-length", Warn, Some("missing (required over TCP/TLS)".to_string())),
+            Some(range) => match self.get_str(*range).trim().parse::<usize>() {
+                Ok(declared) if declared == body_len => report.push("content-length", Pass, None),
+                Ok(declared) => {
+                    let detail = format!("declares {} bytes, body has {}", declared, body_len);
+                    report.push("content-length", Fail, Some(detail));
+                }
+                Err(_) => report.push("content-length", Fail, Some("not a number".to_string())),
+            },
+        }
 
-        // Using a block to limit the scope of the borrow
-        {
-            let from = sip_message.from().unwrap().expect("From header not found");
-            full_range = from.full_range;
-            display_name = from.display_name;
-            host = from.uri.host;
+        if self.is_request {
+            match self.cseq_matches_request() {
+                Ok(true) => report.push("cseq-method", Pass, None),
+                Ok(false) => {
+                    let cseq = self.header_value_ranges("cseq").first().map_or("", |range| self.get_str(*range));
+                    let method = self.request_method().map(|method| method.to_string()).unwrap_or_default();
+                    let detail = format!("CSeq {:?} on a {} request", cseq, method);
+                    report.push("cseq-method", Fail, Some(detail));
+                }
+                Err(e) => report.push("cseq-method", Fail, Some(e.to_string())),
+            }
         }
 
-        // Now we can use get_str without borrowing issues
-        let actual_address = sip_message.get_str(full_range);
-        assert_eq!(
-            actual_address, address_str,
-            "Full address range does not match expected text"
-        );
+        report
+    }
 
-        // Verify that the display name is a subset of the full range
-        if let Some(name_range) = display_name {
-            assert!(
-                name_range.start >= full_range.start,
-                "Display name starts before full range"
-            );
-            assert!(
-                name_range.end <= full_range.end,
-                "Display name extends beyond full range"
+    /// Require a To tag on a 2xx that establishes a dialog (RFC 3261 Section 12.1.1)
+    fn validate_response_to_tag(&mut self) -> Result<(), SsbcError> {
+        let code = self.status_line()?.code;
+        let establishes_dialog = (200..300).contains(&code)
+            && matches!(
+                self.cseq_method()?,
+                Some(Method::INVITE | Method::SUBSCRIBE | Method::REFER)
             );
-            assert_eq!(
-                sip_message.get_str(name_range),
-                "Alice",
-                "Display name text doesn't match"
-            );
-        } else {
-            panic!("Display name not found");
+        if !establishes_dialog {
+            return Ok(());
         }
 
-        // Verify that host is within the full range
-        if let Some(host_range) = host {
-            assert!(
-                host_range.start >= full_range.start,
-                "Host starts before full range"
-            );
-            assert!(
-                host_range.end <= full_range.end,
-                "Host extends beyond full range"
-            );
-            assert_eq!(
-                sip_message.get_str(host_range),
-                "atlanta.com",
-                "Host text doesn't match"
-            );
+        let (_, to_tag) = self.dialog_tags()?;
+        if self.get_opt_str(to_tag).is_some_and(|tag| !tag.is_empty()) {
+            Ok(())
+        } else {
+            Err(SsbcError::parse_error(
+                "Dialog-establishing 2xx is missing the To tag",
+                None,
+                Some(code.to_string()),
+            ))
         }
     }
 
-    #[test]
-    fn test_full_via_range() {
-        let via_str = "SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds";
-        let message = format!("INVITE sip:bob@example.com SIP/2.0\r\nVia: {}\r\nFrom: Alice <sip:alice@atlanta.com>;tag=1928301774\r\nTo: <sip:bob@example.com>\r\nCall-ID: 12345\r\nCSeq: 1 INVITE\r\nMax-Forwards: 70\r\n\r\n", via_str);
+    /// Get this side's dialog tag, seen from the element receiving the message
+    ///
+    /// For a received request we act as UAS, so the local tag is the To tag;
+    /// for a received response we are the UAC and it is the From tag
+    /// (RFC 3261 Section 12.1). `None` while the dialog is still early, e.g. an
+    /// initial INVITE without a To tag.
+    pub fn local_tag(&mut self) -> Result<Option<&str>, SsbcError> {
+        let (from_tag, to_tag) = self.dialog_tags()?;
+        let tag = if self.is_request { to_tag } else { from_tag };
+        Ok(self.get_opt_str(tag).filter(|tag| !tag.is_empty()))
+    }
 
-        let mut sip_message = SipMessage::new_from_str(&message);
-        assert!(sip_message.parse_headers().is_ok());
+    /// Get the peer's dialog tag, the counterpart of [`SipMessage::local_tag`]
+    ///
+    /// The From tag of a received request, or the To tag of a received response
+    /// (`None` for a provisional response that hasn't created an early dialog).
+    pub fn remote_tag(&mut self) -> Result<Option<&str>, SsbcError> {
+        let (from_tag, to_tag) = self.dialog_tags()?;
+        let tag = if self.is_request { from_tag } else { to_tag };
+        Ok(self.get_opt_str(tag).filter(|tag| !tag.is_empty()))
+    }
 
-        // Get the Via header data we need for testing
-        let full_range;
-        let sent_protocol;
-        let sent_by;
-        let params;
+    /// Get the dialog named by the Target-Dialog header (RFC 4538)
+    ///
+    /// `local_tag` and `remote_tag` are the header's `local-tag` and
+    /// `remote-tag` parameters as written; both are mandatory.
+    pub fn target_dialog(&mut self) -> Result<Option<DialogId>, SsbcError> {
+        let Some((call_id, params)) = self.dialog_reference_header("Target-Dialog")? else {
+            return Ok(None);
+        };
+        Ok(Some(DialogId {
+            call_id,
+            local_tag: self.required_tag(&params, "Target-Dialog", "local-tag")?,
+            remote_tag: self.required_tag(&params, "Target-Dialog", "remote-tag")?,
+        }))
+    }
 
-        // Using a block to limit the scope of the borrow
-        {
-            let via = sip_message.via().unwrap().expect("Via header not found");
-            full_range = via.full_range;
-            sent_protocol = via.sent_protocol;
-            sent_by = via.sent_by;
-            params = via.params.clone(); // Clone the params map to avoid borrow issues
+    /// Get the dialog to replace from the Replaces header (RFC 3891)
+    pub fn replaces(&mut self) -> Result<Option<CallReference>, SsbcError> {
+        self.call_reference("Replaces")
+    }
+
+    /// Get the dialog to join from the Join header (RFC 3911)
+    pub fn join(&mut self) -> Result<Option<CallReference>, SsbcError> {
+        self.call_reference("Join")
+    }
+
+    /// Get whichever of Replaces or Join the request carries
+    ///
+    /// The two are mutually exclusive, so a request with both is an error.
+    pub fn call_control_target(&mut self) -> Result<Option<CallControlTarget>, SsbcError> {
+        match (self.replaces()?, self.join()?) {
+            (Some(_), Some(_)) => Err(SsbcError::parse_error(
+                "Replaces and Join cannot appear in the same request",
+                None,
+                None,
+            )),
+            (Some(dialog), None) => Ok(Some(CallControlTarget::Replaces(dialog))),
+            (None, Some(dialog)) => Ok(Some(CallControlTarget::Join(dialog))),
+            (None, None) => Ok(None),
         }
+    }
 
-        // Now we can use get_str without borrowing issues
-        let actual_via = sip_message.get_str(full_range);
-        assert_eq!(
-            actual_via, via_str,
-            "Full Via range doesn't match expected text"
-        );
+    /// Parse a Replaces or Join header, whose tags are both mandatory
+    fn call_reference(&mut self, header: &str) -> Result<Option<CallReference>, SsbcError> {
+        let Some((call_id, params)) = self.dialog_reference_header(header)? else {
+            return Ok(None);
+        };
+        Ok(Some(CallReference {
+            call_id,
+            to_tag: self.required_tag(&params, header, "to-tag")?,
+            from_tag: self.required_tag(&params, header, "from-tag")?,
+            early_only: self.find_param(&params, "early-only").is_some(),
+        }))
+    }
 
-        // Verify that the sent_protocol is within the full range
-        assert!(
-            sent_protocol.start >= full_range.start,
-            "Protocol starts before full range"
-        );
-        assert!(
-            sent_protocol.end <= full_range.end,
-            "Protocol extends beyond full range"
-        );
-        assert_eq!(
-            sip_message.get_str(sent_protocol),
-            "SIP/2.0/UDP",
-            "Protocol text doesn't match"
-        );
+    /// Split a `callid *(;param)` header into its Call-ID and parameters
+    fn dialog_reference_header(&mut self, header: &str) -> Result<Option<(String, ParamMap)>, SsbcError> {
+        self.parse_without_validation()?;
+        let Some(range) = self.header_value_ranges(header).first().copied() else {
+            return Ok(None);
+        };
 
-        // Verify that the sent_by is within the full range
-        assert!(
-            sent_by.start >= full_range.start,
-            "Sent-by starts before full range"
-        );
-        assert!(
-            sent_by.end <= full_range.end,
-            "Sent-by extends beyond full range"
-        );
-        assert_eq!(
-            sip_message.get_str(sent_by),
-            "pc33.atlanta.com",
-            "Sent-by text doesn't match"
-        );
+        let value = self.get_str(range);
+        let call_id_end = value.find(';').unwrap_or(value.len());
+        let call_id = value[..call_id_end].trim();
+        if call_id.is_empty() {
+            return Err(SsbcError::parse_error(
+                format!("{} lacks a Call-ID", header),
+                None,
+                Some(value.to_string()),
+            ));
+        }
 
-        // Verify that the branch parameter exists and is within the full range
-        let mut found_branch = false;
-        for (k, v) in params.iter() {
-            if sip_message.get_str(*k) == "branch" {
-                if let Some(branch) = v {
-                    found_branch = true;
-                    assert!(
-                        branch.start >= full_range.start,
-                        "Branch param starts before full range"
-                    );
-                    assert!(
-                        branch.end <= full_range.end,
-                        "Branch param extends beyond full range"
-                    );
-                    assert_eq!(
-                        sip_message.get_str(*branch),
-                        "z9hG4bK776asdhds",
-                        "Branch param text doesn't match"
-                    );
-                }
-            }
+        let mut params = HashMap::new();
+        if call_id_end < value.len() {
+            let params_range = TextRange::from_usize(range.start + call_id_end + 1, range.end);
+            self.parse_params(params_range, &mut params)?;
         }
+        Ok(Some((call_id.to_string(), params)))
+    }
 
-        assert!(found_branch, "Branch parameter not found");
+    /// Non-empty value of a mandatory tag parameter
+    fn required_tag(&self, params: &ParamMap, header: &str, name: &str) -> Result<String, SsbcError> {
+        self.find_param(params, name)
+            .flatten()
+            .map(|tag| self.get_str(tag).trim())
+            .filter(|tag| !tag.is_empty())
+            .map(str::to_string)
+            .ok_or_else(|| SsbcError::parse_error(format!("{} lacks {}", header, name), None, None))
     }
 
-    #[test]
-    fn test_parse_simple_message() {
-        let message = "INVITE sip:bob@example.com SIP/2.0\r\n\
-                       Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r\n\
-                       Max-Forwards: 70\r\n\
-                       To: Bob <sip:bob@example.com>\r\n\
-                       From: Alice <sip:alice@atlanta.com>;tag=1928301774\r\n\
-                       Call-ID: a84b4c76e66710@pc33.atlanta.com\r\n\
-                       CSeq: 314159 INVITE\r\n\
-                       Contact: <sip:alice@pc33.atlanta.com>\r\n\
-                       Content-Type: application/sdp\r\n\
-                       Content-Length: 0\r\n\
-                       \r\n";
+    /// Get the Refer-Sub header (RFC 4488): whether the REFER asks for an
+    /// implicit subscription
+    pub fn refer_sub(&mut self) -> Result<Option<bool>, SsbcError> {
+        self.parse_without_validation()?;
+        let Some(range) = self.header_value_ranges("refer-sub").first().copied() else {
+            return Ok(None);
+        };
 
-        let mut sip_message = SipMessage::new_from_str(message);
-        assert!(sip_message.parse_headers().is_ok());
-        assert!(sip_message.is_request());
-        assert_eq!(
-            sip_message.start_line(),
-            "INVITE sip:bob@example.com SIP/2.0"
-        );
+        let value = self.get_str(range);
+        let flag = value.split(';').next().unwrap_or_default().trim();
+        if flag.eq_ignore_ascii_case("true") {
+            Ok(Some(true))
+        } else if flag.eq_ignore_ascii_case("false") {
+            Ok(Some(false))
+        } else {
+            Err(SsbcError::parse_error("Refer-Sub must be true or false", None, Some(value.to_string())))
+        }
+    }
 
-        // Fix borrow checker issues by splitting up the borrow access
-        {
-            // Get and parse the From header
-            let sip_message_clone = sip_message.clone();
-            if let Ok(Some(from)) = sip_message.from() {
-                // Copy any needed data for assertions
-                let display_name = from.display_name;
-                let scheme = from.uri.scheme.clone();
-                let host = from.uri.host;
-                let user_info = from.uri.user_info;
-                let has_tag = from
-                    .params
-                    .iter()
-                    .any(|(k, _)| sip_message_clone.get_param_key(k) == "tag");
+    /// Get the P-Charging-Vector header (RFC 7315)
+    ///
+    /// Values may be tokens or quoted strings; quotes are removed. The
+    /// `icid-value` parameter is mandatory, unknown parameters are ignored.
+    pub fn charging_vector(&mut self) -> Result<Option<ChargingVector>, SsbcError> {
+        self.parse_without_validation()?;
+        let Some(range) = self.header_value_ranges("p-charging-vector").first().copied() else {
+            return Ok(None);
+        };
 
-                // Test the values
-                assert_eq!(sip_message_clone.get_opt_str(display_name), Some("Alice"));
-                assert_eq!(scheme, Scheme::SIP);
-                assert_eq!(sip_message_clone.get_opt_str(host), Some("atlanta.com"));
-                assert_eq!(sip_message_clone.get_opt_str(user_info), Some("alice"));
-                assert!(has_tag);
-            } else {
-                panic!("From header not found or couldn't be parsed");
-            }
+        let value = self.get_str(range);
+        let mut vector = ChargingVector::default();
+        let mut icid_value = None;
+        for (name, param) in Self::quoted_params(value)? {
+            let slot = match name.to_ascii_lowercase().as_str() {
+                "icid-value" => &mut icid_value,
+                "icid-generated-at" => &mut vector.icid_generated_at,
+                "orig-ioi" => &mut vector.orig_ioi,
+                "term-ioi" => &mut vector.term_ioi,
+                _ => continue,
+            };
+            slot.get_or_insert(param.unwrap_or_default());
         }
+        vector.icid_value = icid_value.filter(|icid| !icid.is_empty()).ok_or_else(|| {
+            SsbcError::parse_error("P-Charging-Vector lacks icid-value", None, Some(value.to_string()))
+        })?;
+        Ok(Some(vector))
     }
 
-    #[test]
-    fn test_parse_response_message() {
-        let message = "SIP/2.0 200 OK\r\n\
-                       Via: SIP/2.0/UDP server10.biloxi.com;branch=z9hG4bK4b43c2ff8.1\r\n\
-                       Via: SIP/2.0/UDP bigbox3.site3.atlanta.com;branch=z9hG4bK77ef4c2312983.1\r\n\
-                       Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds;received=192.0.2.1\r\n\
-                       To: Bob <sip:bob@biloxi.com>;tag=a6c85cf\r\n\
-                       From: Alice <sip:alice@atlanta.com>;tag=1928301774\r\n\
-                       Call-ID: a84b4c76e66710@pc33.atlanta.com\r\n\
-                       CSeq: 314159 INVITE\r\n\
-                       Contact: <sip:bob@192.0.2.4>\r\n\
-                       Content-Length: 0\r\n\
-                       \r\n";
+    /// Get the Accept-Contact preferences (RFC 3841), compact form `a`
+    ///
+    /// Returns one entry per comma-separated value across all header lines.
+    pub fn accept_contact(&mut self) -> Result<Vec<ContactPreference>, SsbcError> {
+        self.contact_preferences("accept-contact", true)
+    }
 
-        let mut sip_message = SipMessage::new_from_str(message);
-        assert!(sip_message.parse_headers().is_ok());
-        assert!(!sip_message.is_request());
-        assert_eq!(sip_message.start_line(), "SIP/2.0 200 OK");
+    /// Get the Reject-Contact preferences (RFC 3841), compact form `j`
+    ///
+    /// Reject-Contact carries no `require` or `explicit` flags; they stay false.
+    pub fn reject_contact(&mut self) -> Result<Vec<ContactPreference>, SsbcError> {
+        self.contact_preferences("reject-contact", false)
     }
 
-    #[test]
-    fn test_parse_with_body() {
-        let message = "INVITE sip:bob@biloxi.com SIP/2.0\r\n\
-                       Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r\n\
-                       Max-Forwards: 70\r\n\
-                       To: Bob <sip:bob@biloxi.com>\r\n\
-                       From: Alice <sip:alice@atlanta.com>;tag=1928301774\r\n\
-                       Call-ID: a84b4c76e66710@pc33.atlanta.com\r\n\
-                       CSeq: 314159 INVITE\r\n\
-                       Contact: <sip:alice@pc33.atlanta.com>\r\n\
-                       Content-Type: application/sdp\r\n\
-                       Content-Length: 142\r\n\
-                       \r\n\
-                       v=0\r\n\
-                       o=alice 53655765 2353687637 IN IP4 pc33.atlanta.com\r\n
-                       s=Session SDP\r\n
-                       c=IN IP4 pc33.atlanta.com\r\n
-                       t=0 0\r\n
-                       m=audio 49172 RTP/AVP 0\r\n\
-                       a=rtpmap:0 PCMU/8000\r\n";
+    fn contact_preferences(&mut self, header: &str, flags: bool) -> Result<Vec<ContactPreference>, SsbcError> {
+        let values = self.wildcard_values(header)?;
+        Ok(values
+            .into_iter()
+            .map(|params| {
+                let flag = |flag: &str| flags && params.iter().any(|(name, _)| name.eq_ignore_ascii_case(flag));
+                ContactPreference {
+                    require: flag("require"),
+                    explicit: flag("explicit"),
+                    features: FeatureTags::from_params(params.iter().map(|(name, value)| (*name, value.as_deref()))),
+                }
+            })
+            .collect())
+    }
 
-        let mut sip_message = SipMessage::new_from_str(message);
-        assert!(sip_message.parse_without_validation().is_ok());
+    /// Get the public GRUU assigned by the registrar (RFC 5627 Section 5.4)
+    ///
+    /// Read from the quoted `pub-gruu` parameter of the first Contact carrying
+    /// one, typically in a 200 OK to REGISTER. A UA with several bindings
+    /// should pick its own Contact by `+sip.instance` instead.
+    pub fn public_gruu(&mut self) -> Result<Option<String>, SsbcError> {
+        self.contact_gruu("pub-gruu")
+    }
 
-        // Check the body is present and correctly extracted
-        let body = sip_message.body();
-        assert!(body.is_some());
-        assert!(body.unwrap().contains("v=0"));
-        assert!(body.unwrap().contains("s=Session SDP"));
+    /// Get the temporary GRUU assigned by the registrar (RFC 5627 Section 5.4)
+    ///
+    /// Like [`SipMessage::public_gruu`], from the `temp-gruu` parameter.
+    pub fn temp_gruu(&mut self) -> Result<Option<String>, SsbcError> {
+        self.contact_gruu("temp-gruu")
     }
 
-    #[test]
-    fn test_uri_parsing() {
-        // Test different URI formats
-        let simple_uri = "sip:user@example.com";
-        let uri_with_params = "sip:user@example.com;transport=tcp";
-        let uri_with_port = "sip:user@example.com:5060";
-        let uri_with_headers = "sip:user@example.com?subject=project";
-        // Use a valid URI format with percent-encoded password instead of colon
-        let uri_with_everything =
-            "sips:user%40password@example.com:5061;transport=tls?header1=value1&header2=value2";
+    /// Unquoted GRUU in a Contact parameter, checked to be a SIP URI with `gr`
+    fn contact_gruu(&mut self, param: &str) -> Result<Option<String>, SsbcError> {
+        self.parse_without_validation()?;
+        for element in self.list_element_ranges("contact") {
+            let text = self.get_str(element);
+            let params = match text.find('>') {
+                Some(end) => &text[end + 1..],
+                None => text.find(';').map_or("", |start| &text[start..]),
+            };
+            let params = Self::quoted_params(params.trim_start().strip_prefix(';').unwrap_or(params))?;
+            let Some((_, value)) = params.into_iter().find(|(name, _)| name.eq_ignore_ascii_case(param)) else {
+                continue;
+            };
 
-        // Helper function to parse URI
-        fn parse_uri(uri_str: &str) -> Result<SipUri, SsbcError> {
-            let range = TextRange::from_usize(0, uri_str.len());
-            let message = SipMessage::new_from_str(uri_str);
-            message.parse_uri(range)
+            let gruu = value.ok_or_else(|| {
+                SsbcError::parse_error(format!("Contact parameter {} has no value", param), None, None)
+            })?;
+            validation::validate_uri(&gruu, self.limits().max_uri_depth)?;
+            let is_sip = gruu.get(..4).is_some_and(|scheme| scheme.eq_ignore_ascii_case("sip:"))
+                || gruu.get(..5).is_some_and(|scheme| scheme.eq_ignore_ascii_case("sips:"));
+            let uri_params = gruu.split('?').next().unwrap_or_default().split(';').skip(1);
+            let has_gr = uri_params
+                .map(|uri_param| uri_param.split('=').next().unwrap_or_default().trim())
+                .any(|name| name.eq_ignore_ascii_case("gr"));
+            if !is_sip || !has_gr {
+                return Err(SsbcError::parse_error(
+                    format!("Contact parameter {} is not a GRUU", param),
+                    None,
+                    Some(gruu),
+                ));
+            }
+            return Ok(Some(gruu));
         }
+        Ok(None)
+    }
 
-        // Test simple URI
-        let uri = parse_uri(simple_uri).expect("Failed to parse simple URI");
-        assert_eq!(uri.scheme, Scheme::SIP);
+    /// Parameters of each `*;param...` element of a list-valued header, values unquoted
+    ///
+    /// Shared by the RFC 3841 caller preference headers and Feature-Caps.
+    fn wildcard_values(&mut self, header: &str) -> Result<Vec<QuotedParams<'_>>, SsbcError> {
+        self.parse_without_validation()?;
+        let mut values = Vec::new();
+        for range in self.header_value_ranges(header) {
+            let value = self.get_str(range);
+            for (start, end) in Self::split_list_elements(value) {
+                let element = &value[start..end];
+                let params = element.strip_prefix('*').ok_or_else(|| {
+                    SsbcError::parse_error(
+                        format!("{} value must start with '*'", header),
+                        None,
+                        Some(element.to_string()),
+                    )
+                })?;
+                values.push(Self::quoted_params(params.trim_start().strip_prefix(';').unwrap_or(params))?);
+            }
+        }
+        Ok(values)
+    }
 
-        // Test URI with parameters
-        let uri = parse_uri(uri_with_params).expect("Failed to parse URI with params");
-        assert_eq!(uri.scheme, Scheme::SIP);
+    /// Get the Feature-Caps indicators (RFC 6809), one set per header value
+    pub fn feature_caps(&mut self) -> Result<Vec<FeatureTags>, SsbcError> {
+        let values = self.wildcard_values("feature-caps")?;
+        Ok(values
+            .into_iter()
+            .map(|params| FeatureTags::from_params(params.iter().map(|(name, value)| (*name, value.as_deref()))))
+            .collect())
+    }
+
+    /// Info package names listed in Recv-Info (RFC 6086), in order
+    ///
+    /// Package parameters are dropped. Empty when the header is absent, empty
+    /// (no packages accepted) or the message does not parse.
+    pub fn recv_info_packages(&mut self) -> Vec<&str> {
+        if self.parse_without_validation().is_err() {
+            return Vec::new();
+        }
+        self.option_tags("recv-info")
+            .into_iter()
+            .filter_map(|package| package.split(';').next())
+            .map(str::trim)
+            .filter(|package| !package.is_empty())
+            .collect()
+    }
+
+    /// Get the P-Charging-Function-Addresses header (RFC 7315)
+    ///
+    /// Collects every `ccf` and `ecf` parameter in order, across all lines.
+    pub fn charging_function_addresses(&mut self) -> Result<Option<ChargingFunctionAddresses>, SsbcError> {
+        self.parse_without_validation()?;
+        let ranges = self.header_value_ranges("p-charging-function-addresses");
+        if ranges.is_empty() {
+            return Ok(None);
+        }
+
+        let mut addresses = ChargingFunctionAddresses::default();
+        for range in ranges {
+            for (name, param) in Self::quoted_params(self.get_str(range))? {
+                let list = match name.to_ascii_lowercase().as_str() {
+                    "ccf" => &mut addresses.ccf,
+                    "ecf" => &mut addresses.ecf,
+                    _ => continue,
+                };
+                list.extend(param.filter(|address| !address.is_empty()));
+            }
+        }
+        Ok(Some(addresses))
+    }
+
+    /// Split `name=value;name="quoted;value"` into names and unquoted values
+    ///
+    /// Unlike the generic parameter parser, semicolons inside quoted strings
+    /// don't split and `\` escapes in quoted values are resolved.
+    fn quoted_params(value: &str) -> Result<QuotedParams<'_>, SsbcError> {
+        let mut params = Vec::new();
+        let mut rest = value;
+        while !rest.trim().is_empty() {
+            let (name, after_name) = match rest.find(['=', ';']) {
+                Some(pos) => (&rest[..pos], &rest[pos..]),
+                None => (rest, ""),
+            };
+            let name = name.trim();
+            if name.is_empty() {
+                return Err(SsbcError::parse_error("Empty parameter name", None, Some(value.to_string())));
+            }
+
+            let Some(after_equals) = after_name.strip_prefix('=') else {
+                params.push((name, None));
+                rest = after_name.strip_prefix(';').unwrap_or(after_name);
+                continue;
+            };
+
+            let after_equals = after_equals.trim_start();
+            let (param, after_value) = if let Some(quoted) = after_equals.strip_prefix('"') {
+                let mut unquoted = String::new();
+                let mut chars = quoted.char_indices();
+                let mut end = None;
+                while let Some((i, c)) = chars.next() {
+                    match c {
+                        '\\' => unquoted.extend(chars.next().map(|(_, escaped)| escaped)),
+                        '"' => {
+                            end = Some(i + 1);
+                            break;
+                        }
+                        _ => unquoted.push(c),
+                    }
+                }
+                let end = end.ok_or_else(|| {
+                    SsbcError::parse_error("Unterminated quoted string", None, Some(value.to_string()))
+                })?;
+                (unquoted, &quoted[end..])
+            } else {
+                let end = after_equals.find(';').unwrap_or(after_equals.len());
+                (after_equals[..end].trim().to_string(), &after_equals[end..])
+            };
+
+            let after_value = after_value.trim_start();
+            rest = match after_value.strip_prefix(';') {
+                Some(next) => next,
+                None if after_value.is_empty() => after_value,
+                None => {
+                    return Err(SsbcError::parse_error(
+                        "Expected ';' between parameters",
+                        None,
+                        Some(value.to_string()),
+                    ))
+                }
+            };
+            params.push((name, Some(param)));
+        }
+        Ok(params)
+    }
+
+    /// Parse From and To and return the ranges of their tag values
+    fn dialog_tags(&mut self) -> Result<(Option<TextRange>, Option<TextRange>), SsbcError> {
+        self.from()?;
+        self.to()?;
+        Ok((self.address_tag(self.from.as_ref()), self.address_tag(self.to.as_ref())))
+    }
+
+    /// Find the tag parameter value of an already parsed address header
+    fn address_tag(&self, header: Option<&HeaderValue>) -> Option<TextRange> {
+        match header? {
+            HeaderValue::Address(address) => self.find_param(&address.params, "tag")?,
+            _ => None,
+        }
+    }
+
+    /// Look up a parameter by case-insensitive name
+    ///
+    /// Returns `Some(None)` for a parameter present without a value.
+    fn find_param(&self, params: &ParamMap, name: &str) -> Option<Option<TextRange>> {
+        params
+            .iter()
+            .find(|(key, _)| self.get_param_key(key).eq_ignore_ascii_case(name))
+            .map(|(_, value)| *value)
+    }
+
+    /// Get the branch of the top Via, the primary transaction key
+    ///
+    /// Returns `Ok(None)` when there is no Via or it carries no branch. A branch
+    /// that is empty or lacks the RFC 3261 magic cookie is rejected, as it can't
+    /// be used to match transactions reliably.
+    pub fn transaction_branch(&mut self) -> Result<Option<&str>, SsbcError> {
+        self.via()?;
+        let branch = match self.via_headers.first() {
+            Some(HeaderValue::Via(via)) => match self.find_param(&via.params, "branch") {
+                Some(value) => value,
+                None => return Ok(None),
+            },
+            _ => return Ok(None),
+        };
+
+        let branch = self.get_opt_str(branch).unwrap_or("");
+        if branch.is_empty() {
+            return Err(SsbcError::parse_error(
+                "Via branch parameter is empty",
+                None,
+                None,
+            ));
+        }
+        if !branch.starts_with(BRANCH_MAGIC_COOKIE) {
+            return Err(SsbcError::parse_error(
+                "Via branch lacks the RFC 3261 magic cookie",
+                None,
+                Some(branch.to_string()),
+            ));
+        }
+
+        Ok(Some(branch))
+    }
+
+    /// Check that no branch appears twice in the Via stack
+    ///
+    /// A repeated branch means a loop or a crafted message. Returns `false` as
+    /// well when the Via stack can't be parsed, since uniqueness can't be
+    /// vouched for; use [`SipMessage::duplicate_via_branch`] to log the culprit.
+    pub fn via_branches_unique(&mut self) -> bool {
+        matches!(self.duplicate_via_branch(), Ok(None))
+    }
+
+    /// First branch value found twice in the Via stack, top to bottom
+    ///
+    /// Comma-joined Via values count as separate entries. Vias without a
+    /// branch are ignored.
+    pub fn duplicate_via_branch(&mut self) -> Result<Option<&str>, SsbcError> {
+        self.parse_without_validation()?;
+
+        let mut seen: Vec<&str> = Vec::new();
+        for via in self.via_list()? {
+            let Some(branch) = self.find_param(&via.params, "branch").flatten() else {
+                continue;
+            };
+            let branch = self.get_str(branch);
+            if seen.contains(&branch) {
+                return Ok(Some(branch));
+            }
+            seen.push(branch);
+        }
+        Ok(None)
+    }
+
+    /// Via values to place on a response to this request, top first
+    ///
+    /// Comma-joined Via lines are split into one value per entry and folded
+    /// lines are unfolded, so each value fits a Via header of its own; the
+    /// order matches the request (RFC 3261 Section 8.2.6.2). Every entry must
+    /// parse as a Via.
+    pub fn response_via_stack(&mut self) -> Result<Vec<String>, SsbcError> {
+        self.parse_without_validation()?;
+        self.via_list()?;
+
+        let mut stack = Vec::new();
+        for value in &self.via_headers {
+            let text = self.get_str(Self::header_value_range(value));
+            for (start, end) in Self::split_list_elements(text) {
+                stack.push(header_utils::unfold(&text[start..end]).into_owned());
+            }
+        }
+        Ok(stack)
+    }
+
+    /// Every Via entry, splitting comma-joined values
+    pub(crate) fn via_list(&self) -> Result<Vec<Via>, SsbcError> {
+        let mut vias = Vec::new();
+        for value in &self.via_headers {
+            let range = Self::header_value_range(value);
+            for (start, end) in Self::split_list_elements(self.get_str(range)) {
+                let element = TextRange::from_usize(range.start + start, range.start + end);
+                vias.push(self.parse_via(element)?);
+            }
+        }
+        Ok(vias)
+    }
+
+    /// Get Call-ID as string
+    pub fn call_id_str(&self) -> Option<&str> {
+        match self.call_id.as_ref()? {
+            HeaderValue::Raw(range) => Some(self.get_str(*range)),
+            _ => None,
+        }
+    }
+
+    /// Get the Subject header value (compact form `s`), if present
+    pub fn subject(&self) -> Option<&str> {
+        self.header_value_ranges("subject").first().map(|range| self.get_str(*range))
+    }
+
+    /// Get the Organization header value, if present
+    pub fn organization(&self) -> Option<&str> {
+        self.header_value_ranges("organization").first().map(|range| self.get_str(*range))
+    }
+
+    /// Compare the Call-ID against `other` as RFC 3261 requires
+    ///
+    /// Call-IDs are compared byte for byte (Section 20.8), so case matters in
+    /// both the local part and the host after `@`.
+    pub fn call_id_matches(&self, other: &str) -> bool {
+        self.call_id_str()
+            .is_some_and(|call_id| call_id.trim() == other.trim())
+    }
+
+    /// Compare the Call-ID against `other`, ignoring case after the `@` only
+    ///
+    /// Interop mode for peers that change the case of the host part. The part
+    /// before `@` is still compared case-sensitively.
+    pub fn lenient_call_id_matches(&self, other: &str) -> bool {
+        let call_id = match self.call_id_str() {
+            Some(call_id) => call_id.trim(),
+            None => return false,
+        };
+        match (call_id.split_once('@'), other.trim().split_once('@')) {
+            (Some((local, host)), Some((other_local, other_host))) => {
+                local == other_local && host.eq_ignore_ascii_case(other_host)
+            }
+            (None, None) => call_id == other.trim(),
+            _ => false,
+        }
+    }
+
+    /// Add this method to parse Event header for SUBSCRIBE/NOTIFY
+    pub fn parse_event(&mut self) -> Result<Option<&EventPackageData>, SsbcError> {
+        // Find the Event header
+        let event_header = self.headers.iter().find(|(name_range, _)| {
+            let name = self.get_str(*name_range).to_lowercase();
+            name == "event" || name == "o" // 'o' is compact form
+        });
+
+        if let Some((_, HeaderValue::Raw(range))) = event_header {
+            let event_str = self.get_str(*range);
+
+            // Split by semicolon to separate event type from parameters
+            let (event_type, params_str) = if let Some(semi_pos) = event_str.find(';') {
+                (
+                    TextRange::from_usize(range.start as usize, (range.start as usize) + semi_pos),
+                    Some(&event_str[semi_pos + 1..]),
+                )
+            } else {
+                (*range, None)
+            };
+
+            // Create event package
+            let mut event = EventPackageData {
+                event_type,
+                event_params: HashMap::new(),
+            };
+
+            // Parse parameters if present
+            if let Some(params) = params_str {
+                let params_range = TextRange::from_usize(
+                    (range.start as usize) + event_str.len() - params.len(),
+                    range.end as usize,
+                );
+                self.parse_params(params_range, &mut event.event_params)?;
+            }
+
+            // Store and return
+            self.event = Some(event);
+            return Ok(self.event.as_ref());
+        }
+
+        Ok(None)
+    }
+
+    /// Get the Call-ID header value from the dedicated field
+    pub fn call_id(&self) -> Option<String> {
+        if let Some(ref call_id_header) = self.call_id {
+            if let HeaderValue::Raw(range) = call_id_header {
+                Some(range.as_str(&self.raw_message).to_string())
+            } else {
+                None
+            }
+        } else {
+            None
+        }
+    }
+    
+    /// Get the Max-Forwards header value
+    ///
+    /// Max-Forwards is request-only (RFC 3261 Section 8.1.1.6): responses
+    /// normally omit it and get `None`, as do values that are not a number.
+    pub fn max_forwards(&self) -> Option<u32> {
+        if let Some(ref max_forwards_header) = self.max_forwards {
+            if let HeaderValue::Raw(range) = max_forwards_header {
+                range.as_str(&self.raw_message).trim().parse().ok()
+            } else {
+                None
+            }
+        } else {
+            None
+        }
+    }
+}
+
+/// Generic SIP header extraction utilities
+pub mod header_utils {
+    use crate::SipMessage;
+    use std::borrow::Cow;
+
+    /// Extract header value by name, supporting both long and compact forms
+    ///
+    /// This function searches the parsed headers by name, automatically handling
+    /// RFC 3261 compact forms (e.g., "f" for "from", "t" for "to"). Folded
+    /// values are returned on a single line. The message must already be parsed.
+    ///
+    /// # Examples
+    /// ```
+    /// use ssbc::{SipMessage, header_utils::extract_header_value};
+    ///
+    /// let sip_msg = "INVITE sip:bob@example.com SIP/2.0\r\nVia: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r\nFrom: Alice <sip:alice@example.com>;tag=123\r\nTo: Bob <sip:bob@example.com>\r\nCall-ID: call123@example.com\r\nCSeq: 1 INVITE\r\n\r\n";
+    /// let mut message = SipMessage::new_from_str(sip_msg);
+    /// message.parse_without_validation().unwrap();
+    ///
+    /// // Works with both "From" and "f" headers
+    /// let from_value = extract_header_value(&message, "from");
+    /// assert!(from_value.is_some());
+    /// ```
+    pub fn extract_header_value(sip_message: &SipMessage, header_name: &str) -> Option<String> {
+        sip_message
+            .header_value_ranges(header_name)
+            .first()
+            .map(|range| unfold(sip_message.get_str(*range)).into_owned())
+    }
+
+    /// Extract parameter value from a header value string
+    ///
+    /// Parses SIP header parameters in the format "param=value" from
+    /// a header value, handling quoted and unquoted values.
+    ///
+    /// # Examples
+    /// ```
+    /// use ssbc::header_utils::extract_header_parameter;
+    ///
+    /// let from_header = "Alice <sip:alice@example.com>;tag=abc123";
+    /// let tag = extract_header_parameter(from_header, "tag"); // Some("abc123")
+    ///
+    /// let via_header = "SIP/2.0/UDP host.com;branch=z9hG4bK-123";
+    /// let branch = extract_header_parameter(via_header, "branch"); // Some("z9hG4bK-123")
+    /// ```
+    pub fn extract_header_parameter(header_value: &str, param_name: &str) -> Option<String> {
+        let param_lower = param_name.to_lowercase();
+        let param_prefix = format!("{}=", param_lower);
+
+        for part in header_value.split(';') {
+            let part_trimmed = part.trim();
+            let part_lower = part_trimmed.to_lowercase();
+
+            if part_lower.starts_with(&param_prefix) {
+                // Extract the value part after the "param=" prefix
+                let value_start = part_trimmed.find('=').unwrap() + 1;
+                let value = part_trimmed[value_start..].trim();
+
+                // Remove quotes if present
+                let unquoted = if value.starts_with('"') && value.ends_with('"') && value.len() > 1
+                {
+                    &value[1..value.len() - 1]
+                } else {
+                    value
+                };
+
+                return Some(unquoted.to_string());
+            }
+        }
+        None
+    }
+
+    /// Get all header values for a given header name
+    ///
+    /// Returns a vector of all header values that match the given name,
+    /// useful for headers that can appear multiple times (like Via).
+    pub fn get_header_values(sip_message: &SipMessage, header_name: &str) -> Vec<String> {
+        sip_message
+            .header_value_ranges(header_name)
+            .iter()
+            .map(|range| unfold(sip_message.get_str(*range)).into_owned())
+            .collect()
+    }
+
+    /// Join folded continuation lines into a single-line value
+    ///
+    /// Each fold, together with the whitespace around it, becomes a single SP
+    /// (RFC 3261 §7.3.1).
+    pub(crate) fn unfold(value: &str) -> Cow<'_, str> {
+        let value = value.trim();
+        if !value.contains("\r\n") {
+            return Cow::Borrowed(value);
+        }
+        let mut unfolded = String::with_capacity(value.len());
+        for (i, line) in value.split("\r\n").enumerate() {
+            if i == 0 {
+                unfolded.push_str(line.trim_end_matches([' ', '\t']));
+            } else {
+                unfolded.push(' ');
+                unfolded.push_str(line.trim_matches([' ', '\t']));
+            }
+        }
+        Cow::Owned(unfolded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_full_address_range() {
+        let address_str = "Alice <sip:alice@atlanta.com>;tag=1928301774";
+        let message = format!("INVITE sip:bob@example.com SIP/2.0\r\nFrom: {}\r\nTo: <sip:bob@example.com>\r\nCall-ID: 12345\r\nCSeq: 1 INVITE\r\nVia: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r\nMax-Forwards: 70\r\n\r\n", address_str);
+
+        let mut sip_message = SipMessage::new_from_str(&message);
+        assert!(sip_message.parse_headers().is_ok());
+
+        // Get the From header and copy the ranges we need to test
+        let full_range;
+        let display_name;
+        let host;
+
+        // Using a block to limit the scope of the borrow
+        {
+            let from = sip_message.from().unwrap().expect("From header not found");
+            full_range = from.full_range;
+            display_name = from.display_name;
+            host = from.uri.host;
+        }
+
+        // Now we can use get_str without borrowing issues
+        let actual_address = sip_message.get_str(full_range);
+        assert_eq!(
+            actual_address, address_str,
+            "Full address range does not match expected text"
+        );
+
+        // Verify that the display name is a subset of the full range
+        if let Some(name_range) = display_name {
+            assert!(
+                name_range.start >= full_range.start,
+                "Display name starts before full range"
+            );
+            assert!(
+                name_range.end <= full_range.end,
+                "Display name extends beyond full range"
+            );
+            assert_eq!(
+                sip_message.get_str(name_range),
+                "Alice",
+                "Display name text doesn't match"
+            );
+        } else {
+            panic!("Display name not found");
+        }
+
+        // Verify that host is within the full range
+        if let Some(host_range) = host {
+            assert!(
+                host_range.start >= full_range.start,
+                "Host starts before full range"
+            );
+            assert!(
+                host_range.end <= full_range.end,
+                "Host extends beyond full range"
+            );
+            assert_eq!(
+                sip_message.get_str(host_range),
+                "atlanta.com",
+                "Host text doesn't match"
+            );
+        }
+    }
+
+    #[test]
+    fn test_full_via_range() {
+        let via_str = "SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds";
+        let message = format!("INVITE sip:bob@example.com SIP/2.0\r\nVia: {}\r\nFrom: Alice <sip:alice@atlanta.com>;tag=1928301774\r\nTo: <sip:bob@example.com>\r\nCall-ID: 12345\r\nCSeq: 1 INVITE\r\nMax-Forwards: 70\r\n\r\n", via_str);
+
+        let mut sip_message = SipMessage::new_from_str(&message);
+        assert!(sip_message.parse_headers().is_ok());
+
+        // Get the Via header data we need for testing
+        let full_range;
+        let sent_protocol;
+        let sent_by;
+        let params;
+
+        // Using a block to limit the scope of the borrow
+        {
+            let via = sip_message.via().unwrap().expect("Via header not found");
+            full_range = via.full_range;
+            sent_protocol = via.sent_protocol;
+            sent_by = via.sent_by;
+            params = via.params.clone(); // Clone the params map to avoid borrow issues
+        }
+
+        // Now we can use get_str without borrowing issues
+        let actual_via = sip_message.get_str(full_range);
+        assert_eq!(
+            actual_via, via_str,
+            "Full Via range doesn't match expected text"
+        );
+
+        // Verify that the sent_protocol is within the full range
+        assert!(
+            sent_protocol.start >= full_range.start,
+            "Protocol starts before full range"
+        );
+        assert!(
+            sent_protocol.end <= full_range.end,
+            "Protocol extends beyond full range"
+        );
+        assert_eq!(
+            sip_message.get_str(sent_protocol),
+            "SIP/2.0/UDP",
+            "Protocol text doesn't match"
+        );
+
+        // Verify that the sent_by is within the full range
+        assert!(
+            sent_by.start >= full_range.start,
+            "Sent-by starts before full range"
+        );
+        assert!(
+            sent_by.end <= full_range.end,
+            "Sent-by extends beyond full range"
+        );
+        assert_eq!(
+            sip_message.get_str(sent_by),
+            "pc33.atlanta.com",
+            "Sent-by text doesn't match"
+        );
+
+        // Verify that the branch parameter exists and is within the full range
+        let mut found_branch = false;
+        for (k, v) in params.iter() {
+            if sip_message.get_str(*k) == "branch" {
+                if let Some(branch) = v {
+                    found_branch = true;
+                    assert!(
+                        branch.start >= full_range.start,
+                        "Branch param starts before full range"
+                    );
+                    assert!(
+                        branch.end <= full_range.end,
+                        "Branch param extends beyond full range"
+                    );
+                    assert_eq!(
+                        sip_message.get_str(*branch),
+                        "z9hG4bK776asdhds",
+                        "Branch param text doesn't match"
+                    );
+                }
+            }
+        }
+
+        assert!(found_branch, "Branch parameter not found");
+    }
+
+    #[test]
+    fn test_parse_simple_message() {
+        let message = "INVITE sip:bob@example.com SIP/2.0\r\n\
+                       Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r\n\
+                       Max-Forwards: 70\r\n\
+                       To: Bob <sip:bob@example.com>\r\n\
+                       From: Alice <sip:alice@atlanta.com>;tag=1928301774\r\n\
+                       Call-ID: a84b4c76e66710@pc33.atlanta.com\r\n\
+                       CSeq: 314159 INVITE\r\n\
+                       Contact: <sip:alice@pc33.atlanta.com>\r\n\
+                       Content-Type: application/sdp\r\n\
+                       Content-Length: 0\r\n\
+                       \r\n";
+
+        let mut sip_message = SipMessage::new_from_str(message);
+        assert!(sip_message.parse_headers().is_ok());
+        assert!(sip_message.is_request());
+        assert_eq!(
+            sip_message.start_line(),
+            "INVITE sip:bob@example.com SIP/2.0"
+        );
+
+        // Fix borrow checker issues by splitting up the borrow access
+        {
+            // Get and parse the From header
+            let sip_message_clone = sip_message.clone();
+            if let Ok(Some(from)) = sip_message.from() {
+                // Copy any needed data for assertions
+                let display_name = from.display_name;
+                let scheme = from.uri.scheme.clone();
+                let host = from.uri.host;
+                let user_info = from.uri.user_info;
+                let has_tag = from
+                    .params
+                    .iter()
+                    .any(|(k, _)| sip_message_clone.get_param_key(k) == "tag");
+
+                // Test the values
+                assert_eq!(sip_message_clone.get_opt_str(display_name), Some("Alice"));
+                assert_eq!(scheme, Scheme::SIP);
+                assert_eq!(sip_message_clone.get_opt_str(host), Some("atlanta.com"));
+                assert_eq!(sip_message_clone.get_opt_str(user_info), Some("alice"));
+                assert!(has_tag);
+            } else {
+                panic!("From header not found or couldn't be parsed");
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_response_message() {
+        let message = "SIP/2.0 200 OK\r\n\
+                       Via: SIP/2.0/UDP server10.biloxi.com;branch=z9hG4bK4b43c2ff8.1\r\n\
+                       Via: SIP/2.0/UDP bigbox3.site3.atlanta.com;branch=z9hG4bK77ef4c2312983.1\r\n\
+                       Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds;received=192.0.2.1\r\n\
+                       To: Bob <sip:bob@biloxi.com>;tag=a6c85cf\r\n\
+                       From: Alice <sip:alice@atlanta.com>;tag=1928301774\r\n\
+                       Call-ID: a84b4c76e66710@pc33.atlanta.com\r\n\
+                       CSeq: 314159 INVITE\r\n\
+                       Contact: <sip:bob@192.0.2.4>\r\n\
+                       Content-Length: 0\r\n\
+                       \r\n";
+
+        let mut sip_message = SipMessage::new_from_str(message);
+        assert!(sip_message.parse_headers().is_ok());
+        assert!(!sip_message.is_request());
+        assert_eq!(sip_message.start_line(), "SIP/2.0 200 OK");
+    }
+
+    #[test]
+    fn test_parse_with_body() {
+        let message = "INVITE sip:bob@biloxi.com SIP/2.0\r\n\
+                       Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r\n\
+                       Max-Forwards: 70\r\n\
+                       To: Bob <sip:bob@biloxi.com>\r\n\
+                       From: Alice <sip:alice@atlanta.com>;tag=1928301774\r\n\
+                       Call-ID: a84b4c76e66710@pc33.atlanta.com\r\n\
+                       CSeq: 314159 INVITE\r\n\
+                       Contact: <sip:alice@pc33.atlanta.com>\r\n\
+                       Content-Type: application/sdp\r\n\
+                       Content-Length: 142\r\n\
+                       \r\n\
+                       v=0\r\n\
+                       o=alice 53655765 2353687637 IN IP4 pc33.atlanta.com\r\n
+                       s=Session SDP\r\n
+                       c=IN IP4 pc33.atlanta.com\r\n
+                       t=0 0\r\n
+                       m=audio 49172 RTP/AVP 0\r\n\
+                       a=rtpmap:0 PCMU/8000\r\n";
+
+        let mut sip_message = SipMessage::new_from_str(message);
+        assert!(sip_message.parse_without_validation().is_ok());
+
+        // Check the body is present and correctly extracted
+        let body = sip_message.body();
+        assert!(body.is_some());
+        assert!(body.unwrap().contains("v=0"));
+        assert!(body.unwrap().contains("s=Session SDP"));
+    }
+
+    #[test]
+    fn test_uri_parsing() {
+        // Test different URI formats
+        let simple_uri = "sip:user@example.com";
+        let uri_with_params = "sip:user@example.com;transport=tcp";
+        let uri_with_port = "sip:user@example.com:5060";
+        let uri_with_headers = "sip:user@example.com?subject=project";
+        // Use a valid URI format with percent-encoded password instead of colon
+        let uri_with_everything =
+            "sips:user%40password@example.com:5061;transport=tls?header1=value1&header2=value2";
+
+        // Helper function to parse URI
+        fn parse_uri(uri_str: &str) -> Result<SipUri, SsbcError> {
+            let range = TextRange::from_usize(0, uri_str.len());
+            let message = SipMessage::new_from_str(uri_str);
+            message.parse_uri(range)
+        }
+
+        // Test simple URI
+        let uri = parse_uri(simple_uri).expect("Failed to parse simple URI");
+        assert_eq!(uri.scheme, Scheme::SIP);
+
+        // Test URI with parameters
+        let uri = parse_uri(uri_with_params).expect("Failed to parse URI with params");
+        assert_eq!(uri.scheme, Scheme::SIP);
         assert!(!uri.params.is_empty());
 
-        // Test URI with port
-        let uri = parse_uri(uri_with_port).expect("Failed to parse URI with port");
-        assert_eq!(uri.port, Some(5060));
+        // Test URI with port
+        let uri = parse_uri(uri_with_port).expect("Failed to parse URI with port");
+        assert_eq!(uri.port, Some(5060));
+
+        // Test URI with headers
+        let uri = parse_uri(uri_with_headers).expect("Failed to parse URI with headers");
+        assert!(uri.headers.is_some());
+
+        // Test URI with everything
+        let uri = parse_uri(uri_with_everything).expect("Failed to parse complex URI");
+        assert_eq!(uri.scheme, Scheme::SIPS);
+        assert!(uri.port.is_some());
+        assert_eq!(uri.port, Some(5061));
+    }
+
+    #[test]
+    fn test_via_header_parsing() {
+        let via_header = "SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds";
+        let range = TextRange::from_usize(0, via_header.len());
+        let message = SipMessage::new_from_str(via_header);
+
+        let via = message
+            .parse_via(range)
+            .expect("Failed to parse Via header");
+
+        assert_eq!(message.get_str(via.sent_protocol), "SIP/2.0/UDP");
+        assert_eq!(message.get_str(via.sent_by), "pc33.atlanta.com");
+        assert!(!via.params.is_empty());
+
+        // Check branch parameter
+        let branch_param = via
+            .params
+            .iter()
+            .find(|(k, _)| message.get_param_key(k) == "branch");
+        assert!(branch_param.is_some());
+        let (_, branch_value) = branch_param.unwrap();
+        assert_eq!(
+            message.get_param_value(branch_value),
+            Some("z9hG4bK776asdhds")
+        );
+    }
+
+    #[test]
+    fn test_address_header_parsing() {
+        let message = SipMessage::new_from_str("Bob <sip:bob@biloxi.com>;tag=a6c85cf");
+        let range = TextRange::from_usize(0, "Bob <sip:bob@biloxi.com>;tag=a6c85cf".len());
+
+        let address = message
+            .parse_address(range)
+            .expect("Failed to parse address");
+
+        assert_eq!(message.get_opt_str(address.display_name), Some("Bob"));
+        assert_eq!(address.uri.scheme, Scheme::SIP);
+        assert_eq!(message.get_opt_str(address.uri.user_info), Some("bob"));
+        assert_eq!(message.get_opt_str(address.uri.host), Some("biloxi.com"));
+
+        // Check tag parameter
+        let tag_param = address
+            .params
+            .iter()
+            .find(|(k, _)| message.get_param_key(k) == "tag");
+        assert!(tag_param.is_some());
+        let (_, tag_value) = tag_param.unwrap();
+        assert_eq!(message.get_param_value(tag_value), Some("a6c85cf"));
+    }
+
+    #[test]
+    fn test_parse_error_with_position() {
+        // We need a message with an invalid URI where the scheme is invalid
+        let invalid_uri = "INVITE xyz:bob@biloxi.com SIP/2.0\r\nVia: SIP/2.0/UDP pc33.atlanta.com\r\nTo: Bob <xyz:bob@biloxi.com>\r\nFrom: Alice <sip:alice@atlanta.com>;tag=1928301774\r\nCall-ID: a84b4c76e66710@pc33.atlanta.com\r\nCSeq: 314159 INVITE\r\nMax-Forwards: 70\r\n\r\n";
+        let mut message = SipMessage::new_from_str(invalid_uri);
+
+        // Parsing the message should work at the message level
+        assert!(message.parse_without_validation().is_ok());
+
+        // But trying to access a header that needs URI parsing should fail
+        let result = message.to();
+        assert!(
+            result.is_err(),
+            "Expected an error when parsing the To header with invalid URI"
+        );
+
+        if let Err(error) = result {
+            match error {
+                SsbcError::ParseError {
+                    message: _,
+                    position,
+                    context: _,
+                } => {
+                    assert!(position.is_none()); // Position is now None since we use Option<(usize, usize)>
+                }
+                _ => panic!("Expected ParseError"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_max_forwards_parsing() {
+        // Test parsing of Max-Forwards header
+        let message = "\
+INVITE sip:bob@biloxi.com SIP/2.0\r
+Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r
+To: Bob <sip:bob@biloxi.com>\r
+From: Alice <sip:alice@atlanta.com>;tag=1928301774\r
+Call-ID: a84b4c76e66710@pc33.atlanta.com\r
+CSeq: 314159 INVITE\r
+Max-Forwards: 70\r
+\r
+";
+        let mut sip_message = SipMessage::new_from_str(message);
+        assert!(sip_message.parse_without_validation().is_ok());
+
+        // Check Max-Forwards header
+        match &sip_message.max_forwards {
+            Some(HeaderValue::Raw(range)) => {
+                assert_eq!(sip_message.get_str(*range), "70");
+            }
+            _ => panic!("Max-Forwards header not parsed correctly"),
+        }
+    }
+
+    #[test]
+    fn test_mime_type_header_parsing() {
+        // Test parsing of Content-Type header
+        let message = "\
+INVITE sip:bob@biloxi.com SIP/2.0\r
+Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r
+To: Bob <sip:bob@biloxi.com>\r
+From: Alice <sip:alice@atlanta.com>;tag=1928301774\r
+Call-ID: a84b4c76e66710@pc33.atlanta.com\r
+CSeq: 314159 INVITE\r
+Max-Forwards: 70\r
+Content-Type: application/sdp\r
+\r
+";
+        let mut sip_message = SipMessage::new_from_str(message);
+        assert!(sip_message.parse_without_validation().is_ok());
+
+        // Find Content-Type header
+        let content_type = sip_message.headers.iter().find(|(name_range, _)| {
+            sip_message.get_str(*name_range).to_lowercase() == "content-type"
+        });
+
+        assert!(content_type.is_some());
+        if let Some((_, HeaderValue::Raw(value_range))) = content_type {
+            assert_eq!(sip_message.get_str(*value_range), "application/sdp");
+        } else {
+            panic!("Content-Type header not found or not parsed correctly");
+        }
+    }
+
+    #[test]
+    fn test_body_with_content_length() {
+        // Test body parsing using Content-Length header
+        let message = "\
+INVITE sip:bob@biloxi.com SIP/2.0\r
+Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r
+To: Bob <sip:bob@biloxi.com>\r
+From: Alice <sip:alice@atlanta.com>;tag=1928301774\r
+Call-ID: a84b4c76e66710@pc33.atlanta.com\r
+CSeq: 314159 INVITE\r
+Max-Forwards: 70\r
+Content-Length: 11\r
+\r
+Hello World";
+
+        let mut sip_message = SipMessage::new_from_str(message);
+        assert!(sip_message.parse_without_validation().is_ok());
+
+        // Check the body
+        assert_eq!(sip_message.body().unwrap(), "Hello World");
+    }
+
+    #[test]
+    fn test_duplicate_header_detection() {
+        // Test message with duplicate headers that should be rejected
+        let message_with_duplicate_to = "\
+INVITE sip:bob@biloxi.com SIP/2.0\r
+Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r
+To: Bob <sip:bob@biloxi.com>\r
+To: Bob <sip:bob@example.com>\r
+From: Alice <sip:alice@atlanta.com>;tag=1928301774\r
+Call-ID: a84b4c76e66710@pc33.atlanta.com\r
+CSeq: 314159 INVITE\r
+Max-Forwards: 70\r
+\r
+";
+        let mut sip_message = SipMessage::new_from_str(message_with_duplicate_to);
+        let result = sip_message.parse_headers();
+        assert!(result.is_err());
+
+        match result {
+            Err(SsbcError::ParseError {
+                context: None,
+                message,
+                position: _,
+            }) => {
+                assert!(message.contains("Duplicate To header"));
+            }
+            _ => panic!("Expected InvalidHeader error for duplicate To"),
+        }
+
+        // Test message with duplicate From header
+        let message_with_duplicate_from = "\
+INVITE sip:bob@biloxi.com SIP/2.0\r
+Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r
+To: Bob <sip:bob@biloxi.com>\r
+From: Alice <sip:alice@atlanta.com>;tag=1928301774\r
+From: Carol <sip:carol@example.com>;tag=987654321\r
+Call-ID: a84b4c76e66710@pc33.atlanta.com\r
+CSeq: 314159 INVITE\r
+Max-Forwards: 70\r
+\r
+";
+        let mut sip_message = SipMessage::new_from_str(message_with_duplicate_from);
+        let result = sip_message.parse_headers();
+        assert!(result.is_err());
+
+        match result {
+            Err(SsbcError::ParseError {
+                context: None,
+                message,
+                position: _,
+            }) => {
+                assert!(message.contains("Duplicate From header"));
+            }
+            _ => panic!("Expected InvalidHeader error for duplicate From"),
+        }
+
+        // Test message with duplicate CSeq header
+        let message_with_duplicate_cseq = "\
+INVITE sip:bob@biloxi.com SIP/2.0\r
+Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r
+To: Bob <sip:bob@biloxi.com>\r
+From: Alice <sip:alice@atlanta.com>;tag=1928301774\r
+Call-ID: a84b4c76e66710@pc33.atlanta.com\r
+CSeq: 314159 INVITE\r
+CSeq: 1 ACK\r
+Max-Forwards: 70\r
+\r
+";
+        let mut sip_message = SipMessage::new_from_str(message_with_duplicate_cseq);
+        let result = sip_message.parse_headers();
+        assert!(result.is_err());
+
+        match result {
+            Err(SsbcError::ParseError {
+                context: None,
+                message,
+                position: _,
+            }) => {
+                assert!(message.contains("Duplicate CSeq header"));
+            }
+            _ => panic!("Expected InvalidHeader error for duplicate CSeq"),
+        }
+    }
+
+    #[test]
+    fn test_error_in_from_header() {
+        // Test that an error in the From header is correctly reported
+        let message = "\
+INVITE sip:bob@biloxi.com SIP/2.0\r
+Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r
+To: Bob <sip:bob@biloxi.com>\r
+From: Alice <malformed-uri>\r
+Call-ID: a84b4c76e66710@pc33.atlanta.com\r
+CSeq: 314159 INVITE\r
+Max-Forwards: 70\r
+\r
+";
+        let mut message = SipMessage::new_from_str(message);
+
+        // Parsing the message should work at the message level
+        assert!(message.parse_without_validation().is_ok());
+
+        // But trying to access a header that needs URI parsing should fail
+        let result = message.from();
+        assert!(result.is_err());
+
+        // Check error details
+        match result {
+            Err(SsbcError::ParseError {
+                message,
+                position,
+                context,
+            }) => {
+                // Position might be None for URI parsing errors
+                println!(
+                    "ParseError: message={}, position={:?}, context={:?}",
+                    message, position, context
+                );
+                // Just verify we got a parse error
+                assert!(
+                    message.contains("uri")
+                        || message.contains("URI")
+                        || message.contains("Invalid")
+                );
+            }
+            _ => panic!("Expected ParseError, got: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_event_package_parsing() {
+        // Test SUBSCRIBE with Event header
+        let message = "\
+SUBSCRIBE sip:bob@biloxi.com SIP/2.0\r
+Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r
+To: Bob <sip:bob@biloxi.com>\r
+From: Alice <sip:alice@atlanta.com>;tag=1928301774\r
+Call-ID: a84b4c76e66710@pc33.atlanta.com\r
+CSeq: 314159 SUBSCRIBE\r
+Max-Forwards: 70\r
+Event: presence;id=123;expire=3600\r
+\r
+";
+        let mut sip_message = SipMessage::new_from_str(message);
+        assert!(sip_message.parse_without_validation().is_ok());
+
+        // Parse the Event header
+        let event_result = sip_message.parse_event();
+        assert!(event_result.is_ok());
+        let event = event_result.unwrap();
+        assert!(event.is_some());
+    }
+
+    #[test]
+    fn test_folded_header_enhancements() {
+        // Test with multiple folded lines
+        let message = "\
+INVITE sip:bob@biloxi.com SIP/2.0\r
+Via: SIP/2.0/UDP pc33.atlanta.com;\r
+ branch=z9hG4bK4b43c2ff8.1\r
+To: Bob <sip:bob@biloxi.com>\r
+From: Alice \r
+\t<sip:alice@atlanta.com>;\r
+ tag=1928301774\r
+Call-ID: a84b4c76e66710@pc33.atlanta.com\r
+CSeq: 314159 INVITE\r
+Max-Forwards: 70\r
+\r
+";
+        let mut sip_message = SipMessage::new_from_str(message);
+        assert!(sip_message.parse_without_validation().is_ok());
+
+        // Verify that Via header was parsed successfully
+        let via_result = sip_message.via();
+        assert!(via_result.is_ok());
+        assert!(via_result.unwrap().is_some());
+    }
+
+    #[test]
+    fn test_reject_folded_headers() {
+        let message = "\
+INVITE sip:bob@biloxi.com SIP/2.0\r
+Via: SIP/2.0/UDP pc33.atlanta.com;\r
+ branch=z9hG4bK4b43c2ff8.1\r
+To: Bob <sip:bob@biloxi.com>\r
+From: Alice \r
+\t<sip:alice@atlanta.com>;\r
+ tag=1928301774\r
+Call-ID: a84b4c76e66710@pc33.atlanta.com\r
+CSeq: 314159 INVITE\r
+Max-Forwards: 70\r
+\r
+";
+        let mut limits = ParserLimits::default();
+        assert!(!limits.reject_folded_headers);
+        let mut sip_message = SipMessage::new_from_str_with_limits(message, limits.clone());
+        assert!(sip_message.parse_headers().is_ok());
+
+        limits.reject_folded_headers = true;
+        let mut sip_message = SipMessage::new_from_str_with_limits(message, limits.clone());
+        match sip_message.parse_headers() {
+            Err(SsbcError::ParseError { message, .. }) => assert!(message.contains("Folded")),
+            other => panic!("Expected folded header error, got {:?}", other),
+        }
+
+        // Unfolded messages are unaffected by the flag
+        let unfolded = message
+            .replace(";\r\n branch", ";branch")
+            .replace("Alice \r\n\t<sip", "Alice <sip")
+            .replace(";\r\n tag", ";tag");
+        let mut sip_message = SipMessage::new_from_str_with_limits(&unfolded, limits);
+        assert!(sip_message.parse_headers().is_ok());
+    }
+
+    #[test]
+    fn test_method_parsing() {
+        // Test parsing methods from request line
+        let message = "\
+SUBSCRIBE sip:bob@biloxi.com SIP/2.0\r
+Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r
+To: Bob <sip:bob@biloxi.com>\r
+From: Alice <sip:alice@atlanta.com>;tag=1928301774\r
+Call-ID: a84b4c76e66710@pc33.atlanta.com\r
+CSeq: 314159 SUBSCRIBE\r
+Max-Forwards: 70\r
+Event: presence\r
+\r
+";
+        let mut sip_message = SipMessage::new_from_str(message);
+        assert!(sip_message.parse_without_validation().is_ok());
+
+        // Check request method
+        let method = sip_message.request_method();
+        assert!(method.is_some());
+        assert_eq!(method.unwrap(), Method::SUBSCRIBE);
+    }
+
+    #[test]
+    fn test_multiple_via_collection() {
+        // Test message with multiple Via headers
+        let message = "\
+INVITE sip:bob@biloxi.com SIP/2.0\r
+Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r
+Via: SIP/2.0/UDP bigbox3.site3.atlanta.com;branch=z9hG4bK77ef4c2312983.1\r
+Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds;received=192.0.2.1\r
+To: Bob <sip:bob@biloxi.com>\r
+From: Alice <sip:alice@atlanta.com>;tag=1928301774\r
+Call-ID: a84b4c76e66710@pc33.atlanta.com\r
+CSeq: 314159 INVITE\r
+Max-Forwards: 70\r
+\r
+";
+        let mut sip_message = SipMessage::new_from_str(message);
+        assert!(sip_message.parse_without_validation().is_ok());
+
+        // Just check that we have 3 Via headers
+        let via_count = sip_message.via_headers.len();
+        assert_eq!(via_count, 3);
+
+        // Verify the first via is accessible
+        let via_result = sip_message.via();
+        assert!(via_result.is_ok());
+        assert!(via_result.unwrap().is_some());
+    }
+
+    #[test]
+    fn test_required_header_validation() {
+        // Test message missing required headers
+        let message_missing_to = "\
+INVITE sip:bob@biloxi.com SIP/2.0\r
+Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r
+From: Alice <sip:alice@atlanta.com>;tag=1928301774\r
+Call-ID: a84b4c76e66710@pc33.atlanta.com\r
+CSeq: 314159 INVITE\r
+Max-Forwards: 70\r
+\r
+";
+        let mut sip_message = SipMessage::new_from_str(message_missing_to);
+        let result = sip_message.parse_headers();
+        assert!(result.is_err());
+
+        match result {
+            Err(SsbcError::ParseError {
+                context: None,
+                message,
+                position: _,
+            }) => {
+                assert!(message.contains("Missing required To header"));
+            }
+            _ => panic!("Expected InvalidMessage error for missing To header"),
+        }
+
+        // Test message missing Max-Forwards - now optional, should succeed
+        let message_missing_max_forwards = "\
+INVITE sip:bob@biloxi.com SIP/2.0\r
+Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r
+To: Bob <sip:bob@biloxi.com>\r
+From: Alice <sip:alice@atlanta.com>;tag=1928301774\r
+Call-ID: a84b4c76e66710@pc33.atlanta.com\r
+CSeq: 314159 INVITE\r
+\r
+";
+        let mut sip_message = SipMessage::new_from_str(message_missing_max_forwards);
+        let result = sip_message.parse_headers();
+        // Max-Forwards is now optional - proxies add it if missing
+        assert!(result.is_ok());
+        
+        // Verify we can access max_forwards and it returns None
+        assert_eq!(sip_message.max_forwards(), None);
+    }
+
+    #[test]
+    fn test_uri_character_validation() {
+        // Test URI with valid characters
+        let uri_str = "sip:alice@atlanta.com";
+        let range = TextRange::new(0, uri_str.len());
+        let message = SipMessage::new_from_str(uri_str);
+        assert!(message.parse_uri(range).is_ok());
+
+        // Test URI with invalid characters in user part
+        let invalid_uri = "sip:alice[123]@atlanta.com";
+        let range = TextRange::from_usize(0, invalid_uri.len());
+        let message = SipMessage::new_from_str(invalid_uri);
+        let result = message.parse_uri(range);
+        assert!(result.is_err());
+
+        // Test URI with valid percent-encoded characters
+        let encoded_uri = "sip:alice%20smith@atlanta.com".to_string();
+        let range = TextRange::from_usize(0, encoded_uri.len());
+        let message = SipMessage::new_from_str(&encoded_uri);
+        assert!(message.parse_uri(range).is_ok());
+
+        // Test URI with invalid percent-encoding
+        let bad_encoded_uri = "sip:alice%2@atlanta.com";
+        let range = TextRange::from_usize(0, bad_encoded_uri.len());
+        let message = SipMessage::new_from_str(bad_encoded_uri);
+        let result = message.parse_uri(range);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_sip_response_parsing() {
+        // Test parsing a SIP response instead of a request
+        let message = "\
+SIP/2.0 200 OK\r
+Via: SIP/2.0/UDP server10.biloxi.com;branch=z9hG4bK4442ba5c\r
+To: Bob <sip:bob@biloxi.com>;tag=a6c85cf\r
+From: Alice <sip:alice@atlanta.com>;tag=1928301774\r
+Call-ID: a84b4c76e66710@pc33.atlanta.com\r
+CSeq: 314159 INVITE\r
+\r
+";
+        let mut sip_message = SipMessage::new_from_str(message);
+        assert!(sip_message.parse_without_validation().is_ok());
+
+        // Check that it's correctly identified as a response
+        assert!(!sip_message.is_request());
+
+        // Check the start line
+        assert_eq!(sip_message.start_line(), "SIP/2.0 200 OK");
+    }
+
+    #[test]
+    fn test_text_range_operations() {
+        // Test TextRange operations more thoroughly
+        let range1 = TextRange::new(10, 20);
+        let range2 = TextRange::new(10, 20);
+        let range3 = TextRange::new(5, 15);
+
+        // Test equality
+        assert_eq!(range1, range2);
+        assert_ne!(range1, range3);
+
+        // Test length
+        assert_eq!(range1.len(), 10);
+
+        // Test is_empty
+        assert!(!range1.is_empty());
+        assert!(TextRange::new(10, 10).is_empty());
+
+        // Test string extraction
+        let text = "0123456789abcdefghijklmnopqrstuvwxyz";
+        assert_eq!(range1.as_str(text), "abcdefghij");
+    }
+
+    #[test]
+    fn test_basic_from_header() {
+        let input = "From: \"Alice\" <sip:alice@example.com>";
+        let mut message = SipMessage::new_from_str(input);
+        message
+            .process_header_line(TextRange::from_usize(0, input.len()))
+            .unwrap();
+
+        // Check that we can extract the From header
+        let address = message.from().unwrap().expect("From header not found");
+
+        // Create local copies of all the values we need
+        let display_name = address.display_name.unwrap().as_str(&message.raw_message);
+
+        // Check display name
+        assert_eq!(display_name, "Alice");
+    }
+
+    #[test]
+    fn test_complex_from_header() {
+        let input = "From: \"Alice Smith\" <sip:alice@example.com:5060;transport=tcp>;tag=1234;expires=3600";
+        let mut message = SipMessage::new_from_str(input);
+        message
+            .process_header_line(TextRange::from_usize(0, input.len()))
+            .unwrap();
+
+        // In test methods, we need to clone to avoid borrowing conflicts
+        // This is only in tests, not performance-critical code
+        let raw_message = message.raw_message.clone();
+
+        // Now we can safely get the From header and use raw_message in closures
+        let from = message.from().unwrap().expect("From header not found");
+
+        // Extract all values using the as_str method
+        let display_name = from.display_name.map(|range| range.as_str(&raw_message));
+        let scheme = from.uri.scheme.clone();
+        let user_info = from.uri.user_info.map(|range| range.as_str(&raw_message));
+        let host = from.uri.host.map(|range| range.as_str(&raw_message));
+        let port = from.uri.port;
+
+        // Create parameter maps using as_str
+        let mut uri_params_map = HashMap::new();
+        for (k, v) in &from.uri.params {
+            let key = k.as_str(&raw_message);
+            let value = v.as_ref().map(|r| r.as_str(&raw_message));
+            uri_params_map.insert(key, value);
+        }
+
+        let mut header_params_map = HashMap::new();
+        for (k, v) in &from.params {
+            let key = k.as_str(&raw_message);
+            let value = v.as_ref().map(|r| r.as_str(&raw_message));
+            header_params_map.insert(key, value);
+        }
+
+        // Now test with the data we've extracted
+        assert_eq!(display_name, Some("Alice Smith"));
+        assert_eq!(scheme, Scheme::SIP);
+        assert_eq!(user_info, Some("alice"));
+        assert_eq!(host, Some("example.com"));
+        assert_eq!(port, Some(5060));
+
+        // Check URI parameters
+        assert_eq!(uri_params_map.get("transport").unwrap(), &Some("tcp"));
+
+        // Check header parameters
+        assert_eq!(header_params_map.get("tag").unwrap(), &Some("1234"));
+        assert_eq!(header_params_map.get("expires").unwrap(), &Some("3600"));
+    }
+
+    #[test]
+    fn test_escaped_characters() {
+        let input = "From: \"Alice\\\"Quotes\\\"\" <sip:alice@example.com>";
+        let mut message = SipMessage::new_from_str(input);
+        message
+            .process_header_line(TextRange::from_usize(0, input.len()))
+            .unwrap();
+
+        // In test methods, we need to clone to avoid borrowing conflicts
+        // This is only in tests, not performance-critical code
+        let raw_message = message.raw_message.clone();
+
+        // Check that we can extract the From header
+        let address = message.from().unwrap().expect("From header not found");
+
+        // Get display name directly using as_str
+        let display_name = address.display_name.map(|r| r.as_str(&raw_message));
+
+        // Check display name with escaped quotes
+        assert_eq!(display_name, Some("Alice\\\"Quotes\\\""));
+    }
+
+    #[test]
+    fn test_unquoted_display_name() {
+        let input = "From: John Doe <sip:john@example.com>";
+        let mut message = SipMessage::new_from_str(input);
+        message
+            .process_header_line(TextRange::from_usize(0, input.len()))
+            .unwrap();
+
+        // In test methods, we need to clone to avoid borrowing conflicts
+        // This is only in tests, not performance-critical code
+        let raw_message = message.raw_message.clone();
+
+        // Check that we can extract the From header
+        let address = message.from().unwrap().expect("From header not found");
+
+        // Get display name directly using as_str
+        let display_name = address.display_name.map(|r| r.as_str(&raw_message));
+
+        // Check display name
+        assert_eq!(display_name, Some("John Doe"));
+    }
+
+    #[test]
+    fn test_header_params() {
+        let input = "Contact: <sip:user@host.com>;expires=3600;q=0.8";
+        let mut message = SipMessage::new_from_str(input);
+        message
+            .process_header_line(TextRange::from_usize(0, input.len()))
+            .unwrap();
+
+        // In test methods, we need to clone to avoid borrowing conflicts
+        // This is only in tests, not performance-critical code
+        let raw_message = message.raw_message.clone();
+
+        // Get the Contact header directly using contact() method
+        let contact = message
+            .contact()
+            .unwrap()
+            .expect("Contact header not found");
+
+        // For consistency with our test, save as address
+        let address = contact;
+
+        // Create parameter map directly using as_str
+        let mut params_map = HashMap::new();
+        for (k, v) in &address.params {
+            let key = k.as_str(&raw_message);
+            let value = v.as_ref().map(|r| r.as_str(&raw_message));
+            params_map.insert(key, value);
+        }
+
+        // Check parameters
+        assert_eq!(params_map.get("expires").unwrap(), &Some("3600"));
+        assert_eq!(params_map.get("q").unwrap(), &Some("0.8"));
+    }
+
+    #[test]
+    fn test_multiple_header_params() {
+        let input = "Contact: <sip:alice@example.com>;expires=3600;q=0.8;priority=1";
+        let mut message = SipMessage::new_from_str(input);
+        message
+            .process_header_line(TextRange::from_usize(0, input.len()))
+            .unwrap();
+
+        // In test methods, we need to clone to avoid borrowing conflicts
+        // This is only in tests, not performance-critical code
+        let raw_message = message.raw_message.clone();
+
+        // Get the Contact header directly using contact() method
+        let contact = message
+            .contact()
+            .unwrap()
+            .expect("Contact header not found");
+
+        // For consistency with our test, save as address
+        let address = contact;
+
+        // Create parameter map directly using as_str
+        let mut params_map = HashMap::new();
+        for (k, v) in &address.params {
+            let key = k.as_str(&raw_message);
+            let value = v.as_ref().map(|r| r.as_str(&raw_message));
+            params_map.insert(key, value);
+        }
+
+        // Check parameters
+        assert_eq!(params_map.get("expires").unwrap(), &Some("3600"));
+        assert_eq!(params_map.get("q").unwrap(), &Some("0.8"));
+        assert_eq!(params_map.get("priority").unwrap(), &Some("1"));
+    }
+
+    #[test]
+    fn test_quoted_params() {
+        let input = "To: <sip:bob@example.com>;reason=\"moved temporarily\";info=\"contact info\"";
+        let mut message = SipMessage::new_from_str(input);
+        message
+            .process_header_line(TextRange::from_usize(0, input.len()))
+            .unwrap();
+
+        // In test methods, we need to clone to avoid borrowing conflicts
+        // This is only in tests, not performance-critical code
+        let raw_message = message.raw_message.clone();
+
+        // Get the To header directly using to() method
+        let to_address = message.to().unwrap().expect("To header not found");
+
+        // For consistency with our test, save as address
+        let address = to_address;
+
+        // Create parameter map directly using as_str
+        let mut params_map = HashMap::new();
+        for (k, v) in &address.params {
+            let key = k.as_str(&raw_message);
+            let value = v.as_ref().map(|r| r.as_str(&raw_message));
+            params_map.insert(key, value);
+        }
+
+        // Check quoted parameters - our implementation doesn't strip quotes
+        assert_eq!(
+            params_map.get("reason").unwrap(),
+            &Some("\"moved temporarily\"")
+        );
+        assert_eq!(params_map.get("info").unwrap(), &Some("\"contact info\""));
+    }
+
+    #[test]
+    fn test_no_user_info() {
+        let input = "Contact: <sip:example.com>";
+        let mut message = SipMessage::new_from_str(input);
+        message
+            .process_header_line(TextRange::from_usize(0, input.len()))
+            .unwrap();
+
+        // In test methods, we need to clone to avoid borrowing conflicts
+        // This is only in tests, not performance-critical code
+        let raw_message = message.raw_message.clone();
+
+        // Get the Contact header directly using contact() method
+        let contact = message
+            .contact()
+            .unwrap()
+            .expect("Contact header not found");
+
+        // Extract URI host value directly with as_str
+        let host_value = contact.uri.host.map(|r| r.as_str(&raw_message));
+
+        // Check URI components
+        assert_eq!(host_value, Some("example.com"));
+    }
+
+    #[test]
+    fn test_tel_uri() {
+        let input = "Contact: <tel:+1-212-555-0123;phone-context=example.com>";
+        let mut message = SipMessage::new_from_str(input);
+        message
+            .process_header_line(TextRange::from_usize(0, input.len()))
+            .unwrap();
+
+        // In test methods, we need to clone to avoid borrowing conflicts
+        // This is only in tests, not performance-critical code
+        let raw_message = message.raw_message.clone();
+
+        // Get the Contact header directly using contact() method
+        let contact = message
+            .contact()
+            .unwrap()
+            .expect("Contact header not found");
+
+        // For consistency with our test, save as address
+        let address = contact;
+
+        // Extract values
+        let scheme = address.uri.scheme.clone();
+        let user_info_value = address.uri.user_info.map(|r| r.as_str(&raw_message));
+        let mut params_map = HashMap::new();
+
+        // Create a custom parameter map
+        for (k, v) in &address.uri.params {
+            let key = k.as_str(&raw_message);
+            let value = v.as_ref().map(|r| r.as_str(&raw_message));
+            params_map.insert(key, value);
+        }
+
+        // Check URI components
+        assert_eq!(scheme, Scheme::TEL);
+        assert_eq!(user_info_value, Some("+1-212-555-0123"));
+
+        // Check parameters
+        assert_eq!(
+            params_map.get("phone-context").unwrap(),
+            &Some("example.com")
+        );
+    }
+
+    #[test]
+    fn test_via_header() {
+        let input = "Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds;received=192.0.2.1";
+        let mut message = SipMessage::new_from_str(input);
+        message
+            .process_header_line(TextRange::from_usize(0, input.len()))
+            .unwrap();
+
+        // In test methods, we need to clone to avoid borrowing conflicts
+        // This is only in tests, not performance-critical code
+        let raw_message = message.raw_message.clone();
+
+        // Get the Via header
+        let via = message.via().unwrap().expect("Via header not found");
+
+        // Extract values using as_str
+        let sent_protocol_str = via.sent_protocol.as_str(&raw_message);
+        let sent_by_str = via.sent_by.as_str(&raw_message);
+
+        // Create a custom map of the parameters using as_str
+        let mut params_map = HashMap::new();
+        for (k, v) in &via.params {
+            let key = k.as_str(&raw_message);
+            let value = v.as_ref().map(|r| r.as_str(&raw_message));
+            params_map.insert(key, value);
+        }
+
+        // Now check the values
+        assert_eq!(sent_protocol_str, "SIP/2.0/UDP");
+        assert_eq!(sent_by_str, "pc33.atlanta.com");
+        assert_eq!(params_map.get("branch").unwrap(), &Some("z9hG4bK776asdhds"));
+        assert_eq!(params_map.get("received").unwrap(), &Some("192.0.2.1"));
+    }
+
+    #[test]
+    fn test_escaped_uri() {
+        let input = "Contact: <sip:user%20name@host.com;transport=tcp?subject=Meeting%20Request>";
+        let mut message = SipMessage::new_from_str(input);
+        message
+            .process_header_line(TextRange::from_usize(0, input.len()))
+            .unwrap();
+
+        // In test methods, we need to clone to avoid borrowing conflicts
+        // This is only in tests, not performance-critical code
+        let raw_message = message.raw_message.clone();
+
+        // Get the Contact header directly using contact() method
+        let contact = message
+            .contact()
+            .unwrap()
+            .expect("Contact header not found");
+
+        // For consistency with our test, save as address
+        let address = contact;
+
+        // Extract the needed values with as_str
+        let user_info_value = address.uri.user_info.map(|r| r.as_str(&raw_message));
+        let headers_value = address.uri.headers.map(|r| r.as_str(&raw_message));
+
+        // Create a custom parameter map
+        let mut params_map = HashMap::new();
+        for (k, v) in &address.uri.params {
+            let key = k.as_str(&raw_message);
+            let value = v.as_ref().map(|r| r.as_str(&raw_message));
+            params_map.insert(key, value);
+        }
+
+        // Check URI components with raw values instead of decoded values
+        assert_eq!(user_info_value, Some("user%20name"));
+
+        // Check parameters
+        assert_eq!(params_map.get("transport").unwrap(), &Some("tcp"));
+
+        // Check headers - our implementation doesn't decode the URI headers
+        assert_eq!(headers_value, Some("subject=Meeting%20Request"));
+    }
+
+    #[test]
+    fn test_multiple_headers_record_route() {
+        let input = "Record-Route: <sip:proxy1.example.com;lr>, <sip:proxy2.example.com;lr>";
+        let mut message = SipMessage::new_from_str(input);
+        message
+            .process_header_line(TextRange::from_usize(0, input.len()))
+            .unwrap();
+
+        // Manually extract the Record-Route headers
+        let record_route_headers: Vec<_> = message
+            .headers
+            .iter()
+            .filter(|(name, _)| message.get_str(*name).to_lowercase() == "record-route")
+            .map(|(_, value)| value)
+            .collect();
+
+        // We should have at least one header entry
+        assert!(!record_route_headers.is_empty());
+
+        // Examine the values - our implementation should handle multiple values within a header
+        // This depends on how your implementation handles comma-separated header values
+        // This test might need adjustment based on your implementation
+        match &record_route_headers[0] {
+            HeaderValue::Raw(range) => {
+                let value = message.get_str(*range);
+                assert!(value.contains("proxy1.example.com"));
+                assert!(value.contains("proxy2.example.com"));
+            }
+            // If your implementation parses Record-Route as Address types
+            // then we'd need different assertions here
+            _ => {
+                // Depending on your implementation, we might need to check for
+                // HeaderValue::Address variants or other specific handling
+                // This is a simplified check for now
+
+                // Implementation-specific validation needed for Record-Route header
+                // No assert needed here as this is just a placeholder for future implementation
+            }
+        }
+    }
+
+    #[test]
+    fn test_from_uri_extraction() {
+        let message = "\
+INVITE sip:bob@biloxi.com SIP/2.0\r
+Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r
+To: Bob <sip:bob@biloxi.com>\r
+From: Alice <sip:alice@atlanta.com:5060>;tag=1928301774\r
+Call-ID: a84b4c76e66710@pc33.atlanta.com\r
+CSeq: 314159 INVITE\r
+Max-Forwards: 70\r
+\r
+";
+        let mut sip_message = SipMessage::new_from_str(message);
+        assert!(sip_message.parse_headers().is_ok());
+
+        let from_uri = sip_message.from_uri().unwrap();
+        assert_eq!(from_uri.scheme, Scheme::SIP);
+        assert_eq!(sip_message.get_opt_str(from_uri.user_info), Some("alice"));
+        assert_eq!(sip_message.get_opt_str(from_uri.host), Some("atlanta.com"));
+        assert_eq!(from_uri.port, Some(5060));
+    }
 
-        // Test URI with headers
-        let uri = parse_uri(uri_with_headers).expect("Failed to parse URI with headers");
-        assert!(uri.headers.is_some());
+    #[test]
+    fn test_to_uri_extraction() {
+        let message = "\
+INVITE sip:bob@biloxi.com SIP/2.0\r
+Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r
+To: Bob <sip:bob@biloxi.com:5080;transport=tcp>\r
+From: Alice <sip:alice@atlanta.com>;tag=1928301774\r
+Call-ID: a84b4c76e66710@pc33.atlanta.com\r
+CSeq: 314159 INVITE\r
+Max-Forwards: 70\r
+\r
+";
+        let mut sip_message = SipMessage::new_from_str(message);
+        assert!(sip_message.parse_headers().is_ok());
 
-        // Test URI with everything
-        let uri = parse_uri(uri_with_everything).expect("Failed to parse complex URI");
-        assert_eq!(uri.scheme, Scheme::SIPS);
-        assert!(uri.port.is_some());
-        assert_eq!(uri.port, Some(5061));
+        let to_uri = sip_message.to_uri().unwrap();
+        assert_eq!(to_uri.scheme, Scheme::SIP);
+        assert_eq!(sip_message.get_opt_str(to_uri.user_info), Some("bob"));
+        assert_eq!(sip_message.get_opt_str(to_uri.host), Some("biloxi.com"));
+        assert_eq!(to_uri.port, Some(5080));
+
+        // Check URI parameter
+        let params_map = sip_message.get_params_map(&to_uri.params);
+        assert_eq!(params_map.get("transport").unwrap(), &Some("tcp"));
     }
 
     #[test]
-    fn test_via_header_parsing() {
-        let via_header = "SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds";
-        let range = TextRange::from_usize(0, via_header.len());
-        let message = SipMessage::new_from_str(via_header);
+    fn test_contact_uri_extraction() {
+        let message = "\
+INVITE sip:bob@biloxi.com SIP/2.0\r
+Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r
+To: Bob <sip:bob@biloxi.com>\r
+From: Alice <sip:alice@atlanta.com>;tag=1928301774\r
+Call-ID: a84b4c76e66710@pc33.atlanta.com\r
+CSeq: 314159 INVITE\r
+Max-Forwards: 70\r
+Contact: <sip:alice@192.168.1.100:5060;transport=udp>\r
+\r
+";
+        let mut sip_message = SipMessage::new_from_str(message);
+        assert!(sip_message.parse_headers().is_ok());
 
-        let via = message
-            .parse_via(range)
-            .expect("Failed to parse Via header");
+        let contact_uri = sip_message.contact_uri().unwrap();
+        assert_eq!(contact_uri.scheme, Scheme::SIP);
+        assert_eq!(
+            sip_message.get_opt_str(contact_uri.user_info),
+            Some("alice")
+        );
+        assert_eq!(
+            sip_message.get_opt_str(contact_uri.host),
+            Some("192.168.1.100")
+        );
+        assert_eq!(contact_uri.port, Some(5060));
+    }
 
-        assert_eq!(message.get_str(via.sent_protocol), "SIP/2.0/UDP");
-        assert_eq!(message.get_str(via.sent_by), "pc33.atlanta.com");
-        assert!(!via.params.is_empty());
+    #[test]
+    fn test_request_uri_extraction() {
+        let message = "\
+INVITE sip:bob@biloxi.com:5060;transport=tcp SIP/2.0\r
+Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r
+To: Bob <sip:bob@biloxi.com>\r
+From: Alice <sip:alice@atlanta.com>;tag=1928301774\r
+Call-ID: a84b4c76e66710@pc33.atlanta.com\r
+CSeq: 314159 INVITE\r
+Max-Forwards: 70\r
+\r
+";
+        let mut sip_message = SipMessage::new_from_str(message);
+        assert!(sip_message.parse_headers().is_ok());
 
-        // Check branch parameter
-        let branch_param = via
-            .params
-            .iter()
-            .find(|(k, _)| message.get_param_key(k) == "branch");
-        assert!(branch_param.is_some());
-        let (_, branch_value) = branch_param.unwrap();
+        let request_uri = sip_message.request_uri().unwrap();
+        assert_eq!(request_uri.scheme, Scheme::SIP);
+        assert_eq!(sip_message.get_opt_str(request_uri.user_info), Some("bob"));
         assert_eq!(
-            message.get_param_value(branch_value),
-            Some("z9hG4bK776asdhds")
+            sip_message.get_opt_str(request_uri.host),
+            Some("biloxi.com")
         );
+        assert_eq!(request_uri.port, Some(5060));
     }
 
     #[test]
-    fn test_address_header_parsing() {
-        let message = SipMessage::new_from_str("Bob <sip:bob@biloxi.com>;tag=a6c85cf");
-        let range = TextRange::from_usize(0, "Bob <sip:bob@biloxi.com>;tag=a6c85cf".len());
+    fn test_from_tag_extraction() {
+        let message = "\
+INVITE sip:bob@biloxi.com SIP/2.0\r
+Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r
+To: Bob <sip:bob@biloxi.com>\r
+From: Alice <sip:alice@atlanta.com>;tag=1928301774\r
+Call-ID: a84b4c76e66710@pc33.atlanta.com\r
+CSeq: 314159 INVITE\r
+Max-Forwards: 70\r
+\r
+";
+        let mut sip_message = SipMessage::new_from_str(message);
+        assert!(sip_message.parse_headers().is_ok());
 
-        let address = message
-            .parse_address(range)
-            .expect("Failed to parse address");
+        let from_tag = sip_message.from_tag();
+        assert_eq!(from_tag, Some("1928301774"));
+    }
 
-        assert_eq!(message.get_opt_str(address.display_name), Some("Bob"));
-        assert_eq!(address.uri.scheme, Scheme::SIP);
-        assert_eq!(message.get_opt_str(address.uri.user_info), Some("bob"));
-        assert_eq!(message.get_opt_str(address.uri.host), Some("biloxi.com"));
+    #[test]
+    fn test_to_tag_extraction() {
+        let message = "\
+SIP/2.0 200 OK\r
+Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r
+To: Bob <sip:bob@biloxi.com>;tag=a6c85cf\r
+From: Alice <sip:alice@atlanta.com>;tag=1928301774\r
+Call-ID: a84b4c76e66710@pc33.atlanta.com\r
+CSeq: 314159 INVITE\r
+\r
+";
+        let mut sip_message = SipMessage::new_from_str(message);
+        assert!(sip_message.parse_headers().is_ok());
 
-        // Check tag parameter
-        let tag_param = address
-            .params
-            .iter()
-            .find(|(k, _)| message.get_param_key(k) == "tag");
-        assert!(tag_param.is_some());
-        let (_, tag_value) = tag_param.unwrap();
-        assert_eq!(message.get_param_value(tag_value), Some("a6c85cf"));
+        let to_tag = sip_message.to_tag();
+        assert_eq!(to_tag, Some("a6c85cf"));
     }
 
     #[test]
-    fn test_parse_error_with_position() {
-        // We need a message with an invalid URI where the scheme is invalid
-        let invalid_uri = "INVITE xyz:bob@biloxi.com SIP/2.0\r\nVia: SIP/2.0/UDP pc33.atlanta.com\r\nTo: Bob <xyz:bob@biloxi.com>\r\nFrom: Alice <sip:alice@atlanta.com>;tag=1928301774\r\nCall-ID: a84b4c76e66710@pc33.atlanta.com\r\nCSeq: 314159 INVITE\r\nMax-Forwards: 70\r\n\r\n";
-        let mut message = SipMessage::new_from_str(invalid_uri);
+    fn test_call_id_str() {
+        let message = "\
+INVITE sip:bob@biloxi.com SIP/2.0\r
+Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r
+To: Bob <sip:bob@biloxi.com>\r
+From: Alice <sip:alice@atlanta.com>;tag=1928301774\r
+Call-ID: a84b4c76e66710@pc33.atlanta.com\r
+CSeq: 314159 INVITE\r
+Max-Forwards: 70\r
+\r
+";
+        let mut sip_message = SipMessage::new_from_str(message);
+        assert!(sip_message.parse_headers().is_ok());
 
-        // Parsing the message should work at the message level
-        assert!(message.parse_without_validation().is_ok());
+        let call_id = sip_message.call_id_str();
+        assert_eq!(call_id, Some("a84b4c76e66710@pc33.atlanta.com"));
+    }
 
-        // But trying to access a header that needs URI parsing should fail
-        let result = message.to();
-        assert!(
-            result.is_err(),
-            "Expected an error when parsing the To header with invalid URI"
-        );
+    #[test]
+    fn test_missing_from_uri_error() {
+        let message = "\
+INVITE sip:bob@biloxi.com SIP/2.0\r
+Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r
+To: Bob <sip:bob@biloxi.com>\r
+Call-ID: a84b4c76e66710@pc33.atlanta.com\r
+CSeq: 314159 INVITE\r
+Max-Forwards: 70\r
+\r
+";
+        let mut sip_message = SipMessage::new_from_str(message);
+        assert!(sip_message.parse_without_validation().is_ok());
 
-        if let Err(error) = result {
-            match error {
-                SsbcError::ParseError {
-                    message: _,
-                    position,
-                    context: _,
-                } => {
-                    assert!(position.is_none()); // Position is now None since we use Option<(usize, usize)>
-                }
-                _ => panic!("Expected ParseError"),
-            }
-        }
+        let result = sip_message.from_uri();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_request_uri_on_response_error() {
+        let message = "\
+SIP/2.0 200 OK\r
+Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r
+To: Bob <sip:bob@biloxi.com>;tag=a6c85cf\r
+From: Alice <sip:alice@atlanta.com>;tag=1928301774\r
+Call-ID: a84b4c76e66710@pc33.atlanta.com\r
+CSeq: 314159 INVITE\r
+\r
+";
+        let mut sip_message = SipMessage::new_from_str(message);
+        assert!(sip_message.parse_headers().is_ok());
+
+        let result = sip_message.request_uri();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_uri_without_angle_brackets() {
+        let message = "\
+REGISTER sip:registrar.atlanta.com SIP/2.0\r
+Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r
+To: sip:alice@atlanta.com\r
+From: sip:alice@atlanta.com;tag=1928301774\r
+Call-ID: a84b4c76e66710@pc33.atlanta.com\r
+CSeq: 1 REGISTER\r
+Max-Forwards: 70\r
+\r
+";
+        let mut sip_message = SipMessage::new_from_str(message);
+        assert!(sip_message.parse_headers().is_ok());
+
+        // Test From URI without angle brackets
+        let from_uri = sip_message.from_uri().unwrap();
+        assert_eq!(from_uri.scheme, Scheme::SIP);
+        assert_eq!(sip_message.get_opt_str(from_uri.user_info), Some("alice"));
+        assert_eq!(sip_message.get_opt_str(from_uri.host), Some("atlanta.com"));
+
+        // Test To URI without angle brackets
+        let to_uri = sip_message.to_uri().unwrap();
+        assert_eq!(to_uri.scheme, Scheme::SIP);
+        assert_eq!(sip_message.get_opt_str(to_uri.user_info), Some("alice"));
+        assert_eq!(sip_message.get_opt_str(to_uri.host), Some("atlanta.com"));
     }
 
     #[test]
-    fn test_max_forwards_parsing() {
-        // Test parsing of Max-Forwards header
+    fn test_tag_extraction_with_multiple_params() {
         let message = "\
 INVITE sip:bob@biloxi.com SIP/2.0\r
 Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r
-To: Bob <sip:bob@biloxi.com>\r
-From: Alice <sip:alice@atlanta.com>;tag=1928301774\r
+To: Bob <sip:bob@biloxi.com>;tag=a6c85cf;foo=bar\r
+From: Alice <sip:alice@atlanta.com>;foo=baz;tag=1928301774;bar=qux\r
 Call-ID: a84b4c76e66710@pc33.atlanta.com\r
 CSeq: 314159 INVITE\r
 Max-Forwards: 70\r
 \r
 ";
         let mut sip_message = SipMessage::new_from_str(message);
-        assert!(sip_message.parse_without_validation().is_ok());
+        assert!(sip_message.parse_headers().is_ok());
 
-        // Check Max-Forwards header
-        match &sip_message.max_forwards {
-            Some(HeaderValue::Raw(range)) => {
-                assert_eq!(sip_message.get_str(*range), "70");
-            }
-            _ => panic!("Max-Forwards header not parsed correctly"),
-        }
+        // Tags should be extracted correctly even with other parameters
+        let from_tag = sip_message.from_tag();
+        assert_eq!(from_tag, Some("1928301774"));
+
+        let to_tag = sip_message.to_tag();
+        assert_eq!(to_tag, Some("a6c85cf"));
     }
 
     #[test]
-    fn test_mime_type_header_parsing() {
-        // Test parsing of Content-Type header
+    fn test_scheme_case_insensitive() {
+        let uri_str = "SIP:bob@biloxi.com";
+        let range = TextRange::from_usize(0, uri_str.len());
+        let message = SipMessage::new_from_str(uri_str);
+        let uri = message.parse_uri(range).expect("Uppercase scheme should parse");
+        assert_eq!(uri.scheme, Scheme::SIP);
+
+        let uri_str = "SiPs:bob@biloxi.com";
+        let range = TextRange::from_usize(0, uri_str.len());
+        let message = SipMessage::new_from_str(uri_str);
+        let uri = message.parse_uri(range).expect("Mixed-case scheme should parse");
+        assert_eq!(uri.scheme, Scheme::SIPS);
+
+        assert!(Scheme::SIPS.is_secure());
+        assert!(!Scheme::SIP.is_secure());
+        assert!(!Scheme::TEL.is_secure());
+    }
+
+    #[test]
+    fn test_scheme_downgrade_detection() {
         let message = "\
-INVITE sip:bob@biloxi.com SIP/2.0\r
-Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r
-To: Bob <sip:bob@biloxi.com>\r
-From: Alice <sip:alice@atlanta.com>;tag=1928301774\r
+INVITE sips:bob@biloxi.com SIP/2.0\r
+Via: SIP/2.0/TLS pc33.atlanta.com;branch=z9hG4bK776asdhds\r
+Route: <sips:proxy1.atlanta.com;lr>, <sip:proxy2.atlanta.com;lr>\r
+To: Bob <sips:bob@biloxi.com>\r
+From: Alice <sips:alice@atlanta.com>;tag=1928301774\r
 Call-ID: a84b4c76e66710@pc33.atlanta.com\r
 CSeq: 314159 INVITE\r
-Max-Forwards: 70\r
-Content-Type: application/sdp\r
 \r
 ";
         let mut sip_message = SipMessage::new_from_str(message);
-        assert!(sip_message.parse_without_validation().is_ok());
+        assert!(sip_message.parse_headers().is_ok());
 
-        // Find Content-Type header
-        let content_type = sip_message.headers.iter().find(|(name_range, _)| {
-            sip_message.get_str(*name_range).to_lowercase() == "content-type"
-        });
+        let routes = sip_message.routes().unwrap();
+        assert_eq!(routes.len(), 2);
+        assert_eq!(sip_message.get_opt_str(routes[1].uri.host), Some("proxy2.atlanta.com"));
+        assert!(sip_message.is_scheme_downgraded().unwrap());
 
-        assert!(content_type.is_some());
-        if let Some((_, HeaderValue::Raw(value_range))) = content_type {
-            assert_eq!(sip_message.get_str(*value_range), "application/sdp");
-        } else {
-            panic!("Content-Type header not found or not parsed correctly");
-        }
+        let secure = message.replace("<sip:proxy2", "<sips:proxy2");
+        let mut sip_message = SipMessage::new_from_str(&secure);
+        assert!(sip_message.parse_headers().is_ok());
+        assert!(!sip_message.is_scheme_downgraded().unwrap());
     }
 
     #[test]
-    fn test_body_with_content_length() {
-        // Test body parsing using Content-Length header
-        let message = "\
+    fn test_try_as_str_rejects_foreign_ranges() {
+        let message = SipMessage::new_from_str("sip:bob@biloxi.com");
+        assert_eq!(message.try_get_str(TextRange::new(4, 7)).unwrap(), "bob");
+
+        // Past the end, misordered, and splitting a multi-byte character
+        assert!(message.try_get_str(TextRange::new(10, 40)).is_err());
+        assert!(message.try_get_str(TextRange::new(7, 4)).is_err());
+        assert!(TextRange::new(0, 1).try_as_str("é").is_err());
+        assert!(matches!(
+            TextRange::new(0, 99).try_as_str(""),
+            Err(SsbcError::ParseError { .. })
+        ));
+    }
+
+    #[test]
+    fn test_in_same_dialog_as() {
+        let invite = "\
 INVITE sip:bob@biloxi.com SIP/2.0\r
 Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r
 To: Bob <sip:bob@biloxi.com>\r
 From: Alice <sip:alice@atlanta.com>;tag=1928301774\r
 Call-ID: a84b4c76e66710@pc33.atlanta.com\r
 CSeq: 314159 INVITE\r
-Max-Forwards: 70\r
-Content-Length: 11\r
 \r
-Hello World";
+";
+        let ringing = "\
+SIP/2.0 180 Ringing\r
+Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r
+To: Bob <sip:bob@biloxi.com>;tag=a6c85cf\r
+From: Alice <sip:alice@atlanta.com>;tag=1928301774\r
+Call-ID: a84b4c76e66710@pc33.atlanta.com\r
+CSeq: 314159 INVITE\r
+\r
+";
+        // BYE sent by the callee carries the tags swapped
+        let bye = "\
+BYE sip:alice@pc33.atlanta.com SIP/2.0\r
+Via: SIP/2.0/UDP server10.biloxi.com;branch=z9hG4bKnashds8\r
+To: Alice <sip:alice@atlanta.com>;tag=1928301774\r
+From: Bob <sip:bob@biloxi.com>;tag=a6c85cf\r
+Call-ID: a84b4c76e66710@pc33.atlanta.com\r
+CSeq: 231 BYE\r
+\r
+";
+        let parse = |raw: &str| {
+            let mut message = SipMessage::new_from_str(raw);
+            message.parse_headers().unwrap();
+            message
+        };
 
-        let mut sip_message = SipMessage::new_from_str(message);
-        assert!(sip_message.parse_without_validation().is_ok());
+        // Early dialog: the INVITE has no To tag yet
+        let mut invite_msg = parse(invite);
+        let mut ringing_msg = parse(ringing);
+        assert!(invite_msg.in_same_dialog_as(&mut ringing_msg).unwrap());
 
-        // Check the body
-        assert_eq!(sip_message.body().unwrap(), "Hello World");
+        // Established dialog in the reverse direction
+        let mut bye_msg = parse(bye);
+        assert!(ringing_msg.in_same_dialog_as(&mut bye_msg).unwrap());
+        assert!(bye_msg.in_same_dialog_as(&mut ringing_msg).unwrap());
+
+        // A different remote tag is a different dialog (forked response)
+        let mut forked = parse(&ringing.replace("tag=a6c85cf", "tag=other"));
+        assert!(!bye_msg.in_same_dialog_as(&mut forked).unwrap());
+
+        // A different Call-ID never matches
+        let mut other_call = parse(&bye.replace("a84b4c76e66710", "f81d4fae"));
+        assert!(!ringing_msg.in_same_dialog_as(&mut other_call).unwrap());
     }
 
     #[test]
-    fn test_duplicate_header_detection() {
-        // Test message with duplicate headers that should be rejected
-        let message_with_duplicate_to = "\
+    fn test_transaction_branch() {
+        let message = "\
 INVITE sip:bob@biloxi.com SIP/2.0\r
 Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r
+Via: SIP/2.0/UDP bigbox3.site3.atlanta.com;branch=z9hG4bK77ef4c2312983.1\r
 To: Bob <sip:bob@biloxi.com>\r
-To: Bob <sip:bob@example.com>\r
 From: Alice <sip:alice@atlanta.com>;tag=1928301774\r
 Call-ID: a84b4c76e66710@pc33.atlanta.com\r
 CSeq: 314159 INVITE\r
-Max-Forwards: 70\r
 \r
 ";
-        let mut sip_message = SipMessage::new_from_str(message_with_duplicate_to);
-        let result = sip_message.parse_headers();
-        assert!(result.is_err());
-
-        match result {
-            Err(SsbcError::ParseError {
-                context: None,
-                message,
-                position: _,
-            }) => {
-                assert!(message.contains("Duplicate To header"));
-            }
-            _ => panic!("Expected InvalidHeader error for duplicate To"),
-        }
+        let mut sip_message = SipMessage::new_from_str(message);
+        sip_message.parse_headers().unwrap();
+        assert_eq!(sip_message.transaction_branch().unwrap(), Some("z9hG4bK776asdhds"));
+
+        // RFC 2543 style branch without the magic cookie
+        let legacy = message.replace("branch=z9hG4bK776asdhds", "branch=776asdhds");
+        let mut sip_message = SipMessage::new_from_str(&legacy);
+        sip_message.parse_headers().unwrap();
+        assert!(sip_message.transaction_branch().is_err());
+
+        let empty = message.replace("branch=z9hG4bK776asdhds", "branch=");
+        let mut sip_message = SipMessage::new_from_str(&empty);
+        sip_message.parse_headers().unwrap();
+        assert!(sip_message.transaction_branch().is_err());
+
+        let missing = message.replace(";branch=z9hG4bK776asdhds", "");
+        let mut sip_message = SipMessage::new_from_str(&missing);
+        sip_message.parse_headers().unwrap();
+        assert_eq!(sip_message.transaction_branch().unwrap(), None);
+    }
 
-        // Test message with duplicate From header
-        let message_with_duplicate_from = "\
+    #[test]
+    fn test_transaction_ids() {
+        let invite = "\
 INVITE sip:bob@biloxi.com SIP/2.0\r
-Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r
+Via: SIP/2.0/UDP PC33.atlanta.com;branch=z9hG4bK776asdhds\r
 To: Bob <sip:bob@biloxi.com>\r
 From: Alice <sip:alice@atlanta.com>;tag=1928301774\r
-From: Carol <sip:carol@example.com>;tag=987654321\r
 Call-ID: a84b4c76e66710@pc33.atlanta.com\r
 CSeq: 314159 INVITE\r
-Max-Forwards: 70\r
 \r
 ";
-        let mut sip_message = SipMessage::new_from_str(message_with_duplicate_from);
-        let result = sip_message.parse_headers();
-        assert!(result.is_err());
+        let parse = |raw: &str| {
+            let mut message = SipMessage::new_from_str(raw);
+            message.parse_headers().unwrap();
+            message
+        };
 
-        match result {
-            Err(SsbcError::ParseError {
-                context: None,
-                message,
-                position: _,
-            }) => {
-                assert!(message.contains("Duplicate From header"));
-            }
-            _ => panic!("Expected InvalidHeader error for duplicate From"),
-        }
+        let mut invite_msg = parse(invite);
+        let invite_id = invite_msg.server_transaction_id().unwrap();
+        assert_eq!(invite_id.branch, "z9hG4bK776asdhds");
+        assert_eq!(invite_id.sent_by.as_deref(), Some("pc33.atlanta.com"));
+        assert_eq!(invite_id.method, Method::INVITE);
+
+        // ACK for a non-2xx response matches the INVITE server transaction
+        let ack = invite.replace("INVITE sip:", "ACK sip:").replace("314159 INVITE", "314159 ACK");
+        assert_eq!(parse(&ack).server_transaction_id().unwrap(), invite_id);
+
+        // CANCEL shares the branch but is a separate transaction
+        let cancel = invite
+            .replace("INVITE sip:", "CANCEL sip:")
+            .replace("314159 INVITE", "314159 CANCEL");
+        let cancel_id = parse(&cancel).server_transaction_id().unwrap();
+        assert_eq!(cancel_id.branch, invite_id.branch);
+        assert_ne!(cancel_id, invite_id);
+
+        // A response maps to the client transaction of the request that caused it
+        let response = invite.replace("INVITE sip:bob@biloxi.com SIP/2.0", "SIP/2.0 180 Ringing");
+        let mut response_msg = parse(&response);
+        assert_eq!(
+            response_msg.client_transaction_id().unwrap(),
+            invite_msg.client_transaction_id().unwrap()
+        );
+        assert!(response_msg.server_transaction_id().is_err());
+    }
 
-        // Test message with duplicate CSeq header
-        let message_with_duplicate_cseq = "\
+    #[test]
+    fn test_leading_crlf_before_start_line() {
+        let message = "\
+\r
+\r
 INVITE sip:bob@biloxi.com SIP/2.0\r
 Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r
 To: Bob <sip:bob@biloxi.com>\r
 From: Alice <sip:alice@atlanta.com>;tag=1928301774\r
 Call-ID: a84b4c76e66710@pc33.atlanta.com\r
 CSeq: 314159 INVITE\r
-CSeq: 1 ACK\r
-Max-Forwards: 70\r
 \r
 ";
-        let mut sip_message = SipMessage::new_from_str(message_with_duplicate_cseq);
-        let result = sip_message.parse_headers();
-        assert!(result.is_err());
+        let mut sip_message = SipMessage::new_from_str(message);
+        assert!(sip_message.parse_headers().is_ok());
+        assert!(sip_message.is_request());
+        assert_eq!(sip_message.start_line(), "INVITE sip:bob@biloxi.com SIP/2.0");
+        assert_eq!(sip_message.request_method(), Some(Method::INVITE));
+        assert_eq!(sip_message.call_id_str(), Some("a84b4c76e66710@pc33.atlanta.com"));
+        assert_eq!(sip_message.from_tag(), Some("1928301774"));
 
-        match result {
-            Err(SsbcError::ParseError {
-                context: None,
-                message,
-                position: _,
-            }) => {
-                assert!(message.contains("Duplicate CSeq header"));
-            }
-            _ => panic!("Expected InvalidHeader error for duplicate CSeq"),
-        }
+        let mut strict = SipMessage::new_from_str_with_limits(message, ParserLimits::strict());
+        assert!(strict.parse_headers().is_err());
     }
 
     #[test]
-    fn test_error_in_from_header() {
-        // Test that an error in the From header is correctly reported
+    fn test_validate_tags() {
+        let register = "\
+REGISTER sip:registrar.biloxi.com SIP/2.0\r
+Via: SIP/2.0/UDP bobspc.biloxi.com:5060;branch=z9hG4bKnashds7\r
+To: Bob <sip:bob@biloxi.com>\r
+From: Bob <sip:bob@biloxi.com>;tag=456248\r
+Call-ID: 843817637684230@998sdasdh09\r
+CSeq: 1826 REGISTER\r
+\r
+";
+        let check = |raw: &str| {
+            let mut message = SipMessage::new_from_str(raw);
+            message.parse_headers().unwrap();
+            message.validate_tags()
+        };
+
+        assert!(check(register).is_ok());
+        assert!(check(&register.replace(";tag=456248", "")).is_err());
+        assert!(check(&register.replace("<sip:bob@biloxi.com>\r\nFrom", "<sip:bob@biloxi.com>;tag=1\r\nFrom")).is_err());
+
+        // Re-INVITE with a To tag is fine, BYE without one is not
+        let reinvite = register
+            .replace("REGISTER sip:registrar.biloxi.com", "INVITE sip:bob@192.0.2.4")
+            .replace("1826 REGISTER", "1827 INVITE")
+            .replace("To: Bob <sip:bob@biloxi.com>", "To: Bob <sip:bob@biloxi.com>;tag=a6c85cf");
+        assert!(check(&reinvite).is_ok());
+        let bye = register
+            .replace("REGISTER sip:registrar.biloxi.com", "BYE sip:bob@192.0.2.4")
+            .replace("1826 REGISTER", "1828 BYE");
+        assert!(check(&bye).is_err());
+    }
+
+    #[test]
+    fn test_header_utils_use_parsed_headers() {
         let message = "\
 INVITE sip:bob@biloxi.com SIP/2.0\r
-Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r
+v: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r
+Via: SIP/2.0/UDP bigbox3.site3.atlanta.com;branch=z9hG4bK77ef4c2312983.1\r
 To: Bob <sip:bob@biloxi.com>\r
-From: Alice <malformed-uri>\r
+f: Alice \r
+ <sip:alice@atlanta.com>;tag=1928301774\r
 Call-ID: a84b4c76e66710@pc33.atlanta.com\r
 CSeq: 314159 INVITE\r
-Max-Forwards: 70\r
+Subject: lunch\r
+\t tomorrow\r
 \r
 ";
-        let mut message = SipMessage::new_from_str(message);
+        let mut sip_message = SipMessage::new_from_str(message);
+        assert!(sip_message.parse_headers().is_ok());
 
-        // Parsing the message should work at the message level
-        assert!(message.parse_without_validation().is_ok());
+        assert_eq!(
+            header_utils::extract_header_value(&sip_message, "From").as_deref(),
+            Some("Alice <sip:alice@atlanta.com>;tag=1928301774")
+        );
+        assert_eq!(
+            header_utils::extract_header_value(&sip_message, "subject").as_deref(),
+            Some("lunch tomorrow")
+        );
+        assert_eq!(
+            header_utils::extract_header_value(&sip_message, "I").as_deref(),
+            Some("a84b4c76e66710@pc33.atlanta.com")
+        );
 
-        // But trying to access a header that needs URI parsing should fail
-        let result = message.from();
-        assert!(result.is_err());
+        let vias = header_utils::get_header_values(&sip_message, "via");
+        assert_eq!(vias.len(), 2);
+        assert!(vias[0].contains("pc33.atlanta.com"));
+        assert!(vias[1].contains("bigbox3.site3.atlanta.com"));
 
-        // Check error details
-        match result {
-            Err(SsbcError::ParseError {
-                message,
-                position,
-                context,
-            }) => {
-                // Position might be None for URI parsing errors
-                println!(
-                    "ParseError: message={}, position={:?}, context={:?}",
-                    message, position, context
-                );
-                // Just verify we got a parse error
-                assert!(
-                    message.contains("uri")
-                        || message.contains("URI")
-                        || message.contains("Invalid")
-                );
-            }
-            _ => panic!("Expected ParseError, got: {:?}", result),
-        }
+        // Lazily parsed headers still resolve to their value text
+        sip_message.from().unwrap();
+        assert!(header_utils::extract_header_value(&sip_message, "f").unwrap().ends_with("tag=1928301774"));
+        assert!(header_utils::extract_header_value(&sip_message, "Route").is_none());
     }
 
     #[test]
-    fn test_event_package_parsing() {
-        // Test SUBSCRIBE with Event header
+    fn test_compact_forms_resolve_identically() {
+        let base_headers = [
+            ("via", "Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds"),
+            ("to", "To: Bob <sip:bob@biloxi.com>"),
+            ("from", "From: Alice <sip:alice@atlanta.com>;tag=1928301774"),
+            ("call-id", "Call-ID: a84b4c76e66710@pc33.atlanta.com"),
+            ("cseq", "CSeq: 314159 INVITE"),
+        ];
+        let value = "<sip:carol@chicago.com>";
+
+        for (letter, full_name) in COMPACT_HEADER_FORMS {
+            let letter = (*letter as char).to_string();
+            let mut message = String::from("INVITE sip:bob@biloxi.com SIP/2.0\r\n");
+            for (name, line) in &base_headers {
+                if name != full_name {
+                    message.push_str(line);
+                    message.push_str("\r\n");
+                }
+            }
+            message.push_str(&format!("{}: {}\r\n\r\n", letter, value));
+
+            let mut sip_message = SipMessage::new_from_str(&message);
+            sip_message.parse_without_validation().unwrap();
+            assert_eq!(
+                header_utils::extract_header_value(&sip_message, full_name).as_deref(),
+                Some(value),
+                "compact form {} should resolve to {}",
+                letter,
+                full_name
+            );
+            assert_eq!(
+                header_utils::extract_header_value(&sip_message, &letter.to_uppercase()).as_deref(),
+                Some(value)
+            );
+        }
+
+        // "m" is Contact, so both APIs agree and Max-Forwards stays unset
         let message = "\
-SUBSCRIBE sip:bob@biloxi.com SIP/2.0\r
+INVITE sip:bob@biloxi.com SIP/2.0\r
 Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r
 To: Bob <sip:bob@biloxi.com>\r
 From: Alice <sip:alice@atlanta.com>;tag=1928301774\r
 Call-ID: a84b4c76e66710@pc33.atlanta.com\r
-CSeq: 314159 SUBSCRIBE\r
-Max-Forwards: 70\r
-Event: presence;id=123;expire=3600\r
+CSeq: 314159 INVITE\r
+m: <sip:alice@pc33.atlanta.com>\r
 \r
 ";
         let mut sip_message = SipMessage::new_from_str(message);
-        assert!(sip_message.parse_without_validation().is_ok());
-
-        // Parse the Event header
-        let event_result = sip_message.parse_event();
-        assert!(event_result.is_ok());
-        let event = event_result.unwrap();
-        assert!(event.is_some());
+        sip_message.parse_headers().unwrap();
+        assert_eq!(
+            header_utils::extract_header_value(&sip_message, "contact").as_deref(),
+            Some("<sip:alice@pc33.atlanta.com>")
+        );
+        assert_eq!(sip_message.max_forwards(), None);
+        let contact = sip_message.contact().unwrap().expect("compact Contact");
+        let host = contact.uri.host;
+        assert_eq!(sip_message.get_opt_str(host), Some("pc33.atlanta.com"));
     }
 
     #[test]
-    fn test_folded_header_enhancements() {
-        // Test with multiple folded lines
+    fn test_params_ordered() {
         let message = "\
 INVITE sip:bob@biloxi.com SIP/2.0\r
-Via: SIP/2.0/UDP pc33.atlanta.com;\r
- branch=z9hG4bK4b43c2ff8.1\r
+Via: SIP/2.0/UDP pc33.atlanta.com;rport;branch=z9hG4bK776asdhds;received=192.0.2.1;ttl=16;maddr=224.2.0.1\r
 To: Bob <sip:bob@biloxi.com>\r
-From: Alice \r
-\t<sip:alice@atlanta.com>;\r
- tag=1928301774\r
+From: Alice <sip:alice@atlanta.com>;tag=1928301774\r
 Call-ID: a84b4c76e66710@pc33.atlanta.com\r
 CSeq: 314159 INVITE\r
-Max-Forwards: 70\r
 \r
 ";
         let mut sip_message = SipMessage::new_from_str(message);
-        assert!(sip_message.parse_without_validation().is_ok());
+        sip_message.parse_headers().unwrap();
+        let params = sip_message.via().unwrap().unwrap().params.clone();
+
+        assert_eq!(
+            sip_message.params_ordered(&params),
+            vec![
+                ("rport", None),
+                ("branch", Some("z9hG4bK776asdhds")),
+                ("received", Some("192.0.2.1")),
+                ("ttl", Some("16")),
+                ("maddr", Some("224.2.0.1")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sips_uri_effective_port() {
+        let message = "\
+SIP/2.0 200 OK\r
+Via: SIP/2.0/TLS pc33.atlanta.com;branch=z9hG4bK776asdhds\r
+To: Bob <sips:bob@biloxi.com>;tag=a6c85cf\r
+From: Alice <sips:alice@atlanta.com:5071>;tag=1928301774\r
+Call-ID: a84b4c76e66710@pc33.atlanta.com\r
+CSeq: 314159 INVITE\r
+Contact: <sips:bob@192.0.2.4>\r
+\r
+";
+        let mut sip_message = SipMessage::new_from_str(message);
+        sip_message.parse_headers().unwrap();
+
+        let contact_uri = sip_message.contact_uri().unwrap();
+        assert_eq!(contact_uri.scheme, Scheme::SIPS);
+        assert_eq!(contact_uri.port, None);
+        assert_eq!(sip_message.get_opt_str(contact_uri.host), Some("192.0.2.4"));
+        assert_eq!(contact_uri.effective_port(), 5061);
+
+        let to_uri = sip_message.to_uri().unwrap();
+        assert_eq!(to_uri.effective_port(), 5061);
+        assert_eq!(sip_message.from_uri().unwrap().effective_port(), 5071);
 
-        // Verify that Via header was parsed successfully
-        let via_result = sip_message.via();
-        assert!(via_result.is_ok());
-        assert!(via_result.unwrap().is_some());
+        let sip_uri = SipUri::default();
+        assert_eq!(sip_uri.effective_port(), 5060);
     }
 
     #[test]
-    fn test_method_parsing() {
-        // Test parsing methods from request line
+    fn test_scheme_consistency() {
         let message = "\
-SUBSCRIBE sip:bob@biloxi.com SIP/2.0\r
-Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r
-To: Bob <sip:bob@biloxi.com>\r
-From: Alice <sip:alice@atlanta.com>;tag=1928301774\r
+INVITE sips:bob@biloxi.com SIP/2.0\r
+Via: SIP/2.0/TLS pc33.atlanta.com;branch=z9hG4bK776asdhds\r
+Route: <sips:proxy1.atlanta.com;lr>\r
+To: Bob <sips:bob@biloxi.com>\r
+From: Alice <sips:alice@atlanta.com>;tag=1928301774\r
 Call-ID: a84b4c76e66710@pc33.atlanta.com\r
-CSeq: 314159 SUBSCRIBE\r
-Max-Forwards: 70\r
-Event: presence\r
+CSeq: 314159 INVITE\r
+Contact: <sips:alice@pc33.atlanta.com>\r
 \r
 ";
-        let mut sip_message = SipMessage::new_from_str(message);
-        assert!(sip_message.parse_without_validation().is_ok());
+        let check = |raw: &str| {
+            let mut sip_message = SipMessage::new_from_str(raw);
+            sip_message.parse_headers().unwrap();
+            sip_message.scheme_consistency()
+        };
 
-        // Check request method
-        let method = sip_message.request_method();
-        assert!(method.is_some());
-        assert_eq!(method.unwrap(), Method::SUBSCRIBE);
+        assert!(check(message).is_ok());
+
+        let error = check(&message.replace("<sips:proxy1", "<sip:proxy1")).unwrap_err();
+        assert!(error.to_string().contains("Route"));
+
+        let error = check(&message.replace("<sips:alice@pc33", "<sip:alice@pc33")).unwrap_err();
+        assert!(error.to_string().contains("Contact"));
+
+        // Plain sip requests may use any scheme downstream
+        let plain = message.replace("INVITE sips:", "INVITE sip:").replace("<sips:proxy1", "<sip:proxy1");
+        assert!(check(&plain).is_ok());
     }
 
     #[test]
-    fn test_multiple_via_collection() {
-        // Test message with multiple Via headers
+    fn test_header_ranges_in_parse_order() {
         let message = "\
 INVITE sip:bob@biloxi.com SIP/2.0\r
 Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r
-Via: SIP/2.0/UDP bigbox3.site3.atlanta.com;branch=z9hG4bK77ef4c2312983.1\r
-Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds;received=192.0.2.1\r
+Max-Forwards: 70\r
 To: Bob <sip:bob@biloxi.com>\r
-From: Alice <sip:alice@atlanta.com>;tag=1928301774\r
+f: Alice <sip:alice@atlanta.com>;tag=1928301774\r
 Call-ID: a84b4c76e66710@pc33.atlanta.com\r
+Subject: lunch\r
 CSeq: 314159 INVITE\r
-Max-Forwards: 70\r
+Contact: <sip:alice@pc33.atlanta.com>\r
+Content-Length: 0\r
 \r
 ";
         let mut sip_message = SipMessage::new_from_str(message);
-        assert!(sip_message.parse_without_validation().is_ok());
+        sip_message.parse_headers().unwrap();
+        sip_message.to().unwrap();
 
-        // Just check that we have 3 Via headers
-        let via_count = sip_message.via_headers.len();
-        assert_eq!(via_count, 3);
-
-        // Verify the first via is accessible
-        let via_result = sip_message.via();
-        assert!(via_result.is_ok());
-        assert!(via_result.unwrap().is_some());
+        let headers: Vec<(&str, &str)> = sip_message
+            .header_ranges()
+            .map(|(name, value)| (sip_message.get_str(name), sip_message.get_str(value)))
+            .collect();
+        assert_eq!(
+            headers,
+            vec![
+                ("Via", "SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds"),
+                ("Max-Forwards", "70"),
+                ("To", "Bob <sip:bob@biloxi.com>"),
+                ("f", "Alice <sip:alice@atlanta.com>;tag=1928301774"),
+                ("Call-ID", "a84b4c76e66710@pc33.atlanta.com"),
+                ("Subject", "lunch"),
+                ("CSeq", "314159 INVITE"),
+                ("Contact", "<sip:alice@pc33.atlanta.com>"),
+                ("Content-Length", "0"),
+            ]
+        );
     }
 
     #[test]
-    fn test_required_header_validation() {
-        // Test message missing required headers
-        let message_missing_to = "\
-INVITE sip:bob@biloxi.com SIP/2.0\r
+    fn test_redirect_targets() {
+        let message = "\
+SIP/2.0 302 Moved Temporarily\r
 Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r
+To: Bob <sip:bob@biloxi.com>;tag=a6c85cf\r
 From: Alice <sip:alice@atlanta.com>;tag=1928301774\r
 Call-ID: a84b4c76e66710@pc33.atlanta.com\r
 CSeq: 314159 INVITE\r
-Max-Forwards: 70\r
+Contact: <sip:bob@office.biloxi.com>;q=0.5\r
+Contact: <sip:bob@mobile.biloxi.com>;q=0.9, <sip:bob@voicemail.biloxi.com>;q=0.1\r
+Contact: <sip:bob@home.biloxi.com>\r
 \r
 ";
-        let mut sip_message = SipMessage::new_from_str(message_missing_to);
-        let result = sip_message.parse_headers();
-        assert!(result.is_err());
+        let mut sip_message = SipMessage::new_from_str(message);
+        sip_message.parse_headers().unwrap();
+        assert!(!sip_message.is_request());
+        assert_eq!(sip_message.contacts().unwrap().len(), 3);
 
-        match result {
-            Err(SsbcError::ParseError {
-                context: None,
-                message,
-                position: _,
-            }) => {
-                assert!(message.contains("Missing required To header"));
+        let targets = sip_message.redirect_targets().unwrap();
+        let hosts: Vec<(&str, f32)> = targets
+            .iter()
+            .map(|(address, q)| (sip_message.get_opt_str(address.uri.host).unwrap(), *q))
+            .collect();
+        assert_eq!(
+            hosts,
+            vec![
+                ("home.biloxi.com", 1.0),
+                ("mobile.biloxi.com", 0.9),
+                ("office.biloxi.com", 0.5),
+                ("voicemail.biloxi.com", 0.1),
+            ]
+        );
+
+        for bad_q in ["nan", "inf", "-1", "7", "1.5", "0.1234", ".5", "1.001"] {
+            let bad = message.replace("q=0.5", &format!("q={}", bad_q));
+            let mut sip_message = SipMessage::new_from_str(&bad);
+            sip_message.parse_headers().unwrap();
+            assert!(sip_message.redirect_targets().is_err(), "q={} accepted", bad_q);
+        }
+        for (good_q, expected) in [("0", 0.0), ("1.000", 1.0), ("0.", 0.0), ("0.125", 0.125)] {
+            let good = message.replace("q=0.5", &format!("q={}", good_q));
+            let mut sip_message = SipMessage::new_from_str(&good);
+            sip_message.parse_headers().unwrap();
+            let targets = sip_message.redirect_targets().unwrap();
+            assert!(targets.iter().any(|(_, q)| *q == expected), "q={} rejected", good_q);
+        }
+    }
+
+    #[test]
+    fn test_parse_bytes_rejects_invalid_utf8() {
+        let message = b"INVITE sip:bob@biloxi.com SIP/2.0\r\n\
+Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r\n\
+To: Bob <sip:bob@biloxi.com>\r\n\
+From: Alice <sip:alice@atlanta.com>;tag=1928301774\r\n\
+Call-ID: a84b4c76e66710@pc33.atlanta.com\r\n\
+CSeq: 314159 INVITE\r\n\
+\r\n";
+        let parsed = SipMessage::parse(message).unwrap();
+        assert_eq!(parsed.request_method(), Some(Method::INVITE));
+
+        let mut corrupted = message.to_vec();
+        let subject = b"Subject: caf\xe9\r\n";
+        corrupted.splice(35..35, subject.iter().copied());
+        match SipMessage::parse(&corrupted) {
+            Err(SsbcError::ParseError { message, position, .. }) => {
+                assert!(message.contains("Invalid UTF-8"));
+                assert_eq!(position, Some((2, 12)));
             }
-            _ => panic!("Expected InvalidMessage error for missing To header"),
+            other => panic!("Expected UTF-8 parse error, got {:?}", other),
         }
+    }
 
-        // Test message missing Max-Forwards - now optional, should succeed
-        let message_missing_max_forwards = "\
+    #[test]
+    fn test_call_id_matching() {
+        let message = "\
 INVITE sip:bob@biloxi.com SIP/2.0\r
 Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r
 To: Bob <sip:bob@biloxi.com>\r
 From: Alice <sip:alice@atlanta.com>;tag=1928301774\r
-Call-ID: a84b4c76e66710@pc33.atlanta.com\r
+Call-ID: a84B4c76e66710@pc33.Atlanta.com\r
 CSeq: 314159 INVITE\r
 \r
 ";
-        let mut sip_message = SipMessage::new_from_str(message_missing_max_forwards);
-        let result = sip_message.parse_headers();
-        // Max-Forwards is now optional - proxies add it if missing
-        assert!(result.is_ok());
-        
-        // Verify we can access max_forwards and it returns None
-        assert_eq!(sip_message.max_forwards(), None);
+        let mut sip_message = SipMessage::new_from_str(message);
+        sip_message.parse_headers().unwrap();
+
+        assert!(sip_message.call_id_matches("a84B4c76e66710@pc33.Atlanta.com"));
+        assert!(!sip_message.call_id_matches("a84B4c76e66710@pc33.atlanta.com"));
+        assert!(!sip_message.call_id_matches("a84b4c76e66710@pc33.Atlanta.com"));
+
+        assert!(sip_message.lenient_call_id_matches("a84B4c76e66710@PC33.ATLANTA.COM"));
+        assert!(!sip_message.lenient_call_id_matches("a84b4c76e66710@pc33.atlanta.com"));
+        assert!(!sip_message.lenient_call_id_matches("a84B4c76e66710"));
     }
 
     #[test]
-    fn test_uri_character_validation() {
-        // Test URI with valid characters
-        let uri_str = "sip:alice@atlanta.com";
-        let range = TextRange::new(0, uri_str.len());
-        let message = SipMessage::new_from_str(uri_str);
-        assert!(message.parse_uri(range).is_ok());
+    fn test_status_line() {
+        let response = |status_line: &str| {
+            let message = format!(
+                "{}\r\nVia: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r\nCall-ID: a84b4c76e66710\r\n\r\n",
+                status_line
+            );
+            let mut sip_message = SipMessage::new_from_str(&message);
+            sip_message.parse_headers().unwrap();
+            sip_message
+        };
 
-        // Test URI with invalid characters in user part
-        let invalid_uri = "sip:alice[123]@atlanta.com";
-        let range = TextRange::from_usize(0, invalid_uri.len());
-        let message = SipMessage::new_from_str(invalid_uri);
-        let result = message.parse_uri(range);
-        assert!(result.is_err());
+        let ok = response("SIP/2.0 183 Session Progress");
+        let status = ok.status_line().unwrap();
+        assert_eq!(status.version, "SIP/2.0");
+        assert_eq!(status.code, 183);
+        assert_eq!(status.reason, "Session Progress");
+
+        assert!(response("SIP/2.0 20 OK").status_line().is_err());
+        assert!(response("SIP/2.0 2000 OK").status_line().is_err());
+        assert!(response("SIP/2.0 099 Low").status_line().is_err());
+        assert!(response("SIP/2.0 700 High").status_line().is_err());
+        assert!(response("SIP/2.0 200").status_line().is_err());
+        assert!(response("SIP/3.0 200 OK").status_line().is_err());
+
+        // The Reason-Phrase may be empty, but the SP before it is required
+        let empty_reason = response("SIP/2.0 200 ");
+        let status = empty_reason.status_line().unwrap();
+        assert_eq!((status.code, status.reason), (200, ""));
+    }
 
-        // Test URI with valid percent-encoded characters
-        let encoded_uri = "sip:alice%20smith@atlanta.com".to_string();
-        let range = TextRange::from_usize(0, encoded_uri.len());
-        let message = SipMessage::new_from_str(&encoded_uri);
-        assert!(message.parse_uri(range).is_ok());
+    #[test]
+    fn test_request_line() {
+        let request = |request_line: &str| {
+            let message = format!(
+                "{}\r\nVia: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r\nCall-ID: a84b4c76e66710\r\n\r\n",
+                request_line
+            );
+            let mut sip_message = SipMessage::new_from_str(&message);
+            sip_message.parse_without_validation().unwrap();
+            sip_message
+        };
 
-        // Test URI with invalid percent-encoding
-        let bad_encoded_uri = "sip:alice%2@atlanta.com";
-        let range = TextRange::from_usize(0, bad_encoded_uri.len());
-        let message = SipMessage::new_from_str(bad_encoded_uri);
-        let result = message.parse_uri(range);
-        assert!(result.is_err());
+        let invite = request("INVITE sips:bob@biloxi.com:5062 SIP/2.0");
+        let line = invite.request_line().unwrap();
+        assert_eq!(line.method, Method::INVITE);
+        assert_eq!(line.version, "SIP/2.0");
+        assert_eq!(line.uri.scheme, Scheme::SIPS);
+        assert_eq!(line.uri.port, Some(5062));
+        assert_eq!(invite.get_opt_str(line.uri.host), Some("biloxi.com"));
+
+        let extension = request("PUBLISHX sip:bob@biloxi.com SIP/2.0");
+        assert_eq!(
+            extension.request_line().unwrap().method,
+            Method::UNKNOWN("PUBLISHX".to_string())
+        );
+
+        assert!(request("INVITE sip:bob@biloxi.com").request_line().is_err());
+        assert!(request("INVITE sip:bob@biloxi.com SIP/2.0 extra").request_line().is_err());
+        assert!(request("INVITE sip:bob@biloxi.com SIP/1.0").request_line().is_err());
+        assert!(request("inv!te sip:bob@biloxi.com SIP/2.0").request_line().is_err());
     }
 
     #[test]
-    fn test_sip_response_parsing() {
-        // Test parsing a SIP response instead of a request
+    fn test_set_body() {
         let message = "\
-SIP/2.0 200 OK\r
-Via: SIP/2.0/UDP server10.biloxi.com;branch=z9hG4bK4442ba5c\r
-To: Bob <sip:bob@biloxi.com>;tag=a6c85cf\r
+INVITE sip:bob@biloxi.com SIP/2.0\r
+Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r
+To: Bob <sip:bob@biloxi.com>\r
 From: Alice <sip:alice@atlanta.com>;tag=1928301774\r
 Call-ID: a84b4c76e66710@pc33.atlanta.com\r
 CSeq: 314159 INVITE\r
+Content-Type: application/sdp\r
+l: 4\r
 \r
+v=0\r
 ";
+        let new_sdp = "v=0\r\no=- 1 1 IN IP4 10.0.0.1\r\ns=-\r\n";
         let mut sip_message = SipMessage::new_from_str(message);
-        assert!(sip_message.parse_without_validation().is_ok());
-
-        // Check that it's correctly identified as a response
-        assert!(!sip_message.is_request());
-
-        // Check the start line
-        assert_eq!(sip_message.start_line(), "SIP/2.0 200 OK");
-    }
-
-    #[test]
-    fn test_text_range_operations() {
-        // Test TextRange operations more thoroughly
-        let range1 = TextRange::new(10, 20);
-        let range2 = TextRange::new(10, 20);
-        let range3 = TextRange::new(5, 15);
-
-        // Test equality
-        assert_eq!(range1, range2);
-        assert_ne!(range1, range3);
+        sip_message.parse_headers().unwrap();
+        sip_message.set_body(new_sdp);
 
-        // Test length
-        assert_eq!(range1.len(), 10);
+        assert!(sip_message.parse_headers().is_ok());
+        assert_eq!(sip_message.body(), Some(new_sdp));
+        assert_eq!(
+            header_utils::get_header_values(&sip_message, "content-length"),
+            vec![new_sdp.len().to_string()]
+        );
+        assert_eq!(sip_message.from_tag(), Some("1928301774"));
 
-        // Test is_empty
-        assert!(!range1.is_empty());
-        assert!(TextRange::new(10, 10).is_empty());
+        // A message without Content-Length gets one
+        let mut sip_message = SipMessage::new_from_str(&message.replace("l: 4\r\n", ""));
+        sip_message.set_body("");
+        assert!(sip_message.parse_headers().is_ok());
+        assert_eq!(sip_message.body(), None);
+        assert_eq!(
+            header_utils::extract_header_value(&sip_message, "Content-Length").as_deref(),
+            Some("0")
+        );
 
-        // Test string extraction
-        let text = "0123456789abcdefghijklmnopqrstuvwxyz";
-        assert_eq!(range1.as_str(text), "abcdefghij");
+        // CRLFs before the start line are dropped, not mistaken for the headers' end
+        let mut sip_message = SipMessage::new_from_str(&format!("\r\n\r\n{}", message));
+        sip_message.set_body("hello");
+        assert!(sip_message.raw_message().starts_with("INVITE "));
+        assert!(sip_message.parse_headers().is_ok());
+        assert_eq!(sip_message.body(), Some("hello"));
+        assert_eq!(sip_message.call_id_str(), Some("a84b4c76e66710@pc33.atlanta.com"));
     }
 
     #[test]
-    fn test_basic_from_header() {
-        let input = "From: \"Alice\" <sip:alice@example.com>";
-        let mut message = SipMessage::new_from_str(input);
-        message
-            .process_header_line(TextRange::from_usize(0, input.len()))
-            .unwrap();
-
-        // Check that we can extract the From header
-        let address = message.from().unwrap().expect("From header not found");
-
-        // Create local copies of all the values we need
-        let display_name = address.display_name.unwrap().as_str(&message.raw_message);
+    fn test_extension_headers() {
+        let message = "\
+INVITE sip:bob@biloxi.com SIP/2.0\r
+Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r
+Max-Forwards: 70\r
+To: Bob <sip:bob@biloxi.com>\r
+f: Alice <sip:alice@atlanta.com>;tag=1928301774\r
+Call-ID: a84b4c76e66710@pc33.atlanta.com\r
+X-Account-Id: 4711\r
+CSeq: 314159 INVITE\r
+P-Asserted-Identity: <sip:alice@atlanta.com>\r
+Record-Route: <sip:p1.atlanta.com;lr>\r
+Session-Expires: 1800\r
+Vendor-Trace: abc\r
+Content-Length: 0\r
+\r
+";
+        let mut sip_message = SipMessage::new_from_str(message);
+        sip_message.parse_headers().unwrap();
 
-        // Check display name
-        assert_eq!(display_name, "Alice");
+        let extensions: Vec<(&str, &str)> = sip_message.extension_headers().collect();
+        assert_eq!(
+            extensions,
+            vec![
+                ("X-Account-Id", "4711"),
+                ("P-Asserted-Identity", "<sip:alice@atlanta.com>"),
+                ("Vendor-Trace", "abc"),
+            ]
+        );
     }
 
     #[test]
-    fn test_complex_from_header() {
-        let input = "From: \"Alice Smith\" <sip:alice@example.com:5060;transport=tcp>;tag=1234;expires=3600";
-        let mut message = SipMessage::new_from_str(input);
-        message
-            .process_header_line(TextRange::from_usize(0, input.len()))
-            .unwrap();
-
-        // In test methods, we need to clone to avoid borrowing conflicts
-        // This is only in tests, not performance-critical code
-        let raw_message = message.raw_message.clone();
-
-        // Now we can safely get the From header and use raw_message in closures
-        let from = message.from().unwrap().expect("From header not found");
-
-        // Extract all values using the as_str method
-        let display_name = from.display_name.map(|range| range.as_str(&raw_message));
-        let scheme = from.uri.scheme.clone();
-        let user_info = from.uri.user_info.map(|range| range.as_str(&raw_message));
-        let host = from.uri.host.map(|range| range.as_str(&raw_message));
-        let port = from.uri.port;
-
-        // Create parameter maps using as_str
-        let mut uri_params_map = HashMap::new();
-        for (k, v) in &from.uri.params {
-            let key = k.as_str(&raw_message);
-            let value = v.as_ref().map(|r| r.as_str(&raw_message));
-            uri_params_map.insert(key, value);
+    fn test_max_headers_block_size() {
+        let mut message = String::from(
+            "INVITE sip:bob@biloxi.com SIP/2.0\r\n\
+Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r\n\
+To: Bob <sip:bob@biloxi.com>\r\n\
+From: Alice <sip:alice@atlanta.com>;tag=1928301774\r\n\
+Call-ID: a84b4c76e66710@pc33.atlanta.com\r\n\
+CSeq: 314159 INVITE\r\n",
+        );
+        // 40 headers of ~1KB each: every line is fine, the block is not
+        for i in 0..40 {
+            message.push_str(&format!("X-Filler-{}: {}\r\n", i, "a".repeat(1000)));
         }
-
-        let mut header_params_map = HashMap::new();
-        for (k, v) in &from.params {
-            let key = k.as_str(&raw_message);
-            let value = v.as_ref().map(|r| r.as_str(&raw_message));
-            header_params_map.insert(key, value);
+        message.push_str("\r\n");
+
+        let limits = ParserLimits::strict();
+        assert!(1024 < limits.max_header_line_length);
+        let mut sip_message = SipMessage::new_from_str_with_limits(&message, limits.clone());
+        match sip_message.parse_headers() {
+            Err(SsbcError::ParseError { message, .. }) => assert!(message.contains("Header block size")),
+            other => panic!("Expected header block size error, got {:?}", other),
         }
 
-        // Now test with the data we've extracted
-        assert_eq!(display_name, Some("Alice Smith"));
-        assert_eq!(scheme, Scheme::SIP);
-        assert_eq!(user_info, Some("alice"));
-        assert_eq!(host, Some("example.com"));
-        assert_eq!(port, Some(5060));
-
-        // Check URI parameters
-        assert_eq!(uri_params_map.get("transport").unwrap(), &Some("tcp"));
-
-        // Check header parameters
-        assert_eq!(header_params_map.get("tag").unwrap(), &Some("1234"));
-        assert_eq!(header_params_map.get("expires").unwrap(), &Some("3600"));
+        let mut sip_message = SipMessage::new_from_str_with_limits(&message, ParserLimits::default());
+        assert!(sip_message.parse_headers().is_ok());
     }
 
     #[test]
-    fn test_escaped_characters() {
-        let input = "From: \"Alice\\\"Quotes\\\"\" <sip:alice@example.com>";
-        let mut message = SipMessage::new_from_str(input);
-        message
-            .process_header_line(TextRange::from_usize(0, input.len()))
-            .unwrap();
+    fn test_contacts_lossy() {
+        let message = "\
+SIP/2.0 200 OK\r
+Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r
+To: Bob <sip:bob@biloxi.com>;tag=a6c85cf\r
+From: Alice <sip:alice@atlanta.com>;tag=1928301774\r
+Call-ID: a84b4c76e66710@pc33.atlanta.com\r
+CSeq: 314159 INVITE\r
+Contact: <sip:bob@192.0.2.4>\r
+Contact: <foo:bar\r
+Contact: <sip:bob@198.51.100.7>\r
+\r
+";
+        let mut sip_message = SipMessage::new_from_str(message);
+        sip_message.parse_headers().unwrap();
+        assert!(sip_message.contacts().is_err());
+
+        let (contacts, errors) = sip_message.contacts_lossy();
+        let hosts: Vec<TextRange> = contacts.iter().filter_map(|contact| contact.uri.host).collect();
+        assert_eq!(hosts.len(), 2);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(sip_message.get_str(hosts[0]), "192.0.2.4");
+        assert_eq!(sip_message.get_str(hosts[1]), "198.51.100.7");
+    }
 
-        // In test methods, we need to clone to avoid borrowing conflicts
-        // This is only in tests, not performance-critical code
-        let raw_message = message.raw_message.clone();
+    #[test]
+    fn test_header_validator_hook() {
+        use std::sync::Arc;
 
-        // Check that we can extract the From header
-        let address = message.from().unwrap().expect("From header not found");
+        /// Rejects Contacts pointing into 10.0.0.0/8
+        struct NoPrivateContact;
 
-        // Get display name directly using as_str
-        let display_name = address.display_name.map(|r| r.as_str(&raw_message));
+        impl validation::HeaderValidator for NoPrivateContact {
+            fn validate(&self, name: &str, value: &str) -> Result<(), SsbcError> {
+                if name == "contact" && value.contains("@10.") {
+                    return Err(SsbcError::parse_error("Private Contact address", None, Some(value.to_string())));
+                }
+                Ok(())
+            }
+        }
 
-        // Check display name with escaped quotes
-        assert_eq!(display_name, Some("Alice\\\"Quotes\\\""));
+        let message = "\
+INVITE sip:bob@biloxi.com SIP/2.0\r
+Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r
+To: Bob <sip:bob@biloxi.com>\r
+From: Alice <sip:alice@atlanta.com>;tag=1928301774\r
+Call-ID: a84b4c76e66710@pc33.atlanta.com\r
+CSeq: 314159 INVITE\r
+m: <sip:alice@10.1.2.3>\r
+\r
+";
+        let limits = ParserLimits {
+            header_validator: Some(Arc::new(NoPrivateContact)),
+            ..ParserLimits::default()
+        };
+
+        let mut sip_message = SipMessage::new_from_str_with_limits(message, limits.clone());
+        assert!(sip_message.parse_headers().is_err());
+
+        let public = message.replace("@10.1.2.3", "@192.0.2.1");
+        let mut sip_message = SipMessage::new_from_str_with_limits(&public, limits);
+        assert!(sip_message.parse_headers().is_ok());
     }
 
     #[test]
-    fn test_unquoted_display_name() {
-        let input = "From: John Doe <sip:john@example.com>";
-        let mut message = SipMessage::new_from_str(input);
-        message
-            .process_header_line(TextRange::from_usize(0, input.len()))
-            .unwrap();
+    fn test_expand_compact_header_name() {
+        use crate::headers::expand_compact_header_name;
 
-        // In test methods, we need to clone to avoid borrowing conflicts
-        // This is only in tests, not performance-critical code
-        let raw_message = message.raw_message.clone();
+        for (letter, full_name) in COMPACT_HEADER_FORMS {
+            let lower = (*letter as char).to_string();
+            assert_eq!(expand_compact_header_name(&lower), *full_name);
+            assert_eq!(expand_compact_header_name(&lower.to_uppercase()), *full_name);
+        }
+        assert_eq!(expand_compact_header_name("Contact"), "Contact");
+        assert_eq!(expand_compact_header_name("CSeq"), "CSeq");
+        assert_eq!(expand_compact_header_name("q"), "q");
+        assert_eq!(expand_compact_header_name(""), "");
+    }
 
-        // Check that we can extract the From header
-        let address = message.from().unwrap().expect("From header not found");
+    #[test]
+    fn test_pop_route() {
+        let message = "\
+INVITE sip:bob@biloxi.com SIP/2.0\r
+Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r
+Route: <sip:p1.example.com;lr>, <sip:p2.example.com;lr>\r
+To: Bob <sip:bob@biloxi.com>\r
+Route: <sip:p3.example.com;lr>\r
+From: Alice <sip:alice@atlanta.com>;tag=1928301774\r
+Call-ID: a84b4c76e66710@pc33.atlanta.com\r
+CSeq: 314159 INVITE\r
+\r
+";
+        let mut sip_message = SipMessage::new_from_str(message);
+        sip_message.parse_without_validation().unwrap();
 
-        // Get display name directly using as_str
-        let display_name = address.display_name.map(|r| r.as_str(&raw_message));
+        let mut popped = Vec::new();
+        while let Some(route) = sip_message.pop_route().unwrap() {
+            popped.push(sip_message.get_str(route.uri.host.unwrap()).to_string());
+            assert_eq!(sip_message.routes().unwrap().len(), 3 - popped.len());
+        }
+        assert_eq!(popped, ["p1.example.com", "p2.example.com", "p3.example.com"]);
+        assert!(sip_message.pop_route().unwrap().is_none());
+    }
 
-        // Check display name
-        assert_eq!(display_name, Some("John Doe"));
+    #[test]
+    fn test_as_header_pairs() {
+        let message = "\
+INVITE sip:bob@biloxi.com SIP/2.0\r
+Via: SIP/2.0/UDP proxy.atlanta.com;branch=z9hG4bK2d4790\r
+Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r
+To: Bob <sip:bob@biloxi.com>\r
+From: Alice <sip:alice@atlanta.com>;tag=1928301774\r
+Call-ID: a84b4c76e66710@pc33.atlanta.com\r
+CSeq: 314159 INVITE\r
+Subject: lunch\r
+ tomorrow\r
+\r
+";
+        let mut sip_message = SipMessage::new_from_str(message);
+        sip_message.parse_without_validation().unwrap();
+
+        let pairs = sip_message.as_header_pairs();
+        let names: Vec<&str> = pairs.iter().map(|(name, _)| *name).collect();
+        assert_eq!(names, ["Via", "Via", "To", "From", "Call-ID", "CSeq", "Subject"]);
+        assert_eq!(pairs[1].1, "SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds");
+        assert!(matches!(pairs[2].1, Cow::Borrowed("Bob <sip:bob@biloxi.com>")));
+        assert_eq!(pairs[6].1, "lunch tomorrow");
     }
 
     #[test]
-    fn test_header_params() {
-        let input = "Contact: <sip:user@host.com>;expires=3600;q=0.8";
-        let mut message = SipMessage::new_from_str(input);
-        message
-            .process_header_line(TextRange::from_usize(0, input.len()))
-            .unwrap();
+    fn test_100rel_option_tags() {
+        let message = "\
+INVITE sip:bob@biloxi.com SIP/2.0\r
+Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r
+To: Bob <sip:bob@biloxi.com>\r
+From: Alice <sip:alice@atlanta.com>;tag=1928301774\r
+Call-ID: a84b4c76e66710@pc33.atlanta.com\r
+CSeq: 314159 INVITE\r
+k: timer\r
+Supported: replaces, 100REL\r
+Require: timer\r
+\r
+";
+        let mut sip_message = SipMessage::new_from_str(message);
+        assert!(sip_message.supports_100rel());
+        assert!(!sip_message.requires_100rel());
+        assert_eq!(sip_message.option_tags("Supported"), ["timer", "replaces", "100REL"]);
 
-        // In test methods, we need to clone to avoid borrowing conflicts
-        // This is only in tests, not performance-critical code
-        let raw_message = message.raw_message.clone();
+        let mut required = SipMessage::new_from_str(&message.replace("Require: timer", "Require: 100rel"));
+        assert!(required.requires_100rel());
+    }
 
-        // Get the Contact header directly using contact() method
-        let contact = message
-            .contact()
-            .unwrap()
-            .expect("Contact header not found");
+    #[test]
+    fn test_default_transaction_timers() {
+        use std::time::Duration;
+
+        let invite = Method::INVITE.default_transaction_timers();
+        assert_eq!(invite.retransmit, Duration::from_millis(500));
+        assert_eq!(invite.retransmit_cap, None);
+        assert_eq!(invite.timeout, Duration::from_secs(32));
+
+        let bye = Method::BYE.default_transaction_timers();
+        assert_eq!(bye.retransmit_cap, Some(TIMER_T2));
+        assert_eq!(bye.timeout, Duration::from_secs(32));
+        assert_eq!(bye.completed_wait, TIMER_T4);
+        assert_eq!(Method::UNKNOWN("FOO".into()).default_transaction_timers(), bye);
+    }
 
-        // For consistency with our test, save as address
-        let address = contact;
+    #[test]
+    fn test_sdp_role() {
+        let sdp = "v=0\r\no=- 1 1 IN IP4 192.0.2.1\r\ns=-\r\nc=IN IP4 192.0.2.1\r\nt=0 0\r\nm=audio 5004 RTP/AVP 0\r\n";
+        let build = |start_line: &str, cseq: &str, body: &str| {
+            format!(
+                "{}\r\nVia: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r\n\
+                 To: Bob <sip:bob@biloxi.com>\r\nFrom: Alice <sip:alice@atlanta.com>;tag=1928301774\r\n\
+                 Call-ID: a84b4c76e66710@pc33.atlanta.com\r\nCSeq: {}\r\n\
+                 c: application/sdp\r\nContent-Length: {}\r\n\r\n{}",
+                start_line,
+                cseq,
+                body.len(),
+                body
+            )
+        };
+        let role = |message: String| SipMessage::new_from_str(&message).sdp_role().unwrap();
+
+        assert_eq!(role(build("UPDATE sip:bob@biloxi.com SIP/2.0", "2 UPDATE", sdp)), Some(SdpRole::Offer));
+        assert_eq!(role(build("ACK sip:bob@biloxi.com SIP/2.0", "1 ACK", sdp)), Some(SdpRole::Answer));
+        assert_eq!(role(build("SIP/2.0 183 Session Progress", "1 INVITE", sdp)), Some(SdpRole::Answer));
+        assert_eq!(role(build("SIP/2.0 488 Not Acceptable Here", "1 INVITE", sdp)), None);
+        assert_eq!(role(build("INVITE sip:bob@biloxi.com SIP/2.0", "1 INVITE", "")), None);
+        assert_eq!(role(build("MESSAGE sip:bob@biloxi.com SIP/2.0", "3 MESSAGE", sdp)), None);
+    }
 
-        // Create parameter map directly using as_str
-        let mut params_map = HashMap::new();
-        for (k, v) in &address.params {
-            let key = k.as_str(&raw_message);
-            let value = v.as_ref().map(|r| r.as_str(&raw_message));
-            params_map.insert(key, value);
+    #[test]
+    fn test_uri_port_strictness() {
+        for (port, valid) in [("5060", true), ("0", false), ("070", false), ("65536", false)] {
+            let message = format!(
+                "INVITE sip:bob@biloxi.com:{} SIP/2.0\r\nVia: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r\n\
+                 To: Bob <sip:bob@biloxi.com>\r\nFrom: Alice <sip:alice@atlanta.com>;tag=1928301774\r\n\
+                 Call-ID: a84b4c76e66710@pc33.atlanta.com\r\nCSeq: 314159 INVITE\r\n\r\n",
+                port
+            );
+            let mut sip_message = SipMessage::new_from_str(&message);
+            sip_message.parse_without_validation().unwrap();
+            let result = sip_message.request_uri();
+            assert_eq!(result.is_ok(), valid, "port {}", port);
+            if let Err(error) = result {
+                assert!(error.to_string().contains(port), "{}", error);
+            }
         }
+    }
 
-        // Check parameters
-        assert_eq!(params_map.get("expires").unwrap(), &Some("3600"));
-        assert_eq!(params_map.get("q").unwrap(), &Some("0.8"));
+    #[test]
+    fn test_granted_expiry() {
+        let message = "\
+SIP/2.0 200 OK\r
+Via: SIP/2.0/UDP bobspc.biloxi.com:5060;branch=z9hG4bKnashds7\r
+To: Bob <sip:bob@biloxi.com>;tag=2493k59kd\r
+From: Bob <sip:bob@biloxi.com>;tag=456248\r
+Call-ID: 843817637684230@998sdasdh09\r
+CSeq: 1826 REGISTER\r
+Contact: <sip:bob@192.0.2.4>;expires=600, <sip:bob@192.0.2.5>\r
+Contact: <sip:bob@192.0.2.6>;expires=120\r
+Expires: 3600\r
+\r
+";
+        let mut sip_message = SipMessage::new_from_str(message);
+        sip_message.parse_headers().unwrap();
+        assert_eq!(sip_message.granted_expiry(0).unwrap(), Some(600));
+        assert_eq!(sip_message.granted_expiry(1).unwrap(), Some(3600));
+        assert_eq!(sip_message.granted_expiry(2).unwrap(), Some(120));
+        assert!(sip_message.granted_expiry(3).is_err());
+
+        let mut no_expires = SipMessage::new_from_str(&message.replace("Expires: 3600\r\n", ""));
+        no_expires.parse_headers().unwrap();
+        assert_eq!(no_expires.granted_expiry(1).unwrap(), None);
+
+        // Only a 2xx to REGISTER grants anything
+        let mut busy = SipMessage::new_from_str(&message.replace("200 OK", "486 Busy Here"));
+        busy.parse_headers().unwrap();
+        assert!(busy.granted_expiry(0).is_err());
+        let mut invite_ok = SipMessage::new_from_str(&message.replace("1826 REGISTER", "1826 INVITE"));
+        invite_ok.parse_headers().unwrap();
+        assert!(invite_ok.granted_expiry(0).is_err());
     }
 
     #[test]
-    fn test_multiple_header_params() {
-        let input = "Contact: <sip:alice@example.com>;expires=3600;q=0.8;priority=1";
-        let mut message = SipMessage::new_from_str(input);
-        message
-            .process_header_line(TextRange::from_usize(0, input.len()))
-            .unwrap();
+    fn test_via_host_and_port() {
+        for (sent_by, host, port) in [
+            ("pc33.atlanta.com", "pc33.atlanta.com", None),
+            ("192.0.2.1:5080", "192.0.2.1", Some(5080)),
+            ("[2001:db8::1]:5061", "2001:db8::1", Some(5061)),
+            ("[::1]", "::1", None),
+        ] {
+            let message = format!(
+                "SIP/2.0 200 OK\r\nVia: SIP/2.0/UDP {};branch=z9hG4bK776asdhds\r\n\
+                 To: Bob <sip:bob@biloxi.com>;tag=a6c85cf\r\nFrom: Alice <sip:alice@atlanta.com>;tag=1928301774\r\n\
+                 Call-ID: a84b4c76e66710@pc33.atlanta.com\r\nCSeq: 314159 INVITE\r\n\r\n",
+                sent_by
+            );
+            let mut sip_message = SipMessage::new_from_str(&message);
+            sip_message.parse_headers().unwrap();
+            let via = sip_message.via().unwrap().unwrap().clone();
+            assert_eq!(via.host(sip_message.raw_message()), host);
+            assert_eq!(via.port(sip_message.raw_message()), port);
+        }
+    }
 
-        // In test methods, we need to clone to avoid borrowing conflicts
-        // This is only in tests, not performance-critical code
-        let raw_message = message.raw_message.clone();
+    #[test]
+    fn test_compliance_report() {
+        let message = "\
+INVITE sip:bob@biloxi.com SIP/2.0\r
+Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r
+Max-Forwards: 70\r
+To: Bob <sip:bob@biloxi.com>\r
+From: Alice <sip:alice@atlanta.com>;tag=1928301774\r
+Call-ID: a84b4c76e66710@pc33.atlanta.com\r
+CSeq: 314159 INVITE\r
+Content-Length: 0\r
+\r
+";
+        let report = SipMessage::new_from_str(message).compliance_report();
+        assert!(report.is_compliant(), "{:?}", report);
+        assert_eq!(report.warnings().count(), 0);
+        assert_eq!(report.items.len(), 6);
+
+        let broken = message
+            .replace("Max-Forwards: 70\r\n", "")
+            .replace(";tag=1928301774", "")
+            .replace("branch=z9hG4bK776asdhds", "branch=776asdhds")
+            .replace("Content-Length: 0", "Content-Length: 12")
+            .replace("CSeq: 314159 INVITE", "CSeq: 314159 BYE");
+        let report = SipMessage::new_from_str(&broken).compliance_report();
+        let failed: Vec<&str> = report.failures().map(|item| item.check).collect();
+        assert_eq!(failed, ["required-headers", "tags", "max-forwards", "content-length", "cseq-method"]);
+        let warned: Vec<&str> = report.warnings().map(|item| item.check).collect();
+        assert_eq!(warned, ["branch-cookie"]);
+    }
 
-        // Get the Contact header directly using contact() method
-        let contact = message
-            .contact()
-            .unwrap()
-            .expect("Contact header not found");
+    #[test]
+    fn test_cseq_matches_request() {
+        let build = |method: &str, cseq: &str| {
+            format!(
+                "{} sip:bob@biloxi.com SIP/2.0\r\nVia: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r\n\
+                 To: Bob <sip:bob@biloxi.com>\r\nFrom: Alice <sip:alice@atlanta.com>;tag=1928301774\r\n\
+                 Call-ID: a84b4c76e66710@pc33.atlanta.com\r\nCSeq: {}\r\n\r\n",
+                method, cseq
+            )
+        };
+        let matches = |method: &str, cseq: &str| {
+            let mut sip_message = SipMessage::new_from_str(&build(method, cseq));
+            sip_message.parse_without_validation().unwrap();
+            sip_message.cseq_matches_request().unwrap()
+        };
 
-        // For consistency with our test, save as address
-        let address = contact;
+        assert!(matches("INVITE", "1 INVITE"));
+        assert!(!matches("INVITE", "1 BYE"));
+        assert!(matches("ACK", "1 ACK"));
+        assert!(!matches("ACK", "1 INVITE"));
+        assert!(matches("CANCEL", "1 CANCEL"));
+        assert!(matches("FOO", "7 FOO"));
+
+        let mut response = SipMessage::new_from_str(&build("INVITE", "1 INVITE").replace(
+            "INVITE sip:bob@biloxi.com SIP/2.0",
+            "SIP/2.0 200 OK",
+        ));
+        response.parse_without_validation().unwrap();
+        assert!(response.cseq_matches_request().is_err());
+    }
 
-        // Create parameter map directly using as_str
-        let mut params_map = HashMap::new();
-        for (k, v) in &address.params {
-            let key = k.as_str(&raw_message);
-            let value = v.as_ref().map(|r| r.as_str(&raw_message));
-            params_map.insert(key, value);
-        }
+    #[test]
+    fn test_local_and_remote_tags() {
+        let request = "\
+INVITE sip:bob@biloxi.com SIP/2.0\r
+Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r
+To: Bob <sip:bob@biloxi.com>\r
+From: Alice <sip:alice@atlanta.com>;tag=1928301774\r
+Call-ID: a84b4c76e66710@pc33.atlanta.com\r
+CSeq: 314159 INVITE\r
+\r
+";
+        let mut sip_message = SipMessage::new_from_str(request);
+        sip_message.parse_without_validation().unwrap();
+        assert_eq!(sip_message.local_tag().unwrap(), None);
+        assert_eq!(sip_message.remote_tag().unwrap(), Some("1928301774"));
+
+        let response = request
+            .replace("INVITE sip:bob@biloxi.com SIP/2.0", "SIP/2.0 200 OK")
+            .replace("<sip:bob@biloxi.com>\r", "<sip:bob@biloxi.com>;tag=a6c85cf\r");
+        let mut sip_message = SipMessage::new_from_str(&response);
+        sip_message.parse_without_validation().unwrap();
+        assert_eq!(sip_message.local_tag().unwrap(), Some("1928301774"));
+        assert_eq!(sip_message.remote_tag().unwrap(), Some("a6c85cf"));
+        assert!(sip_message.validate_tags().is_ok());
+
+        let mut untagged = SipMessage::new_from_str(&request.replace("INVITE sip:bob@biloxi.com SIP/2.0", "SIP/2.0 200 OK"));
+        untagged.parse_without_validation().unwrap();
+        assert!(untagged.validate_tags().is_err());
+        let mut ringing = SipMessage::new_from_str(&request.replace("INVITE sip:bob@biloxi.com SIP/2.0", "SIP/2.0 180 Ringing"));
+        ringing.parse_without_validation().unwrap();
+        assert!(ringing.validate_tags().is_ok());
+        assert_eq!(ringing.remote_tag().unwrap(), None);
+    }
 
-        // Check parameters
-        assert_eq!(params_map.get("expires").unwrap(), &Some("3600"));
-        assert_eq!(params_map.get("q").unwrap(), &Some("0.8"));
-        assert_eq!(params_map.get("priority").unwrap(), &Some("1"));
+    #[test]
+    fn test_negotiate_options() {
+        let message = "\
+INVITE sip:bob@biloxi.com SIP/2.0\r
+Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r
+To: Bob <sip:bob@biloxi.com>\r
+From: Alice <sip:alice@atlanta.com>;tag=1928301774\r
+Call-ID: a84b4c76e66710@pc33.atlanta.com\r
+CSeq: 314159 INVITE\r
+Require: 100rel, foo\r
+Proxy-Require: sec-agree\r
+Supported: timer, replaces, Foo\r
+Require: bar, FOO\r
+\r
+";
+        let mut sip_message = SipMessage::new_from_str(message);
+        let result = sip_message.negotiate_options(&["100REL", "timer", "path"]);
+        assert_eq!(result.unsupported, ["foo", "bar"]);
+        assert_eq!(result.unsupported_proxy, ["sec-agree"]);
+        assert_eq!(result.common, ["100rel", "timer"]);
+        assert!(!result.is_acceptable());
+        assert_eq!(result.unsupported_header().as_deref(), Some("foo, bar"));
+
+        let result = sip_message.negotiate_options(&["100rel", "foo", "bar"]);
+        assert!(result.is_acceptable());
+        assert_eq!(result.unsupported_header(), None);
     }
 
     #[test]
-    fn test_quoted_params() {
-        let input = "To: <sip:bob@example.com>;reason=\"moved temporarily\";info=\"contact info\"";
-        let mut message = SipMessage::new_from_str(input);
-        message
-            .process_header_line(TextRange::from_usize(0, input.len()))
-            .unwrap();
+    fn test_zero_content_length_with_trailing_bytes() {
+        let message = "\
+OPTIONS sip:bob@example.com SIP/2.0\r
+Via: SIP/2.0/UDP pc33.example.com;branch=z9hG4bK776asdhds\r
+To: Bob <sip:bob@example.com>\r
+From: Alice <sip:alice@example.com>;tag=1928301774\r
+Call-ID: a84b4c76e66710\r
+CSeq: 1 OPTIONS\r
+Content-Length: 0\r
+\r
+OPTIONS sip:carol@example.com SIP/2.0\r
+";
 
-        // In test methods, we need to clone to avoid borrowing conflicts
-        // This is only in tests, not performance-critical code
-        let raw_message = message.raw_message.clone();
+        // On a stream the trailing bytes start the next message, not a body
+        let mut sip_message = SipMessage::new_from_str(message);
+        sip_message.parse_headers().unwrap();
+        assert_eq!(sip_message.body(), None);
 
-        // Get the To header directly using to() method
-        let to_address = message.to().unwrap().expect("To header not found");
+        let datagram = ParserLimits {
+            datagram: true,
+            ..ParserLimits::default()
+        };
+        let mut sip_message = SipMessage::new_from_str_with_limits(message, datagram.clone());
+        assert!(sip_message.parse_headers().is_err());
 
-        // For consistency with our test, save as address
-        let address = to_address;
+        // Trailing whitespace in a datagram is harmless
+        let padded = message.split_once("\r\n\r\n").unwrap().0.to_string() + "\r\n\r\n\r\n";
+        let mut sip_message = SipMessage::new_from_str_with_limits(&padded, datagram);
+        assert!(sip_message.parse_headers().is_ok());
+        assert_eq!(sip_message.body(), None);
+    }
 
-        // Create parameter map directly using as_str
-        let mut params_map = HashMap::new();
-        for (k, v) in &address.params {
-            let key = k.as_str(&raw_message);
-            let value = v.as_ref().map(|r| r.as_str(&raw_message));
-            params_map.insert(key, value);
-        }
+    #[test]
+    fn test_body_bounded_by_content_length() {
+        let message = "\
+MESSAGE sip:bob@example.com SIP/2.0\r
+Via: SIP/2.0/UDP pc33.example.com;branch=z9hG4bK776asdhds\r
+To: Bob <sip:bob@example.com>\r
+From: Alice <sip:alice@example.com>;tag=1928301774\r
+Call-ID: a84b4c76e66710\r
+CSeq: 1 MESSAGE\r
+Content-Type: text/plain\r
+Content-Length: 5\r
+\r
+Hello, extra bytes past the declared length";
 
-        // Check quoted parameters - our implementation doesn't strip quotes
-        assert_eq!(
-            params_map.get("reason").unwrap(),
-            &Some("\"moved temporarily\"")
-        );
-        assert_eq!(params_map.get("info").unwrap(), &Some("\"contact info\""));
+        let mut sip_message = SipMessage::new_from_str(message);
+        sip_message.parse_headers().unwrap();
+        assert_eq!(sip_message.body(), Some("Hello"));
+        assert_eq!(sip_message.trailing_bytes(), Some(", extra bytes past the declared length"));
+
+        // Without Content-Length the body runs to the end
+        let unbounded = message.replace("Content-Length: 5\r\n", "");
+        let mut sip_message = SipMessage::new_from_str(&unbounded);
+        sip_message.parse_headers().unwrap();
+        assert!(sip_message.body().unwrap().ends_with("declared length"));
+        assert_eq!(sip_message.trailing_bytes(), None);
     }
 
     #[test]
-    fn test_no_user_info() {
-        let input = "Contact: <sip:example.com>";
-        let mut message = SipMessage::new_from_str(input);
-        message
-            .process_header_line(TextRange::from_usize(0, input.len()))
-            .unwrap();
-
-        // In test methods, we need to clone to avoid borrowing conflicts
-        // This is only in tests, not performance-critical code
-        let raw_message = message.raw_message.clone();
+    fn test_duplicate_via_branch() {
+        let message = "\
+INVITE sip:bob@example.com SIP/2.0\r
+Via: SIP/2.0/UDP proxy.example.com;branch=z9hG4bKloop, SIP/2.0/UDP edge.example.com;branch=z9hG4bKedge\r
+Via: SIP/2.0/UDP client.example.com;branch=z9hG4bKloop\r
+To: Bob <sip:bob@example.com>\r
+From: Alice <sip:alice@example.com>;tag=1928301774\r
+Call-ID: a84b4c76e66710\r
+CSeq: 1 INVITE\r
+\r
+";
 
-        // Get the Contact header directly using contact() method
-        let contact = message
-            .contact()
-            .unwrap()
-            .expect("Contact header not found");
+        let mut sip_message = SipMessage::new_from_str(message);
+        assert!(!sip_message.via_branches_unique());
+        assert_eq!(sip_message.duplicate_via_branch().unwrap(), Some("z9hG4bKloop"));
 
-        // Extract URI host value directly with as_str
-        let host_value = contact.uri.host.map(|r| r.as_str(&raw_message));
+        let unique = message.replacen("branch=z9hG4bKloop", "branch=z9hG4bKproxy", 1);
+        let mut sip_message = SipMessage::new_from_str(&unique);
+        assert!(sip_message.via_branches_unique());
+        assert_eq!(sip_message.duplicate_via_branch().unwrap(), None);
+    }
 
-        // Check URI components
-        assert_eq!(host_value, Some("example.com"));
+    #[test]
+    fn test_ipv6_hosts_at_every_site() {
+        let message = "\
+INVITE sip:bob@[2001:db8::2]:5062;transport=tcp SIP/2.0\r
+Via: SIP/2.0/UDP [2001:db8::1]:5060;branch=z9hG4bK776asdhds\r
+Route: <sip:[2001:db8::10];lr>\r
+Record-Route: <sip:[2001:db8::11]:5070;lr>\r
+To: Bob <sip:bob@example.com>\r
+From: Alice <sip:alice@example.com>;tag=1928301774\r
+Call-ID: a84b4c76e66710\r
+CSeq: 1 INVITE\r
+Contact: <sip:alice@[2001:db8::1]:5060>\r
+\r
+";
+        let mut sip_message = SipMessage::new_from_str(message);
+        sip_message.parse_headers().unwrap();
+
+        let uri = sip_message.request_uri().unwrap();
+        assert_eq!(sip_message.get_opt_str(uri.host), Some("2001:db8::2"));
+        assert_eq!(uri.port, Some(5062));
+
+        let via = sip_message.via().unwrap().unwrap().clone();
+        assert_eq!(via.host(sip_message.raw_message()), "2001:db8::1");
+        assert_eq!(via.port(sip_message.raw_message()), Some(5060));
+
+        let route = sip_message.routes().unwrap().remove(0);
+        assert_eq!(sip_message.get_opt_str(route.uri.host), Some("2001:db8::10"));
+        assert_eq!(route.uri.port, None);
+
+        let record_route_range = sip_message.header_value_ranges("record-route")[0];
+        let record_route = sip_message.parse_address(record_route_range).unwrap();
+        assert_eq!(sip_message.get_opt_str(record_route.uri.host), Some("2001:db8::11"));
+        assert_eq!(record_route.uri.port, Some(5070));
+
+        let contact = sip_message.contact().unwrap().unwrap().clone();
+        assert_eq!(sip_message.get_opt_str(contact.uri.host), Some("2001:db8::1"));
+        assert_eq!(contact.uri.port, Some(5060));
+
+        // An unbracketed IPv6 address is ambiguous with the port separator
+        let bare = message.replace("[2001:db8::1]:5060;branch", "2001:db8::1;branch");
+        let mut sip_message = SipMessage::new_from_str(&bare);
+        sip_message.parse_headers().unwrap();
+        assert!(sip_message.via().is_err());
     }
 
     #[test]
-    fn test_tel_uri() {
-        let input = "Contact: <tel:+1-212-555-0123;phone-context=example.com>";
-        let mut message = SipMessage::new_from_str(input);
-        message
-            .process_header_line(TextRange::from_usize(0, input.len()))
-            .unwrap();
+    fn test_address_same_uri_as() {
+        let route_message = |route: &str| {
+            let message = format!(
+                "OPTIONS sip:bob@example.com SIP/2.0\r\n\
+                 Via: SIP/2.0/UDP pc33.example.com;branch=z9hG4bK776asdhds\r\n\
+                 Route: {}\r\n\
+                 To: <sip:bob@example.com>\r\n\
+                 From: <sip:alice@example.com>;tag=1928301774\r\n\
+                 Call-ID: a84b4c76e66710\r\n\
+                 CSeq: 1 OPTIONS\r\n\
+                 \r\n",
+                route
+            );
+            let mut sip_message = SipMessage::new_from_str(&message);
+            sip_message.parse_headers().unwrap();
+            let route = sip_message.routes().unwrap().remove(0);
+            (sip_message, route)
+        };
 
-        // In test methods, we need to clone to avoid borrowing conflicts
-        // This is only in tests, not performance-critical code
-        let raw_message = message.raw_message.clone();
+        let (top, top_route) = route_message("<sip:%70roxy@edge.example.com;lr;transport=tcp>");
+        let same_uri = |candidate: &str| {
+            let (own, own_route) = route_message(candidate);
+            top_route.same_uri_as(top.raw_message(), &own_route, own.raw_message())
+        };
 
-        // Get the Contact header directly using contact() method
-        let contact = message
-            .contact()
-            .unwrap()
-            .expect("Contact header not found");
+        assert!(same_uri("\"Edge\" <sip:proxy@EDGE.example.com;transport=TCP>;foo=bar"));
+        assert!(same_uri("<sip:proxy@edge.example.com;transport=tcp;lr;other=1>"));
+        assert!(!same_uri("<sip:proxy@edge.example.com;lr>"));
+        assert!(!same_uri("<sip:proxy@edge.example.com:5060;lr;transport=tcp>"));
+        assert!(!same_uri("<sip:Proxy@edge.example.com;lr;transport=tcp>"));
+        assert!(!same_uri("<sips:proxy@edge.example.com;lr;transport=tcp>"));
+    }
 
-        // For consistency with our test, save as address
-        let address = contact;
+    #[test]
+    fn test_read_from_buffered_reader() {
+        let first = "\
+MESSAGE sip:bob@example.com SIP/2.0\r
+Via: SIP/2.0/TCP pc33.example.com;branch=z9hG4bK776asdhds\r
+To: Bob <sip:bob@example.com>\r
+From: Alice <sip:alice@example.com>;tag=1928301774\r
+Call-ID: a84b4c76e66710\r
+CSeq: 1 MESSAGE\r
+l: 11\r
+\r
+Hello World";
+        let second = first
+            .replace("CSeq: 1", "CSeq: 2")
+            .replace("l: 11\r\n", "")
+            .replace("Hello World", "");
+        let capture = format!("\r\n{}\r\n\r\n{}", first, second);
+        let mut reader = std::io::BufReader::new(capture.as_bytes());
+        let limits = ParserLimits::default();
+
+        let message = SipMessage::read_from(&mut reader, &limits).unwrap().unwrap();
+        assert_eq!(message.raw_message(), first);
+        assert_eq!(message.body(), Some("Hello World"));
+
+        let message = SipMessage::read_from(&mut reader, &limits).unwrap().unwrap();
+        assert!(message.raw_message().contains("CSeq: 2 MESSAGE"));
+        assert_eq!(message.body(), None);
+
+        assert!(SipMessage::read_from(&mut reader, &limits).unwrap().is_none());
+
+        let truncated = &first[..first.len() - 3];
+        assert!(SipMessage::read_from(truncated.as_bytes(), &limits).is_err());
+
+        // A line that never ends stops at the limit instead of buffering on
+        let endless = format!("MESSAGE sip:bob@example.com SIP/2.0\r\nSubject: {}", "x".repeat(1 << 20));
+        let limits = ParserLimits::strict();
+        match SipMessage::read_from(endless.as_bytes(), &limits) {
+            Err(SsbcError::ParseError { context, .. }) => assert_eq!(context.as_deref(), Some("DoS protection")),
+            other => panic!("expected a line length error, got {:?}", other),
+        }
+        let huge_body = first.replace("l: 11", &format!("l: {}", limits.max_body_size + 1));
+        assert!(SipMessage::read_from(huge_body.as_bytes(), &limits).is_err());
+    }
 
-        // Extract values
-        let scheme = address.uri.scheme.clone();
-        let user_info_value = address.uri.user_info.map(|r| r.as_str(&raw_message));
-        let mut params_map = HashMap::new();
+    #[test]
+    fn test_reject_control_chars_in_headers() {
+        let message = "\
+MESSAGE sip:bob@example.com SIP/2.0\r
+Via: SIP/2.0/UDP pc33.example.com;branch=z9hG4bK776asdhds\r
+To: Bob <sip:bob@example.com>\r
+From: Alice <sip:alice@example.com>;tag=1928301774\r
+Call-ID: a84b4c76e66710\r
+CSeq: 1 MESSAGE\r
+Subject: hi\0there\r
+Content-Length: 5\r
+\r
+a\0b\x01c";
 
-        // Create a custom parameter map
-        for (k, v) in &address.uri.params {
-            let key = k.as_str(&raw_message);
-            let value = v.as_ref().map(|r| r.as_str(&raw_message));
-            params_map.insert(key, value);
+        let mut sip_message = SipMessage::new_from_str_with_limits(message, ParserLimits::strict());
+        match sip_message.parse_headers() {
+            Err(SsbcError::ParseError { message, position, .. }) => {
+                assert!(message.contains("0x00 at byte"), "{}", message);
+                assert_eq!(position, Some((7, 11)));
+            }
+            other => panic!("expected a parse error, got {:?}", other),
         }
 
-        // Check URI components
-        assert_eq!(scheme, Scheme::TEL);
-        assert_eq!(user_info_value, Some("+1-212-555-0123"));
+        // The body is exempt
+        let clean = message.replace("hi\0there", "hi there");
+        let mut sip_message = SipMessage::new_from_str_with_limits(&clean, ParserLimits::strict());
+        sip_message.parse_headers().unwrap();
+        assert_eq!(sip_message.body(), Some("a\0b\x01c"));
+
+        // Leading CRLFs do not hide the header section from the scan
+        let limits = ParserLimits { reject_control_chars: true, ..ParserLimits::default() };
+        let mut sip_message = SipMessage::new_from_str_with_limits(&format!("\r\n\r\n{}", message), limits);
+        match sip_message.parse_headers() {
+            Err(SsbcError::ParseError { message, position, .. }) => {
+                assert!(message.contains("0x00 at byte"), "{}", message);
+                assert_eq!(position, Some((9, 11)));
+            }
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+    }
 
-        // Check parameters
+    #[test]
+    fn test_target_dialog_and_refer_sub() {
+        let message = "\
+REFER sip:carol@example.com SIP/2.0\r
+Via: SIP/2.0/UDP pc33.example.com;branch=z9hG4bK776asdhds\r
+To: <sip:carol@example.com>\r
+From: Alice <sip:alice@example.com>;tag=1928301774\r
+Call-ID: a84b4c76e66710\r
+CSeq: 1 REFER\r
+Refer-To: <sip:bob@example.com>\r
+Refer-Sub: FALSE\r
+Target-Dialog: 7a9f@192.0.2.1;remote-tag=3456;local-tag=1234\r
+\r
+";
+        let mut sip_message = SipMessage::new_from_str(message);
         assert_eq!(
-            params_map.get("phone-context").unwrap(),
-            &Some("example.com")
+            sip_message.target_dialog().unwrap(),
+            Some(DialogId {
+                call_id: "7a9f@192.0.2.1".to_string(),
+                local_tag: "1234".to_string(),
+                remote_tag: "3456".to_string(),
+            })
         );
-    }
+        assert_eq!(sip_message.refer_sub().unwrap(), Some(false));
+        assert!(sip_message.extension_headers().next().is_none());
 
-    #[test]
-    fn test_via_header() {
-        let input = "Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds;received=192.0.2.1";
-        let mut message = SipMessage::new_from_str(input);
-        message
-            .process_header_line(TextRange::from_usize(0, input.len()))
-            .unwrap();
+        let missing_tag = message.replace(";local-tag=1234", "");
+        assert!(SipMessage::new_from_str(&missing_tag).target_dialog().is_err());
 
-        // In test methods, we need to clone to avoid borrowing conflicts
-        // This is only in tests, not performance-critical code
-        let raw_message = message.raw_message.clone();
+        let plain = message.replace("Refer-Sub: FALSE\r\n", "").replace("Target-Dialog", "X-Other");
+        let mut sip_message = SipMessage::new_from_str(&plain);
+        assert_eq!(sip_message.target_dialog().unwrap(), None);
+        assert_eq!(sip_message.refer_sub().unwrap(), None);
+    }
 
-        // Get the Via header
-        let via = message.via().unwrap().expect("Via header not found");
+    #[test]
+    fn test_response_via_stack() {
+        let message = "\
+INVITE sip:bob@example.com SIP/2.0\r
+Via: SIP/2.0/UDP proxy2.example.com;branch=z9hG4bKp2, SIP/2.0/TCP proxy1.example.com\r
+ ;branch=z9hG4bKp1\r
+To: Bob <sip:bob@example.com>\r
+v: SIP/2.0/UDP client.example.com;branch=z9hG4bK776asdhds\r
+From: Alice <sip:alice@example.com>;tag=1928301774\r
+Call-ID: a84b4c76e66710\r
+CSeq: 1 INVITE\r
+\r
+";
+        let mut sip_message = SipMessage::new_from_str(message);
+        assert_eq!(
+            sip_message.response_via_stack().unwrap(),
+            vec![
+                "SIP/2.0/UDP proxy2.example.com;branch=z9hG4bKp2",
+                "SIP/2.0/TCP proxy1.example.com ;branch=z9hG4bKp1",
+                "SIP/2.0/UDP client.example.com;branch=z9hG4bK776asdhds",
+            ]
+        );
 
-        // Extract values using as_str
-        let sent_protocol_str = via.sent_protocol.as_str(&raw_message);
-        let sent_by_str = via.sent_by.as_str(&raw_message);
+        let broken = message.replace("v: SIP/2.0/UDP client", "v: SIP/2.0/UDPclient");
+        assert!(SipMessage::new_from_str(&broken).response_via_stack().is_err());
+    }
 
-        // Create a custom map of the parameters using as_str
-        let mut params_map = HashMap::new();
-        for (k, v) in &via.params {
-            let key = k.as_str(&raw_message);
-            let value = v.as_ref().map(|r| r.as_str(&raw_message));
-            params_map.insert(key, value);
+    #[test]
+    fn test_is_cancel_for() {
+        let invite_text = "\
+INVITE sip:bob@example.com SIP/2.0\r
+Via: SIP/2.0/UDP pc33.example.com;branch=z9hG4bK776asdhds\r
+To: Bob <sip:bob@example.com>\r
+From: Alice <sip:alice@example.com>;tag=1928301774\r
+Call-ID: a84b4c76e66710\r
+CSeq: 314159 INVITE\r
+\r
+";
+        let mut invite = SipMessage::new_from_str(invite_text);
+        invite.parse_headers().unwrap();
+
+        let cancel_text = invite_text
+            .replace("INVITE sip:", "CANCEL sip:")
+            .replace("314159 INVITE", "314159 CANCEL")
+            .replace("pc33.example.com", "PC33.example.com");
+        assert!(SipMessage::new_from_str(&cancel_text).is_cancel_for(&invite).unwrap());
+
+        let non_matching = [
+            cancel_text.replace("z9hG4bK776asdhds", "z9hG4bKother"),
+            cancel_text.replace("PC33.example.com", "pc34.example.com"),
+            cancel_text.replace("314159 CANCEL", "314160 CANCEL"),
+            cancel_text.replace("314159 CANCEL", "314159 INVITE"),
+            cancel_text.replace("CANCEL sip:", "BYE sip:").replace("CANCEL\r", "BYE\r"),
+        ];
+        for cancel in non_matching {
+            assert!(!SipMessage::new_from_str(&cancel).is_cancel_for(&invite).unwrap(), "{}", cancel);
         }
 
-        // Now check the values
-        assert_eq!(sent_protocol_str, "SIP/2.0/UDP");
-        assert_eq!(sent_by_str, "pc33.atlanta.com");
-        assert_eq!(params_map.get("branch").unwrap(), &Some("z9hG4bK776asdhds"));
-        assert_eq!(params_map.get("received").unwrap(), &Some("192.0.2.1"));
+        let no_branch = cancel_text.replace(";branch=z9hG4bK776asdhds", "");
+        assert!(SipMessage::new_from_str(&no_branch).is_cancel_for(&invite).is_err());
     }
 
     #[test]
-    fn test_escaped_uri() {
-        let input = "Contact: <sip:user%20name@host.com;transport=tcp?subject=Meeting%20Request>";
-        let mut message = SipMessage::new_from_str(input);
-        message
-            .process_header_line(TextRange::from_usize(0, input.len()))
-            .unwrap();
+    fn test_charging_headers() {
+        let message = "\
+INVITE sip:bob@example.com SIP/2.0\r
+Via: SIP/2.0/UDP pc33.example.com;branch=z9hG4bK776asdhds\r
+To: Bob <sip:bob@example.com>\r
+From: Alice <sip:alice@example.com>;tag=1928301774\r
+Call-ID: a84b4c76e66710\r
+CSeq: 1 INVITE\r
+P-Charging-Vector: icid-value=\"AyretyU0dm+6O2IrT5tAFrbHLso=;023551024\"; icid-generated-at=192.0.6.8;orig-ioi=home1.net\r
+P-Charging-Function-Addresses: ccf=192.1.1.1; ccf=\"[2001:db8::1]\"; ecf=192.1.1.3\r
+P-Charging-Function-Addresses: ecf=192.1.1.4\r
+\r
+";
+        let mut sip_message = SipMessage::new_from_str(message);
+        assert_eq!(
+            sip_message.charging_vector().unwrap(),
+            Some(ChargingVector {
+                icid_value: "AyretyU0dm+6O2IrT5tAFrbHLso=;023551024".to_string(),
+                icid_generated_at: Some("192.0.6.8".to_string()),
+                orig_ioi: Some("home1.net".to_string()),
+                term_ioi: None,
+            })
+        );
+        assert_eq!(
+            sip_message.charging_function_addresses().unwrap(),
+            Some(ChargingFunctionAddresses {
+                ccf: vec!["192.1.1.1".to_string(), "[2001:db8::1]".to_string()],
+                ecf: vec!["192.1.1.3".to_string(), "192.1.1.4".to_string()],
+            })
+        );
 
-        // In test methods, we need to clone to avoid borrowing conflicts
-        // This is only in tests, not performance-critical code
-        let raw_message = message.raw_message.clone();
+        let no_icid = message.replace("icid-value=", "other=");
+        assert!(SipMessage::new_from_str(&no_icid).charging_vector().is_err());
+        let unterminated = message.replace("023551024\"", "023551024");
+        assert!(SipMessage::new_from_str(&unterminated).charging_vector().is_err());
+    }
 
-        // Get the Contact header directly using contact() method
-        let contact = message
-            .contact()
-            .unwrap()
-            .expect("Contact header not found");
+    #[test]
+    fn test_call_control_target() {
+        let message = "\
+INVITE sip:bob@example.com SIP/2.0\r
+Via: SIP/2.0/UDP pc33.example.com;branch=z9hG4bK776asdhds\r
+To: Bob <sip:bob@example.com>\r
+From: Alice <sip:alice@example.com>;tag=1928301774\r
+Call-ID: a84b4c76e66710\r
+CSeq: 1 INVITE\r
+Replaces: 98732@sip.example.com;from-tag=r33th4x0r;to-tag=ff87ff;early-only\r
+\r
+";
+        let dialog = CallReference {
+            call_id: "98732@sip.example.com".to_string(),
+            to_tag: "ff87ff".to_string(),
+            from_tag: "r33th4x0r".to_string(),
+            early_only: true,
+        };
+        let mut sip_message = SipMessage::new_from_str(message);
+        assert_eq!(sip_message.call_control_target().unwrap(), Some(CallControlTarget::Replaces(dialog.clone())));
 
-        // For consistency with our test, save as address
-        let address = contact;
+        let join = message.replace("Replaces:", "Join:").replace(";early-only", "");
+        let mut sip_message = SipMessage::new_from_str(&join);
+        assert_eq!(
+            sip_message.call_control_target().unwrap(),
+            Some(CallControlTarget::Join(CallReference { early_only: false, ..dialog }))
+        );
 
-        // Extract the needed values with as_str
-        let user_info_value = address.uri.user_info.map(|r| r.as_str(&raw_message));
-        let headers_value = address.uri.headers.map(|r| r.as_str(&raw_message));
+        let both = message.replace("\r\n\r\n", "\r\nJoin: 1@example.com;from-tag=a;to-tag=b\r\n\r\n");
+        assert!(SipMessage::new_from_str(&both).call_control_target().is_err());
+        let missing_tag = message.replace(";to-tag=ff87ff", "");
+        assert!(SipMessage::new_from_str(&missing_tag).replaces().is_err());
+    }
 
-        // Create a custom parameter map
-        let mut params_map = HashMap::new();
-        for (k, v) in &address.uri.params {
-            let key = k.as_str(&raw_message);
-            let value = v.as_ref().map(|r| r.as_str(&raw_message));
-            params_map.insert(key, value);
-        }
+    #[test]
+    fn test_allowed_unknown_methods() {
+        let request = |method: &str| {
+            format!(
+                "{} sip:bob@example.com SIP/2.0\r\n\
+                 Via: SIP/2.0/UDP pc33.example.com;branch=z9hG4bK776asdhds\r\n\
+                 To: Bob <sip:bob@example.com>\r\n\
+                 From: Alice <sip:alice@example.com>;tag=1928301774\r\n\
+                 Call-ID: a84b4c76e66710\r\n\
+                 CSeq: 1 {}\r\n\
+                 \r\n",
+                method, method
+            )
+        };
+        let limits = ParserLimits {
+            allowed_unknown_methods: Some(vec!["PING".to_string()]),
+            ..ParserLimits::default()
+        };
+        let parse = |method: &str, limits: ParserLimits| {
+            SipMessage::new_from_str_with_limits(&request(method), limits).parse_headers()
+        };
 
-        // Check URI components with raw values instead of decoded values
-        assert_eq!(user_info_value, Some("user%20name"));
+        assert!(parse("INVITE", limits.clone()).is_ok());
+        assert!(parse("PING", limits.clone()).is_ok());
+        assert!(parse("FOOBAR", limits.clone()).is_err());
+        assert!(parse("FOOBAR", ParserLimits::default()).is_ok());
 
-        // Check parameters
-        assert_eq!(params_map.get("transport").unwrap(), &Some("tcp"));
+        // Responses carry no method to check
+        let response = request("FOOBAR").replacen("FOOBAR sip:bob@example.com SIP/2.0", "SIP/2.0 200 OK", 1);
+        assert!(SipMessage::new_from_str_with_limits(&response, limits).parse_headers().is_ok());
+    }
 
-        // Check headers - our implementation doesn't decode the URI headers
-        assert_eq!(headers_value, Some("subject=Meeting%20Request"));
+    #[test]
+    fn test_lazy_parse_state() {
+        let message = "\
+INVITE sip:bob@example.com SIP/2.0\r
+Via: SIP/2.0/UDP proxy.example.com;branch=z9hG4bKproxy\r
+Via: SIP/2.0/UDP pc33.example.com;branch=z9hG4bK776asdhds\r
+To: Bob <sip:bob@example.com>\r
+From: Alice <sip:alice@example.com>;tag=1928301774\r
+Call-ID: a84b4c76e66710\r
+CSeq: 1 INVITE\r
+Contact: <sip:alice@pc33.example.com>\r
+\r
+";
+        let mut sip_message = SipMessage::new_from_str(message);
+        assert_eq!(sip_message.lazy_parse_state(), LazyState::default());
+
+        sip_message.parse_headers().unwrap();
+        // Raw accessors keep the zero-copy path
+        sip_message.call_id_str();
+        sip_message.from_tag();
+        let state = sip_message.lazy_parse_state();
+        assert!(state.headers_parsed && state.is_zero_copy());
+        assert_eq!((state.via_total, state.contact_total), (2, 1));
+
+        sip_message.from().unwrap();
+        sip_message.via().unwrap();
+        let state = sip_message.lazy_parse_state();
+        assert!(state.from && !state.to);
+        assert_eq!((state.via_parsed, state.contact_parsed), (1, 0));
+        assert!(!state.is_zero_copy());
     }
 
     #[test]
-    fn test_multiple_headers_record_route() {
-        let input = "Record-Route: <sip:proxy1.example.com;lr>, <sip:proxy2.example.com;lr>";
-        let mut message = SipMessage::new_from_str(input);
-        message
-            .process_header_line(TextRange::from_usize(0, input.len()))
-            .unwrap();
+    fn test_coalesced_header() {
+        let message = "\
+INVITE sip:bob@example.com SIP/2.0\r
+Via: SIP/2.0/UDP proxy.example.com;branch=z9hG4bKproxy\r
+Allow: INVITE, ACK\r
+Supported: timer\r
+To: Bob <sip:bob@example.com>\r
+v: SIP/2.0/UDP pc33.example.com;branch=z9hG4bK776asdhds\r
+Allow:\r
+ BYE,\r
+ CANCEL\r
+Allow: \r
+From: Alice <sip:alice@example.com>;tag=1928301774\r
+Call-ID: a84b4c76e66710\r
+CSeq: 1 INVITE\r
+\r
+";
+        let mut sip_message = SipMessage::new_from_str(message);
+        sip_message.parse_headers().unwrap();
 
-        // Manually extract the Record-Route headers
-        let record_route_headers: Vec<_> = message
-            .headers
-            .iter()
-            .filter(|(name, _)| message.get_str(*name).to_lowercase() == "record-route")
-            .map(|(_, value)| value)
-            .collect();
+        assert_eq!(sip_message.coalesced_header("allow").as_deref(), Some("INVITE, ACK, BYE, CANCEL"));
+        assert_eq!(
+            sip_message.coalesced_header("Via").as_deref(),
+            Some("SIP/2.0/UDP proxy.example.com;branch=z9hG4bKproxy, SIP/2.0/UDP pc33.example.com;branch=z9hG4bK776asdhds")
+        );
+        assert_eq!(sip_message.coalesced_header("k").as_deref(), Some("timer"));
+        assert_eq!(sip_message.coalesced_header("Route"), None);
+    }
 
-        // We should have at least one header entry
-        assert!(!record_route_headers.is_empty());
+    #[test]
+    fn test_contact_feature_tags() {
+        let message = "\
+REGISTER sip:registrar.example.com SIP/2.0\r
+Via: SIP/2.0/UDP pc33.example.com;branch=z9hG4bK776asdhds\r
+To: <sip:alice@example.com>\r
+From: <sip:alice@example.com>;tag=1928301774\r
+Call-ID: a84b4c76e66710\r
+CSeq: 1 REGISTER\r
+Contact: <sip:alice@pc33.example.com>;audio;video=\"FALSE\";methods=\"INVITE,BYE\";expires=3600;+sip.instance=\"<urn:uuid:00000000-0000-1000-8000-000A95A0E128>\";+sip.extensions=\"100rel,!gruu\"\r
+\r
+";
+        let mut sip_message = SipMessage::new_from_str(message);
+        sip_message.parse_without_validation().unwrap();
+        let contact = sip_message.contact().unwrap().unwrap().clone();
+        let tags = contact.feature_tags(sip_message.raw_message());
+
+        assert_eq!(tags.tags.len(), 5);
+        assert!(tags.has("audio"));
+        assert!(!tags.has("video"));
+        assert!(tags.get("expires").is_none());
+        assert_eq!(
+            tags.get("methods"),
+            Some(&FeatureValue::List(vec!["INVITE".to_string(), "BYE".to_string()]))
+        );
+        assert_eq!(
+            tags.get("+sip.extensions"),
+            Some(&FeatureValue::List(vec!["100rel".to_string(), "!gruu".to_string()]))
+        );
+        assert_eq!(
+            tags.get("sip.instance"),
+            Some(&FeatureValue::String("urn:uuid:00000000-0000-1000-8000-000A95A0E128".to_string()))
+        );
+    }
 
-        // Examine the values - our implementation should handle multiple values within a header
-        // This depends on how your implementation handles comma-separated header values
-        // This test might need adjustment based on your implementation
-        match &record_route_headers[0] {
-            HeaderValue::Raw(range) => {
-                let value = message.get_str(*range);
-                assert!(value.contains("proxy1.example.com"));
-                assert!(value.contains("proxy2.example.com"));
-            }
-            // If your implementation parses Record-Route as Address types
-            // then we'd need different assertions here
-            _ => {
-                // Depending on your implementation, we might need to check for
-                // HeaderValue::Address variants or other specific handling
-                // This is a simplified check for now
+    #[test]
+    fn test_caller_preferences() {
+        let message = "\
+INVITE sip:bob@example.com SIP/2.0\r
+Via: SIP/2.0/UDP pc33.example.com;branch=z9hG4bK776asdhds\r
+To: <sip:bob@example.com>\r
+From: <sip:alice@example.com>;tag=1928301774\r
+Call-ID: a84b4c76e66710\r
+CSeq: 1 INVITE\r
+Accept-Contact: *;audio;require;explicit, *;methods=\"INVITE,BYE\";q=0.5\r
+j: *;automata\r
+\r
+";
+        let mut sip_message = SipMessage::new_from_str(message);
+        let accept = sip_message.accept_contact().unwrap();
+        assert_eq!(accept.len(), 2);
+        assert!(accept[0].require && accept[0].explicit);
+        assert!(accept[0].features.has("audio"));
+        assert!(!accept[1].require);
+        assert_eq!(
+            accept[1].features.get("methods"),
+            Some(&FeatureValue::List(vec!["INVITE".to_string(), "BYE".to_string()]))
+        );
+        assert_eq!(accept[1].features.tags.len(), 1);
 
-                // Implementation-specific validation needed for Record-Route header
-                // No assert needed here as this is just a placeholder for future implementation
-            }
-        }
+        let reject = sip_message.reject_contact().unwrap();
+        assert_eq!(reject.len(), 1);
+        assert!(reject[0].features.has("automata"));
+
+        let mut invalid = SipMessage::new_from_str(&message.replace("j: *;automata", "j: sip:x@y"));
+        assert!(invalid.reject_contact().is_err());
     }
 
     #[test]
-    fn test_from_uri_extraction() {
+    fn test_whitespace_before_header_colon() {
         let message = "\
-INVITE sip:bob@biloxi.com SIP/2.0\r
-Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r
-To: Bob <sip:bob@biloxi.com>\r
-From: Alice <sip:alice@atlanta.com:5060>;tag=1928301774\r
-Call-ID: a84b4c76e66710@pc33.atlanta.com\r
-CSeq: 314159 INVITE\r
-Max-Forwards: 70\r
+OPTIONS sip:bob@example.com SIP/2.0\r
+Via: SIP/2.0/UDP pc33.example.com;branch=z9hG4bK776asdhds\r
+To: <sip:bob@example.com>\r
+From : <sip:alice@example.com>;tag=1928301774\r
+Call-ID: a84b4c76e66710\r
+CSeq: 1 OPTIONS\r
+X-Custom\t: yes\r
 \r
 ";
         let mut sip_message = SipMessage::new_from_str(message);
-        assert!(sip_message.parse_headers().is_ok());
-
-        let from_uri = sip_message.from_uri().unwrap();
-        assert_eq!(from_uri.scheme, Scheme::SIP);
-        assert_eq!(sip_message.get_opt_str(from_uri.user_info), Some("alice"));
-        assert_eq!(sip_message.get_opt_str(from_uri.host), Some("atlanta.com"));
-        assert_eq!(from_uri.port, Some(5060));
+        sip_message.parse_without_validation().unwrap();
+        assert_eq!(sip_message.from_tag(), Some("1928301774"));
+        assert_eq!(sip_message.extension_headers().collect::<Vec<_>>(), vec![("X-Custom", "yes")]);
+
+        let mut strict = SipMessage::new_from_str_with_limits(message, ParserLimits::strict());
+        match strict.parse_without_validation() {
+            Err(SsbcError::ParseError { message: error, position, .. }) => {
+                assert!(error.contains("'From'"));
+                assert_eq!(position, Some((0, message.find("From :").unwrap() + 4)));
+            }
+            other => panic!("Expected whitespace error, got {:?}", other),
+        }
     }
 
     #[test]
-    fn test_to_uri_extraction() {
+    fn test_is_early_media() {
         let message = "\
-INVITE sip:bob@biloxi.com SIP/2.0\r
-Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r
-To: Bob <sip:bob@biloxi.com:5080;transport=tcp>\r
-From: Alice <sip:alice@atlanta.com>;tag=1928301774\r
-Call-ID: a84b4c76e66710@pc33.atlanta.com\r
-CSeq: 314159 INVITE\r
-Max-Forwards: 70\r
+SIP/2.0 183 Session Progress\r
+Via: SIP/2.0/UDP pc33.example.com;branch=z9hG4bK776asdhds\r
+To: <sip:bob@example.com>;tag=a6c85cf\r
+From: <sip:alice@example.com>;tag=1928301774\r
+Call-ID: a84b4c76e66710\r
+CSeq: 1 INVITE\r
+Content-Type: application/sdp\r
+Content-Length: 4\r
 \r
+v=0
 ";
         let mut sip_message = SipMessage::new_from_str(message);
-        assert!(sip_message.parse_headers().is_ok());
+        assert!(sip_message.is_early_media().unwrap());
 
-        let to_uri = sip_message.to_uri().unwrap();
-        assert_eq!(to_uri.scheme, Scheme::SIP);
-        assert_eq!(sip_message.get_opt_str(to_uri.user_info), Some("bob"));
-        assert_eq!(sip_message.get_opt_str(to_uri.host), Some("biloxi.com"));
-        assert_eq!(to_uri.port, Some(5080));
+        let mut ringing = SipMessage::new_from_str(&message.replace("183 Session Progress", "180 Ringing"));
+        assert!(ringing.is_early_media().unwrap());
 
-        // Check URI parameter
-        let params_map = sip_message.get_params_map(&to_uri.params);
-        assert_eq!(params_map.get("transport").unwrap(), &Some("tcp"));
+        let no_body = message.replace("Content-Length: 4", "Content-Length: 0").replace("v=0\n", "");
+        assert!(!SipMessage::new_from_str(&no_body).is_early_media().unwrap());
+
+        let answered = message.replace("183 Session Progress", "200 OK");
+        assert!(!SipMessage::new_from_str(&answered).is_early_media().unwrap());
+
+        let update = message.replace("CSeq: 1 INVITE", "CSeq: 2 UPDATE");
+        assert!(!SipMessage::new_from_str(&update).is_early_media().unwrap());
     }
 
     #[test]
-    fn test_contact_uri_extraction() {
+    fn test_response_destination() {
         let message = "\
-INVITE sip:bob@biloxi.com SIP/2.0\r
-Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r
-To: Bob <sip:bob@biloxi.com>\r
-From: Alice <sip:alice@atlanta.com>;tag=1928301774\r
-Call-ID: a84b4c76e66710@pc33.atlanta.com\r
-CSeq: 314159 INVITE\r
-Max-Forwards: 70\r
-Contact: <sip:alice@192.168.1.100:5060;transport=udp>\r
+INVITE sip:bob@example.com SIP/2.0\r
+Via: SIP/2.0/UDP pc33.example.com:5070;branch=z9hG4bK776asdhds;rport=40123;received=192.0.2.4\r
+Via: SIP/2.0/UDP proxy.example.com;branch=z9hG4bK4b43c2\r
+To: <sip:bob@example.com>\r
+From: <sip:alice@example.com>;tag=1928301774\r
+Call-ID: a84b4c76e66710\r
+CSeq: 1 INVITE\r
 \r
 ";
-        let mut sip_message = SipMessage::new_from_str(message);
-        assert!(sip_message.parse_headers().is_ok());
+        let destination = |message: &str| SipMessage::new_from_str(message).response_destination().unwrap();
+        assert_eq!(
+            destination(message),
+            ResponseDest { host: "192.0.2.4".to_string(), port: 40123, transport: Transport::Udp }
+        );
 
-        let contact_uri = sip_message.contact_uri().unwrap();
-        assert_eq!(contact_uri.scheme, Scheme::SIP);
+        let no_rport = message.replace(";rport=40123", ";rport");
+        assert_eq!(destination(&no_rport).port, 5070);
+
+        let plain = message.replace(";rport=40123;received=192.0.2.4", "").replace("pc33.example.com:5070", "pc33.example.com");
         assert_eq!(
-            sip_message.get_opt_str(contact_uri.user_info),
-            Some("alice")
+            destination(&plain),
+            ResponseDest { host: "pc33.example.com".to_string(), port: 5060, transport: Transport::Udp }
         );
+
+        let tls = plain.replace("SIP/2.0/UDP pc33.example.com;", "SIP/2.0/TLS [2001:db8::1];");
         assert_eq!(
-            sip_message.get_opt_str(contact_uri.host),
-            Some("192.168.1.100")
+            destination(&tls),
+            ResponseDest { host: "2001:db8::1".to_string(), port: 5061, transport: Transport::Tls }
         );
-        assert_eq!(contact_uri.port, Some(5060));
+
+        let maddr = message.replace(";received=192.0.2.4", ";maddr=239.255.255.1;received=192.0.2.4");
+        assert_eq!(destination(&maddr).host, "239.255.255.1");
+        assert_eq!(destination(&maddr).port, 5070);
     }
 
     #[test]
-    fn test_request_uri_extraction() {
+    fn test_recv_info_and_feature_caps() {
         let message = "\
-INVITE sip:bob@biloxi.com:5060;transport=tcp SIP/2.0\r
-Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r
-To: Bob <sip:bob@biloxi.com>\r
-From: Alice <sip:alice@atlanta.com>;tag=1928301774\r
-Call-ID: a84b4c76e66710@pc33.atlanta.com\r
-CSeq: 314159 INVITE\r
-Max-Forwards: 70\r
+INVITE sip:bob@example.com SIP/2.0\r
+Via: SIP/2.0/UDP pc33.example.com;branch=z9hG4bK776asdhds\r
+To: <sip:bob@example.com>\r
+From: <sip:alice@example.com>;tag=1928301774\r
+Call-ID: a84b4c76e66710\r
+CSeq: 1 INVITE\r
+Recv-Info: foo;version=2, bar\r
+Recv-Info: dtmf\r
+Feature-Caps: *;+g.3gpp.icsi-ref=\"urn%3Aurn-7%3A3gpp-service.ims.icsi.mmtel\";+sip.pns\r
 \r
 ";
         let mut sip_message = SipMessage::new_from_str(message);
-        assert!(sip_message.parse_headers().is_ok());
+        assert_eq!(sip_message.recv_info_packages(), ["foo", "bar", "dtmf"]);
 
-        let request_uri = sip_message.request_uri().unwrap();
-        assert_eq!(request_uri.scheme, Scheme::SIP);
-        assert_eq!(sip_message.get_opt_str(request_uri.user_info), Some("bob"));
+        let caps = sip_message.feature_caps().unwrap();
+        assert_eq!(caps.len(), 1);
+        assert!(caps[0].has("+sip.pns"));
         assert_eq!(
-            sip_message.get_opt_str(request_uri.host),
-            Some("biloxi.com")
+            caps[0].get("g.3gpp.icsi-ref"),
+            Some(&FeatureValue::List(vec!["urn%3Aurn-7%3A3gpp-service.ims.icsi.mmtel".to_string()]))
         );
-        assert_eq!(request_uri.port, Some(5060));
+        assert!(sip_message.extension_headers().next().is_none());
+
+        let mut empty = SipMessage::new_from_str(&message.replace("Recv-Info: foo;version=2, bar", "Recv-Info:"));
+        assert_eq!(empty.recv_info_packages(), ["dtmf"]);
     }
 
     #[test]
-    fn test_from_tag_extraction() {
+    fn test_has_header() {
         let message = "\
-INVITE sip:bob@biloxi.com SIP/2.0\r
-Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r
-To: Bob <sip:bob@biloxi.com>\r
-From: Alice <sip:alice@atlanta.com>;tag=1928301774\r
-Call-ID: a84b4c76e66710@pc33.atlanta.com\r
-CSeq: 314159 INVITE\r
-Max-Forwards: 70\r
+INVITE sip:bob@example.com SIP/2.0\r
+v: SIP/2.0/UDP pc33.example.com;branch=z9hG4bK776asdhds\r
+To: <sip:bob@example.com>\r
+From: <sip:alice@example.com>;tag=1928301774\r
+i: a84b4c76e66710\r
+CSeq: 1 INVITE\r
+m: <sip:alice@pc33.example.com>\r
+X-Custom: yes\r
 \r
 ";
         let mut sip_message = SipMessage::new_from_str(message);
-        assert!(sip_message.parse_headers().is_ok());
+        sip_message.parse_without_validation().unwrap();
 
-        let from_tag = sip_message.from_tag();
-        assert_eq!(from_tag, Some("1928301774"));
+        for name in ["Via", "via", "v", "Call-ID", "I", "Contact", "m", "CSEQ", "x-custom"] {
+            assert!(sip_message.has_header(name), "{}", name);
+        }
+        for name in ["Route", "Max-Forwards", "Subject", "s"] {
+            assert!(!sip_message.has_header(name), "{}", name);
+        }
     }
 
     #[test]
-    fn test_to_tag_extraction() {
+    fn test_header_count() {
         let message = "\
-SIP/2.0 200 OK\r
-Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r
-To: Bob <sip:bob@biloxi.com>;tag=a6c85cf\r
-From: Alice <sip:alice@atlanta.com>;tag=1928301774\r
-Call-ID: a84b4c76e66710@pc33.atlanta.com\r
-CSeq: 314159 INVITE\r
+INVITE sip:bob@example.com SIP/2.0\r
+Via: SIP/2.0/UDP proxy.example.com;branch=z9hG4bK4b43c2, SIP/2.0/UDP relay.example.com;branch=z9hG4bK77ef4c\r
+v: SIP/2.0/UDP pc33.example.com;branch=z9hG4bK776asdhds\r
+To: <sip:bob@example.com>\r
+From: <sip:alice@example.com>;tag=1928301774\r
+Call-ID: a84b4c76e66710\r
+CSeq: 1 INVITE\r
+Contact: <sip:alice@pc33.example.com>\r
+m: <sip:alice@192.0.2.4>\r
 \r
 ";
         let mut sip_message = SipMessage::new_from_str(message);
-        assert!(sip_message.parse_headers().is_ok());
+        sip_message.parse_without_validation().unwrap();
 
-        let to_tag = sip_message.to_tag();
-        assert_eq!(to_tag, Some("a6c85cf"));
+        assert_eq!(sip_message.header_count("Via"), 2);
+        assert_eq!(sip_message.header_count("m"), 2);
+        assert_eq!(sip_message.header_count("cseq"), 1);
+        assert_eq!(sip_message.header_count("Route"), 0);
     }
 
     #[test]
-    fn test_call_id_str() {
+    fn test_via_and_contact_counted_once() {
         let message = "\
-INVITE sip:bob@biloxi.com SIP/2.0\r
-Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r
-To: Bob <sip:bob@biloxi.com>\r
-From: Alice <sip:alice@atlanta.com>;tag=1928301774\r
-Call-ID: a84b4c76e66710@pc33.atlanta.com\r
-CSeq: 314159 INVITE\r
-Max-Forwards: 70\r
+INVITE sip:bob@example.com SIP/2.0\r
+Via: SIP/2.0/UDP proxy.example.com;branch=z9hG4bK4b43c2\r
+Via: SIP/2.0/UDP relay.example.com;branch=z9hG4bK77ef4c\r
+v: SIP/2.0/UDP pc33.example.com;branch=z9hG4bK776asdhds\r
+To: <sip:bob@example.com>\r
+From: <sip:alice@example.com>;tag=1928301774\r
+Call-ID: a84b4c76e66710\r
+CSeq: 1 INVITE\r
+Contact: <sip:alice@pc33.example.com>\r
 \r
 ";
         let mut sip_message = SipMessage::new_from_str(message);
-        assert!(sip_message.parse_headers().is_ok());
-
-        let call_id = sip_message.call_id_str();
-        assert_eq!(call_id, Some("a84b4c76e66710@pc33.atlanta.com"));
+        sip_message.parse_without_validation().unwrap();
+        sip_message.all_vias().unwrap();
+        sip_message.contacts().unwrap();
+
+        assert_eq!(sip_message.header_count("Via"), 3);
+        assert_eq!(sip_message.get_headers_by_name("v").len(), 3);
+        assert_eq!(sip_message.header_count("Contact"), 1);
+        assert_eq!(sip_message.header_ranges().count(), 8);
     }
 
     #[test]
-    fn test_missing_from_uri_error() {
+    fn test_response_without_max_forwards() {
         let message = "\
-INVITE sip:bob@biloxi.com SIP/2.0\r
-Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r
-To: Bob <sip:bob@biloxi.com>\r
-Call-ID: a84b4c76e66710@pc33.atlanta.com\r
-CSeq: 314159 INVITE\r
-Max-Forwards: 70\r
+SIP/2.0 200 OK\r
+Via: SIP/2.0/UDP pc33.example.com;branch=z9hG4bK776asdhds\r
+To: <sip:bob@example.com>;tag=a6c85cf\r
+From: <sip:alice@example.com>;tag=1928301774\r
+Call-ID: a84b4c76e66710\r
+CSeq: 1 INVITE\r
+Content-Length: 0\r
 \r
 ";
-        let mut sip_message = SipMessage::new_from_str(message);
-        assert!(sip_message.parse_without_validation().is_ok());
+        let mut sip_message = SipMessage::parse(message.as_bytes()).unwrap();
+        assert_eq!(sip_message.max_forwards(), None);
+        assert!(!sip_message.has_header("Max-Forwards"));
 
-        let result = sip_message.from_uri();
-        assert!(result.is_err());
+        let report = sip_message.compliance_report();
+        assert!(report.items.iter().all(|item| item.check != "max-forwards"));
     }
 
     #[test]
-    fn test_request_uri_on_response_error() {
+    fn test_uri_percent_escapes() {
+        fn parse_uri(uri_str: &str, limits: ParserLimits) -> Result<SipUri, SsbcError> {
+            let message = SipMessage::new_from_str_with_limits(uri_str, limits);
+            message.parse_uri(TextRange::from_usize(0, uri_str.len()))
+        }
+
+        assert!(parse_uri("sip:u%20x@host;p=%41?h=%4a", ParserLimits::default()).is_ok());
+        for (uri, component) in [
+            ("sip:u@host;p=%ZZ", "parameter"),
+            ("sip:u@host;p=%2", "parameter"),
+            ("sip:u@[fe80::1%2]", "host"),
+            ("sip:u@host;transport=tcp?subject=100%", "header"),
+            ("sip:u@host?subject=%G1", "header"),
+            ("tel:+1234;phone-context=%", "parameter"),
+        ] {
+            match parse_uri(uri, ParserLimits::strict()) {
+                Err(SsbcError::ParseError { message, .. }) => assert!(message.contains(component), "{}: {}", uri, message),
+                other => panic!("Expected escape error for {}, got {:?}", uri, other),
+            }
+        }
+
+        // Other limits let bad escapes through, except in the user part
+        for limits in [ParserLimits::default(), ParserLimits::carrier_grade()] {
+            assert!(parse_uri("sip:u@host;p=%ZZ?h=%", limits.clone()).is_ok());
+            assert!(parse_uri("sip:u%ZZ@host", limits).is_err());
+        }
+    }
+
+    #[test]
+    fn test_request_uri_is_local() {
+        let request = |uri: &str| {
+            SipMessage::new_from_str(&format!(
+                "OPTIONS {} SIP/2.0\r\nVia: SIP/2.0/UDP pc33.example.com;branch=z9hG4bK776asdhds\r\n\
+                 To: <sip:bob@example.com>\r\nFrom: <sip:alice@example.com>;tag=1\r\nCall-ID: abc\r\nCSeq: 1 OPTIONS\r\n\r\n",
+                uri
+            ))
+        };
+        let local = ["Example.COM", "192.0.2.10:5060", "[2001:db8::1]", "::1"];
+
+        for uri in ["sip:bob@example.com", "sip:bob@EXAMPLE.com.", "sips:192.0.2.10:5061", "sip:[2001:DB8:0::1]", "sip:[0:0::1]"] {
+            assert!(request(uri).request_uri_is_local(&local).unwrap(), "{}", uri);
+        }
+        for uri in ["sip:bob@example.org", "sip:bob@sub.example.com", "sip:192.0.2.11", "tel:+15551234"] {
+            assert!(!request(uri).request_uri_is_local(&local).unwrap(), "{}", uri);
+        }
+    }
+
+    #[test]
+    fn test_to_parsed() {
         let message = "\
-SIP/2.0 200 OK\r
-Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r
-To: Bob <sip:bob@biloxi.com>;tag=a6c85cf\r
-From: Alice <sip:alice@atlanta.com>;tag=1928301774\r
-Call-ID: a84b4c76e66710@pc33.atlanta.com\r
+INVITE sip:bob@example.com;transport=tcp SIP/2.0\r
+Via: SIP/2.0/TCP proxy.example.com:5070;branch=z9hG4bK4b43c2, SIP/2.0/UDP [2001:db8::1];branch=z9hG4bK776asdhds;rport\r
+Max-Forwards: 69\r
+Route: <sip:edge.example.com;lr>\r
+Record-Route: <sip:proxy.example.com;lr>\r
+To: Bob <sip:bob@example.com>\r
+From: \"Alice\" <sip:alice@example.com>;tag=1928301774\r
+Call-ID: a84b4c76e66710\r
 CSeq: 314159 INVITE\r
+Contact: <sip:alice@pc33.example.com>;expires=60\r
+Subject: lunch\r
+Content-Length: 4\r
 \r
+v=0
 ";
-        let mut sip_message = SipMessage::new_from_str(message);
-        assert!(sip_message.parse_headers().is_ok());
+        let parsed = {
+            let mut sip_message = SipMessage::new_from_str(message);
+            sip_message.to_parsed().unwrap()
+        };
 
-        let result = sip_message.request_uri();
-        assert!(result.is_err());
+        assert_eq!(
+            parsed.start_line,
+            ParsedStartLine::Request {
+                method: Method::INVITE,
+                uri: ParsedUri {
+                    scheme: Scheme::SIP,
+                    user: Some("bob".to_string()),
+                    host: Some("example.com".to_string()),
+                    params: vec![("transport".to_string(), Some("tcp".to_string()))],
+                    ..ParsedUri::default()
+                },
+            }
+        );
+        assert_eq!(parsed.via.len(), 2);
+        assert_eq!(parsed.via[0].transport, Transport::Tcp);
+        assert_eq!(parsed.via[0].port, Some(5070));
+        assert_eq!(parsed.via[1].host, "2001:db8::1");
+        assert_eq!(parsed.via[1].params[1], ("rport".to_string(), None));
+        assert_eq!(parsed.from.as_ref().unwrap().display_name.as_deref(), Some("Alice"));
+        assert_eq!(parsed.to.as_ref().unwrap().params, []);
+        assert_eq!(parsed.call_id.as_deref(), Some("a84b4c76e66710"));
+        assert_eq!(parsed.cseq, Some(ParsedCSeq { seq: 314159, method: Method::INVITE }));
+        assert_eq!(parsed.max_forwards, Some(69));
+        assert_eq!(parsed.contacts[0].params, [("expires".to_string(), Some("60".to_string()))]);
+        assert_eq!(parsed.routes[0].uri.host.as_deref(), Some("edge.example.com"));
+        assert_eq!(parsed.record_routes[0].uri.params, [("lr".to_string(), None)]);
+        assert_eq!(parsed.headers.len(), 11);
+        assert_eq!(parsed.headers[9], ("Subject".to_string(), "lunch".to_string()));
+        assert_eq!(parsed.body.as_deref(), Some("v=0\n"));
+
+        let response = "SIP/2.0 200 OK\r\nVia: SIP/2.0/UDP pc33.example.com;branch=z9hG4bK776asdhds\r\n\
+                        To: <sip:bob@example.com>;tag=a6c85cf\r\nFrom: <sip:alice@example.com>;tag=1\r\n\
+                        Call-ID: abc\r\nCSeq: 1 REGISTER\r\nContact: *\r\n\r\n";
+        let parsed = SipMessage::new_from_str(response).to_parsed().unwrap();
+        assert_eq!(parsed.start_line, ParsedStartLine::Response { code: 200, reason: "OK".to_string() });
+        assert!(parsed.contacts.is_empty());
     }
 
     #[test]
-    fn test_uri_without_angle_brackets() {
-        let message = "\
-REGISTER sip:registrar.atlanta.com SIP/2.0\r
-Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r
-To: sip:alice@atlanta.com\r
-From: sip:alice@atlanta.com;tag=1928301774\r
-Call-ID: a84b4c76e66710@pc33.atlanta.com\r
-CSeq: 1 REGISTER\r
-Max-Forwards: 70\r
-\r
-";
-        let mut sip_message = SipMessage::new_from_str(message);
-        assert!(sip_message.parse_headers().is_ok());
+    fn test_is_forward_safe() {
+        let message = |extra: &str, content_length: &str, body: &str| {
+            format!(
+                "INVITE sip:bob@example.com SIP/2.0\r\n\
+                 Via: SIP/2.0/UDP pc33.example.com;branch=z9hG4bK776asdhds\r\n\
+                 Max-Forwards: 70\r\n\
+                 To: <sip:bob@example.com>\r\n\
+                 From: <sip:alice@example.com>;tag=1928301774\r\n\
+                 Call-ID: a84b4c76e66710\r\n\
+                 CSeq: 314159 INVITE\r\n\
+                 {}Content-Length: {}\r\n\r\n{}",
+                extra, content_length, body
+            )
+        };
+        let safe = |text: String| SipMessage::new(text).is_forward_safe();
+
+        assert!(safe(message("", "0", "")));
+        assert!(safe(message("Content-Type: application/sdp\r\n", "4", "v=0\n")));
+        assert!(!safe(message("", "3", "v=0\n")));
+        assert!(!safe(message("Content-Length: 4\r\n", "4", "v=0\n")));
+        assert!(!safe(message("Subject: lunch\r\n at noon\r\n", "0", "")));
+        assert!(!safe(message("Subject: lunch\n", "0", "")));
+        assert!(!safe(message("", "0", "").replacen("Via:", "X-Via:", 1)));
+        assert!(!safe(message("", "0", "").replacen("Max-Forwards: 70", "Max-Forwards: 0", 1)));
+        assert!(!safe(format!("\r\n{}", message("", "0", ""))));
+    }
 
-        // Test From URI without angle brackets
-        let from_uri = sip_message.from_uri().unwrap();
-        assert_eq!(from_uri.scheme, Scheme::SIP);
-        assert_eq!(sip_message.get_opt_str(from_uri.user_info), Some("alice"));
-        assert_eq!(sip_message.get_opt_str(from_uri.host), Some("atlanta.com"));
+    #[test]
+    fn test_supports_path() {
+        let message = |supported: &str| {
+            format!(
+                "REGISTER sip:registrar.example.com SIP/2.0\r\nVia: SIP/2.0/UDP pc33.example.com;branch=z9hG4bK1\r\n\
+                 From: <sip:bob@example.com>;tag=1\r\nTo: <sip:bob@example.com>\r\nCall-ID: reg1\r\n\
+                 CSeq: 1 REGISTER\r\n{}Content-Length: 0\r\n\r\n",
+                supported
+            )
+        };
 
-        // Test To URI without angle brackets
-        let to_uri = sip_message.to_uri().unwrap();
-        assert_eq!(to_uri.scheme, Scheme::SIP);
-        assert_eq!(sip_message.get_opt_str(to_uri.user_info), Some("alice"));
-        assert_eq!(sip_message.get_opt_str(to_uri.host), Some("atlanta.com"));
+        assert!(SipMessage::new(message("Supported: outbound, Path\r\n")).supports_path());
+        assert!(SipMessage::new(message("k: path\r\n")).supports_path());
+        assert!(!SipMessage::new(message("Supported: 100rel\r\n")).supports_path());
+        assert!(!SipMessage::new(message("")).supports_path());
     }
 
     #[test]
-    fn test_tag_extraction_with_multiple_params() {
-        let message = "\
-INVITE sip:bob@biloxi.com SIP/2.0\r
-Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r
-To: Bob <sip:bob@biloxi.com>;tag=a6c85cf;foo=bar\r
-From: Alice <sip:alice@atlanta.com>;foo=baz;tag=1928301774;bar=qux\r
-Call-ID: a84b4c76e66710@pc33.atlanta.com\r
-CSeq: 314159 INVITE\r
-Max-Forwards: 70\r
-\r
-";
-        let mut sip_message = SipMessage::new_from_str(message);
-        assert!(sip_message.parse_headers().is_ok());
+    fn test_remote_target() {
+        let message = |start_line: &str, contact: &str| {
+            format!(
+                "{}\r\nVia: SIP/2.0/UDP pc33.example.com;branch=z9hG4bK1\r\n\
+                 From: <sip:alice@example.com>;tag=1\r\nTo: <sip:bob@example.com>\r\nCall-ID: c1\r\n\
+                 CSeq: 1 INVITE\r\n{}Content-Length: 0\r\n\r\n",
+                start_line, contact
+            )
+        };
 
-        // Tags should be extracted correctly even with other parameters
-        let from_tag = sip_message.from_tag();
-        assert_eq!(from_tag, Some("1928301774"));
+        let mut ok = SipMessage::new(message(
+            "SIP/2.0 200 OK",
+            "Contact: \"Bob\" <sip:bob@192.0.2.4:5080;transport=tcp>;expires=60, <sip:bob@198.51.100.7>\r\n",
+        ));
+        let target = ok.remote_target().unwrap().unwrap();
+        assert_eq!(target.host.map(|host| host.as_str(ok.raw_message())), Some("192.0.2.4"));
+        assert_eq!(target.port, Some(5080));
+
+        let mut invite = SipMessage::new(message("INVITE sip:bob@example.com SIP/2.0", "m: sip:alice@pc33.example.com\r\n"));
+        let target = invite.remote_target().unwrap().unwrap();
+        assert_eq!(target.host.map(|host| host.as_str(invite.raw_message())), Some("pc33.example.com"));
+
+        let mut redirect = SipMessage::new(message("SIP/2.0 302 Moved", "Contact: <sip:bob@elsewhere.example.com>\r\n"));
+        assert_eq!(redirect.remote_target().unwrap(), None);
+        assert_eq!(SipMessage::new(message("SIP/2.0 200 OK", "")).remote_target().unwrap(), None);
+        assert!(SipMessage::new(message("SIP/2.0 200 OK", "Contact: *\r\n")).remote_target().is_err());
+    }
 
-        let to_tag = sip_message.to_tag();
-        assert_eq!(to_tag, Some("a6c85cf"));
+    #[test]
+    fn test_requires_ack() {
+        let response = |status: &str, cseq: &str| {
+            SipMessage::new(format!(
+                "SIP/2.0 {}\r\nVia: SIP/2.0/UDP pc33.example.com;branch=z9hG4bK1\r\n\
+                 From: <sip:alice@example.com>;tag=1\r\nTo: <sip:bob@example.com>;tag=2\r\nCall-ID: c1\r\n\
+                 CSeq: {}\r\nContent-Length: 0\r\n\r\n",
+                status, cseq
+            ))
+            .requires_ack()
+            .unwrap()
+        };
+
+        assert!(response("200 OK", "1 INVITE"));
+        assert!(response("486 Busy Here", "1 INVITE"));
+        assert!(response("302 Moved Temporarily", "1 INVITE"));
+        assert!(response("603 Decline", "1 INVITE"));
+        assert!(!response("180 Ringing", "1 INVITE"));
+        assert!(!response("200 OK", "2 BYE"));
+        assert!(!response("404 Not Found", "1 REGISTER"));
+        // An empty Reason-Phrase is valid
+        assert!(response("200 ", "1 INVITE"));
+
+        let mut request = SipMessage::new_from_str("OPTIONS sip:bob@example.com SIP/2.0\r\nCSeq: 1 INVITE\r\n\r\n");
+        assert!(!request.requires_ack().unwrap());
+
+        // Other helpers that only need the status code accept it too
+        let sdp = "v=0\r\no=- 1 1 IN IP4 192.0.2.4\r\ns=-\r\n";
+        let with_sdp = |code: &str| {
+            SipMessage::new(format!(
+                "SIP/2.0 {} \r\nVia: SIP/2.0/UDP pc33.example.com;branch=z9hG4bK1\r\n\
+                 From: <sip:alice@example.com>;tag=1\r\nTo: <sip:bob@example.com>;tag=2\r\nCall-ID: c1\r\n\
+                 CSeq: 1 INVITE\r\nContact: <sip:bob@192.0.2.4>\r\nContent-Type: application/sdp\r\n\
+                 Content-Length: {}\r\n\r\n{}",
+                code,
+                sdp.len(),
+                sdp
+            ))
+        };
+        assert!(with_sdp("183").is_early_media().unwrap());
+        assert_eq!(with_sdp("200").sdp_role().unwrap(), Some(SdpRole::Answer));
+        assert!(with_sdp("200").remote_target().unwrap().is_some());
+    }
+
+    #[test]
+    fn test_build_ack_for_2xx() {
+        use crate::b2bua::{CounterRandom, OsRandom};
+
+        let mut invite = SipMessage::new_from_str(
+            "INVITE sip:bob@biloxi.example.com SIP/2.0\r\n\
+             Via: SIP/2.0/UDP pc33.atlanta.example.com;branch=z9hG4bKnashds8\r\n\
+             Max-Forwards: 70\r\n\
+             To: Bob <sip:bob@biloxi.example.com>\r\n\
+             From: Alice <sip:alice@atlanta.example.com>;tag=1928301774\r\n\
+             Call-ID: a84b4c76e66710\r\n\
+             CSeq: 314159 INVITE\r\n\
+             Proxy-Authorization: Digest username=\"alice\", realm=\"atlanta.example.com\"\r\n\
+             Contact: <sip:alice@pc33.atlanta.example.com>\r\n\
+             Content-Length: 0\r\n\r\n",
+        );
+        let response = |record_route: &str| {
+            SipMessage::new(format!(
+                "SIP/2.0 200 OK\r\n\
+                 Via: SIP/2.0/UDP pc33.atlanta.example.com;branch=z9hG4bKnashds8\r\n\
+                 {}To: Bob <sip:bob@biloxi.example.com>;tag=a6c85cf\r\n\
+                 From: Alice <sip:alice@atlanta.example.com>;tag=1928301774\r\n\
+                 Call-ID: a84b4c76e66710\r\n\
+                 CSeq: 314159 INVITE\r\n\
+                 Contact: <sip:bob@192.0.2.4>\r\n\
+                 Content-Length: 0\r\n\r\n",
+                record_route
+            ))
+        };
+
+        let random = CounterRandom::new(7);
+        let ack = invite
+            .build_ack_for_2xx(
+                &mut response("Record-Route: <sip:p2.biloxi.example.com;lr>, <sip:p1.atlanta.example.com;lr>\r\n"),
+                &random,
+            )
+            .unwrap();
+        let mut ack = SipMessage::new(String::from_utf8(ack).unwrap());
+        ack.parse_headers().unwrap();
+        assert_eq!(ack.start_line(), "ACK sip:bob@192.0.2.4 SIP/2.0");
+        assert_eq!(ack.cseq_parts().unwrap(), (314159, "ACK"));
+        assert_eq!(ack.to_tag(), Some("a6c85cf"));
+        assert_eq!(ack.from_tag(), Some("1928301774"));
+        assert!(ack.has_header("Proxy-Authorization"));
+        let routes: Vec<_> = ack.header_value_ranges("route").iter().map(|r| ack.get_str(*r)).collect();
+        assert_eq!(routes, ["<sip:p1.atlanta.example.com;lr>", "<sip:p2.biloxi.example.com;lr>"]);
+        assert_eq!(ack.transaction_branch().unwrap(), Some("z9hG4bK0000000700000008"));
+
+        let ack = invite
+            .build_ack_for_2xx(&mut response("Record-Route: <sip:strict.example.com>\r\n"), &OsRandom)
+            .unwrap();
+        let ack = String::from_utf8(ack).unwrap();
+        assert!(ack.starts_with("ACK sip:strict.example.com SIP/2.0\r\n"));
+        assert!(ack.contains("Route: <sip:bob@192.0.2.4>\r\n"));
+
+        let mut ringing = SipMessage::new(response("").raw_message().replacen("200 OK", "180 Ringing", 1));
+        assert!(invite.build_ack_for_2xx(&mut ringing, &random).is_err());
+    }
+
+    #[test]
+    fn test_ack_type() {
+        let request = |method: &str, branch: &str| {
+            SipMessage::new(format!(
+                "{} sip:bob@biloxi.example.com SIP/2.0\r\n\
+                 Via: SIP/2.0/UDP pc33.atlanta.example.com{}\r\n\
+                 To: Bob <sip:bob@biloxi.example.com>;tag=a6c85cf\r\n\
+                 From: Alice <sip:alice@atlanta.example.com>;tag=1928301774\r\n\
+                 Call-ID: a84b4c76e66710\r\nCSeq: 314159 {}\r\nContent-Length: 0\r\n\r\n",
+                method, branch, method
+            ))
+        };
+
+        let mut invite = request("INVITE", ";branch=z9hG4bKnashds8");
+        invite.parse_headers().unwrap();
+        let invite_id = invite.server_transaction_id().unwrap();
+        let known = |id: &TransactionId| *id == invite_id;
+        assert_eq!(request("ACK", ";branch=z9hG4bKnashds8").ack_type(known).unwrap(), AckType::NonTwoXxAck);
+        assert_eq!(request("ACK", ";branch=z9hG4bK74b21").ack_type(known).unwrap(), AckType::TwoXxAck);
+        assert!(request("ACK", "").ack_type(known).is_err());
+        assert!(request("ACK", ";branch=1234").ack_type(known).is_err());
+        assert!(request("BYE", ";branch=z9hG4bKnashds8").ack_type(known).is_err());
+    }
+
+    #[test]
+    fn test_registration_gruus() {
+        let response = |contact: &str| {
+            SipMessage::new(format!(
+                "SIP/2.0 200 OK\r\nVia: SIP/2.0/UDP 192.0.2.1;branch=z9hG4bKnashds7\r\n\
+                 From: <sip:alice@example.com>;tag=a73kszlfl\r\nTo: <sip:alice@example.com>;tag=b88sn\r\n\
+                 Call-ID: 1j9FpLxk3uxtm8tn\r\nCSeq: 1 REGISTER\r\n{}Content-Length: 0\r\n\r\n",
+                contact
+            ))
+        };
+
+        let mut ok = response(
+            "Contact: <sip:alice@192.0.2.1>;expires=3600\r\n\
+             Contact: <sip:alice@pc33.example.com>;pub-gruu=\"sip:alice@example.com;gr=urn:uuid:f81d4fae-7dec-11d0-a765-00a0c91e6bf6\";\
+             temp-gruu=\"sip:tgruu.7hs==jd7vnzga5w7fajsc7-ajd6fabz0f8g5@example.com;gr\";\
+             +sip.instance=\"<urn:uuid:f81d4fae-7dec-11d0-a765-00a0c91e6bf6>\";expires=3600\r\n",
+        );
+        assert_eq!(
+            ok.public_gruu().unwrap().as_deref(),
+            Some("sip:alice@example.com;gr=urn:uuid:f81d4fae-7dec-11d0-a765-00a0c91e6bf6")
+        );
+        assert_eq!(
+            ok.temp_gruu().unwrap().as_deref(),
+            Some("sip:tgruu.7hs==jd7vnzga5w7fajsc7-ajd6fabz0f8g5@example.com;gr")
+        );
+
+        assert_eq!(response("Contact: <sip:alice@192.0.2.1>\r\n").public_gruu().unwrap(), None);
+        let mut not_gruu = response("Contact: <sip:alice@192.0.2.1>;pub-gruu=\"sip:alice@example.com\"\r\n");
+        assert!(not_gruu.public_gruu().is_err());
+    }
+
+    #[test]
+    fn test_binary_body() {
+        let head = "INVITE sip:+15551234567@gw.example.com SIP/2.0\r\n\
+                    Via: SIP/2.0/UDP mgc.example.com;branch=z9hG4bK9a1\r\n\
+                    Max-Forwards: 70\r\n\
+                    To: <sip:+15551234567@gw.example.com>\r\n\
+                    From: <sip:+15557654321@mgc.example.com>;tag=77\r\n\
+                    Call-ID: isup-1\r\n\
+                    CSeq: 1 INVITE\r\n\
+                    Content-Type: application/isup;version=itu-t92+\r\n\
+                    Content-Length: 6\r\n\r\n";
+        let isup = [0x01u8, 0x00, 0x49, 0xff, 0xfe, 0x80];
+        let mut data = head.as_bytes().to_vec();
+        data.extend_from_slice(&isup);
+        data.extend_from_slice(b"\r\n");
+
+        assert!(SipMessage::parse(&data).is_err());
+        let message = SipMessage::parse_bytes(&data).unwrap();
+        assert_eq!(message.body_bytes(), Some(&isup[..]));
+        assert_eq!(message.body(), None);
+        assert_eq!(message.raw_message(), head);
+        assert_eq!(message.call_id_str(), Some("isup-1"));
+
+        let text = format!("{}abc", head.replace("Content-Length: 6", "Content-Length: 3"));
+        let text = SipMessage::parse_bytes(text.as_bytes()).unwrap();
+        assert_eq!(text.body_bytes(), Some(&b"abc"[..]));
+    }
+
+    #[test]
+    fn test_multipart_depth_limit() {
+        let sdp = "v=0\r\no=- 1 1 IN IP4 192.0.2.1\r\n";
+        let nested = |levels: usize| {
+            let mut body = sdp.to_string();
+            let mut content_type = "application/sdp".to_string();
+            for level in 0..levels {
+                body = format!(
+                    "--b{level}\r\nContent-Type: {content_type}\r\n\r\n{body}\r\n--b{level}--\r\n"
+                );
+                content_type = format!("multipart/mixed;boundary=b{level}");
+            }
+            let mut message = SipMessage::new(format!(
+                "INVITE sip:bob@example.com SIP/2.0\r\nVia: SIP/2.0/UDP pc33.example.com;branch=z9hG4bK1\r\n\
+                 Content-Type: {}\r\nContent-Length: {}\r\n\r\n{}",
+                content_type,
+                body.len(),
+                body
+            ));
+            message.parse_without_validation().unwrap();
+            message
+        };
+
+        let message = nested(2);
+        let parts = message.body_parts().unwrap();
+        assert_eq!(parts.len(), 1);
+        assert_eq!(parts[0].content_type(), Some("multipart/mixed;boundary=b0"));
+        assert_eq!(parts[0].parts[0].content_type(), Some("application/sdp"));
+        assert_eq!(parts[0].parts[0].body, sdp);
+
+        assert!(nested(MAX_MULTIPART_DEPTH).body_parts().is_ok());
+        let error = nested(MAX_MULTIPART_DEPTH + 1).body_parts().unwrap_err();
+        assert!(error.to_string().contains("Multipart nesting exceeds maximum"));
+        assert!(nested(0).body_parts().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_clone_shares_message_text() {
+        let mut message = SipMessage::new_from_str(
+            "OPTIONS sip:bob@example.com SIP/2.0\r\nVia: SIP/2.0/UDP pc33.example.com;branch=z9hG4bK1\r\n\
+             From: <sip:alice@example.com>;tag=1\r\nTo: <sip:bob@example.com>\r\nCall-ID: c1\r\n\
+             CSeq: 1 OPTIONS\r\nContent-Length: 0\r\n\r\n",
+        );
+        message.parse_headers().unwrap();
+
+        let mut copy = message.clone();
+        assert_eq!(copy.raw_message().as_ptr(), message.raw_message().as_ptr());
+        assert_eq!(copy.call_id_str(), Some("c1"));
+
+        copy.set_body("hello");
+        assert_ne!(copy.raw_message().as_ptr(), message.raw_message().as_ptr());
+        assert_eq!(message.body(), None);
+    }
+
+    #[test]
+    fn test_conflicting_content_length() {
+        let message = |lengths: &str| {
+            SipMessage::new(format!(
+                "INVITE sip:bob@example.com SIP/2.0\r\nVia: SIP/2.0/UDP pc33.example.com;branch=z9hG4bK1\r\n\
+                 From: <sip:alice@example.com>;tag=1\r\nTo: <sip:bob@example.com>\r\nCall-ID: c1\r\n\
+                 CSeq: 1 INVITE\r\nMax-Forwards: 70\r\n{}\r\nhello",
+                lengths
+            ))
+            .parse_headers()
+        };
+
+        match message("l: 5\r\nContent-Length: 0\r\n") {
+            Err(SsbcError::ParseError { message, context, .. }) => {
+                assert_eq!(message, "Conflicting Content-Length values 5 and 0");
+                assert_eq!(context.as_deref(), Some("Request smuggling"));
+            }
+            other => panic!("Expected a smuggling error, got {:?}", other),
+        }
+        assert!(message("Content-Length: 5\r\nContent-Length: 50\r\n").is_err());
+        assert!(message("Content-Length: 5\r\ncontent-length: x\r\n").is_err());
+        assert!(message("Content-Length: 5\r\nl: 5\r\n").is_ok());
+        assert!(message("Content-Length: 5\r\n").is_ok());
+    }
+
+    #[test]
+    fn test_max_folds_per_header() {
+        let message = |folds: usize| {
+            format!(
+                "OPTIONS sip:bob@example.com SIP/2.0\r\nVia: SIP/2.0/UDP pc33.example.com;branch=z9hG4bK1\r\n\
+                 Subject: lunch{}\r\nContent-Length: 0\r\n\r\n",
+                "\r\n and".repeat(folds)
+            )
+        };
+
+        let mut at_limit = SipMessage::new(message(MAX_FOLDS_PER_HEADER));
+        assert!(at_limit.parse_without_validation().is_ok());
+        assert_eq!(at_limit.coalesced_header("Subject").unwrap().matches("and").count(), MAX_FOLDS_PER_HEADER);
+
+        match SipMessage::new(message(MAX_FOLDS_PER_HEADER + 1)).parse_without_validation() {
+            Err(SsbcError::ParseError { message, context, .. }) => {
+                assert!(message.contains("continuation lines exceeds maximum"));
+                assert_eq!(context.as_deref(), Some("DoS protection"));
+            }
+            other => panic!("Expected a fold limit error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_all_uris() {
+        let mut message = SipMessage::new_from_str(
+            "INVITE sip:bob@biloxi.example.com SIP/2.0\r\n\
+             Via: SIP/2.0/UDP pc33.atlanta.example.com;branch=z9hG4bK1\r\n\
+             Route: <sip:p1.example.com;lr>, <sip:p2.example.com;lr>\r\n\
+             To: Bob <sip:bob@biloxi.example.com>\r\n\
+             f: Alice <sip:alice@atlanta.example.com>;tag=1\r\n\
+             Call-ID: c1\r\nCSeq: 1 INVITE\r\n\
+             Contact: <sip:alice@192.0.2.1:5070>\r\n\
+             P-Asserted-Identity: <tel:+15551234567>\r\n\
+             Content-Length: 0\r\n\r\n",
+        );
+        let uris = message.all_uris().unwrap();
+        let raw = message.raw_message();
+        let hosts: Vec<_> = uris.iter().map(|uri| uri.host.map(|host| host.as_str(raw))).collect();
+        assert_eq!(
+            hosts[..6],
+            [
+                Some("biloxi.example.com"),
+                Some("biloxi.example.com"),
+                Some("atlanta.example.com"),
+                Some("192.0.2.1"),
+                Some("p1.example.com"),
+                Some("p2.example.com"),
+            ]
+        );
+        assert_eq!(uris.len(), 7);
+        assert_eq!(uris[6].scheme, Scheme::TEL);
+
+        let mut register = SipMessage::new_from_str(
+            "SIP/2.0 200 OK\r\nTo: <sip:bob@example.com>;tag=2\r\nContact: *\r\n\r\n",
+        );
+        assert_eq!(register.all_uris().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_reply_to_and_in_reply_to() {
+        let mut message = SipMessage::new_from_str(
+            "INVITE sip:bob@biloxi.example.com SIP/2.0\r\n\
+             Via: SIP/2.0/UDP pc33.atlanta.example.com;branch=z9hG4bK1\r\n\
+             To: <sip:bob@biloxi.example.com>\r\nFrom: <sip:alice@atlanta.example.com>;tag=1\r\n\
+             Call-ID: c1\r\nCSeq: 1 INVITE\r\n\
+             Reply-To: Bob <sip:bob@biloxi.example.com>;x=1\r\n\
+             In-Reply-To: 70710@saturn.example.com, 17320@saturn.example.com\r\n\
+             Content-Length: 0\r\n\r\n",
+        );
+        let reply_to = message.reply_to().unwrap().unwrap();
+        assert_eq!(reply_to.display_name.map(|name| name.as_str(message.raw_message())), Some("Bob"));
+        assert_eq!(reply_to.uri.host.map(|host| host.as_str(message.raw_message())), Some("biloxi.example.com"));
+        assert_eq!(message.in_reply_to(), ["70710@saturn.example.com", "17320@saturn.example.com"]);
+
+        let mut plain = SipMessage::new_from_str("OPTIONS sip:bob@example.com SIP/2.0\r\nCall-ID: c2\r\n\r\n");
+        assert!(plain.reply_to().unwrap().is_none());
+        assert!(plain.in_reply_to().is_empty());
+    }
+
+    #[test]
+    fn test_response_matches_request() {
+        let message = |start_line: &str, branch: &str, cseq: &str| {
+            SipMessage::new(format!(
+                "{}\r\nVia: SIP/2.0/UDP pc33.atlanta.example.com;branch={}\r\n\
+                 To: <sip:bob@biloxi.example.com>\r\nFrom: <sip:alice@atlanta.example.com>;tag=1\r\n\
+                 Call-ID: a84b4c76e66710\r\nCSeq: {}\r\nContent-Length: 0\r\n\r\n",
+                start_line, branch, cseq
+            ))
+        };
+        let mut invite = message("INVITE sip:bob@biloxi.example.com SIP/2.0", "z9hG4bKnashds8", "1 INVITE");
+        let mut response = |branch: &str, cseq: &str| {
+            message("SIP/2.0 180 Ringing", branch, cseq).response_matches_request(&mut invite).unwrap()
+        };
+
+        assert!(response("z9hG4bKnashds8", "1 INVITE"));
+        assert!(!response("z9hG4bKother", "1 INVITE"));
+        assert!(!response("z9hG4bKnashds8", "2 INVITE"));
+        assert!(!response("z9hG4bKnashds8", "1 CANCEL"));
+
+        let other_call = message("SIP/2.0 200 OK", "z9hG4bKnashds8", "1 INVITE");
+        let mut other_call = SipMessage::new(other_call.raw_message().replace("a84b4c76e66710", "other"));
+        assert!(!other_call.response_matches_request(&mut invite).unwrap());
+        let mut request = message("BYE sip:bob@biloxi.example.com SIP/2.0", "z9hG4bKnashds8", "1 INVITE");
+        assert!(request.response_matches_request(&mut invite).is_err());
     }
 }
@@ -187,8 +187,12 @@ pub mod message_modifier {
 
 /// SIP message building utilities
 pub mod message_builder {
-    use crate::{Method, SipUri, Scheme, error::SsbcError};
+    use crate::{ContentType, Method, SipMessage, Scheme, error::SsbcError};
+    use crate::b2bua::{generate_tag, OsRandom, RandomSource};
+    use crate::headers::expand_compact_header_name;
+    use crate::main_impl::header_utils;
     use std::collections::HashMap;
+    use std::sync::Arc;
     
     /// SIP message builder for constructing SIP requests and responses
     /// 
@@ -230,11 +234,14 @@ pub mod message_builder {
         message_type: MessageType,
         headers: Vec<(String, String)>,
         body: Option<String>,
+        /// Add a generated tag to To on build
+        tag_to: bool,
+        random: Arc<dyn RandomSource>,
     }
     
     #[derive(Debug, Clone)]
     enum MessageType {
-        Request { method: Method, uri: SipUri },
+        Request { method: Method, uri: String },
         Response { code: u16, reason: String },
         None,
     }
@@ -246,6 +253,8 @@ pub mod message_builder {
                 message_type: MessageType::None,
                 headers: Vec::new(),
                 body: None,
+                tag_to: false,
+                random: Arc::new(OsRandom),
             }
         }
         
@@ -256,8 +265,15 @@ pub mod message_builder {
                 uri: None,
                 headers: self.headers,
                 body: self.body,
+                random: self.random,
             }
         }
+
+        /// Draw generated tags and boundaries from `random` instead of [`OsRandom`]
+        pub fn random_source(mut self, random: Arc<dyn RandomSource>) -> Self {
+            self.random = random;
+            self
+        }
         
         /// Set this as a SIP response with status code and reason phrase
         pub fn response(mut self, code: u16, reason: &str) -> Self {
@@ -273,6 +289,96 @@ pub mod message_builder {
             self.headers.push((name.to_string(), value.to_string()));
             self
         }
+
+        /// 420 Bad Extension listing the option tags we don't support
+        ///
+        /// Copies Via, From, To, Call-ID and CSeq from `request`, adds a To tag if
+        /// it has none and an `Unsupported` header (RFC 3261 Section 8.2.2.3).
+        pub fn bad_extension_response(request: &SipMessage, unsupported: &[&str]) -> Self {
+            Self::rejection(request, 420, "Bad Extension").header("Unsupported", &unsupported.join(", "))
+        }
+
+        /// 405 Method Not Allowed with an Allow header listing `allowed`
+        pub fn method_not_allowed(request: &SipMessage, allowed: &[Method]) -> Self {
+            let allow: Vec<String> = allowed.iter().map(|method| method.to_string()).collect();
+            Self::rejection(request, 405, "Method Not Allowed").header("Allow", &allow.join(", "))
+        }
+
+        /// 483 Too Many Hops, for a request whose Max-Forwards reached zero
+        pub fn too_many_hops(request: &SipMessage) -> Self {
+            Self::rejection(request, 483, "Too Many Hops")
+        }
+
+        /// 423 Interval Too Brief with the shortest acceptable registration interval
+        pub fn interval_too_brief(request: &SipMessage, min_expires: u32) -> Self {
+            Self::rejection(request, 423, "Interval Too Brief").header("Min-Expires", &min_expires.to_string())
+        }
+
+        /// 422 Session Interval Too Small with the shortest acceptable session interval
+        pub fn session_interval_too_small(request: &SipMessage, min_se: u32) -> Self {
+            Self::rejection(request, 422, "Session Interval Too Small").header("Min-SE", &min_se.to_string())
+        }
+
+        /// 400 Bad Request carrying `reason` as the reason phrase
+        ///
+        /// Line breaks in `reason` are replaced by spaces so it can't inject headers.
+        pub fn bad_request(request: &SipMessage, reason: &str) -> Self {
+            let reason = reason.replace(['\r', '\n'], " ");
+            Self::rejection(request, 400, &reason)
+        }
+
+        /// 500 Server Internal Error
+        pub fn server_internal_error(request: &SipMessage) -> Self {
+            Self::rejection(request, 500, "Server Internal Error")
+        }
+
+        /// Response skeleton with the transaction headers copied from `request`
+        ///
+        /// Via (every line, in order, so the response retraces the request path),
+        /// From, To, Call-ID and CSeq are copied verbatim apart from unfolding;
+        /// compact names are written in full. No To tag is added. `request` must
+        /// already be parsed.
+        ///
+        /// # Examples
+        /// ```
+        /// use ssbc::{SipMessage, SipMessageBuilder};
+        ///
+        /// let request = SipMessage::parse(b"OPTIONS sip:bob@example.com SIP/2.0\r\nVia: SIP/2.0/UDP pc33.example.com;branch=z9hG4bK776asdhds\r\nFrom: <sip:alice@example.com>;tag=1\r\nTo: <sip:bob@example.com>\r\nCall-ID: abc\r\nCSeq: 7 OPTIONS\r\n\r\n").unwrap();
+        /// let response = SipMessageBuilder::from_request(&request, 200, "OK")
+        ///     .header("Allow", "INVITE, ACK, BYE")
+        ///     .build()
+        ///     .unwrap();
+        /// assert!(response.contains("CSeq: 7 OPTIONS\r\n"));
+        /// ```
+        pub fn from_request(request: &SipMessage, code: u16, reason: &str) -> Self {
+            let mut builder = Self::new().response(code, reason);
+            for (name_range, value_range) in request.header_ranges() {
+                let name = expand_compact_header_name(request.get_str(name_range));
+                let canonical = match name.to_ascii_lowercase().as_str() {
+                    "via" => "Via",
+                    "from" => "From",
+                    "to" => "To",
+                    "call-id" => "Call-ID",
+                    "cseq" => "CSeq",
+                    _ => continue,
+                };
+                let value = header_utils::unfold(request.get_str(value_range));
+                builder = builder.header(canonical, &value);
+            }
+            builder
+        }
+
+        /// Final response to `request` that also tags To when needed (RFC 3261 Section 8.2.6.2)
+        ///
+        /// The tag itself is generated on build, from the configured random source.
+        fn rejection(request: &SipMessage, code: u16, reason: &str) -> Self {
+            let mut builder = Self::from_request(request, code, reason);
+            if let Some((_, to)) = builder.headers.iter().find(|(name, _)| name == "To") {
+                let params = to.rsplit_once('>').map_or(to.as_str(), |(_, params)| params);
+                builder.tag_to = header_utils::extract_header_parameter(params, "tag").is_none();
+            }
+            builder
+        }
         
         /// Add multiple headers from a map
         pub fn headers(mut self, headers: HashMap<String, String>) -> Self {
@@ -287,9 +393,26 @@ pub mod message_builder {
             self.body = Some(body.to_string());
             self
         }
+
+        /// Set a `multipart/mixed` body, one part per `(type, content)` pair
+        ///
+        /// Picks a boundary that occurs in none of the parts and replaces any
+        /// Content-Type header with the multipart one (RFC 5621).
+        pub fn multipart_body(mut self, parts: &[(ContentType, &str)]) -> Self {
+            set_multipart_body(&mut self.headers, &mut self.body, parts, self.random.as_ref());
+            self
+        }
         
         /// Build the final SIP message
-        pub fn build(self) -> Result<String, SsbcError> {
+        pub fn build(mut self) -> Result<String, SsbcError> {
+            if self.tag_to {
+                let tag = generate_tag(self.random.as_ref());
+                if let Some((_, to)) = self.headers.iter_mut().find(|(name, _)| name == "To") {
+                    to.push_str(";tag=");
+                    to.push_str(&tag);
+                }
+            }
+
             let mut lines = Vec::new();
             
             // Add start line
@@ -340,10 +463,8 @@ pub mod message_builder {
             // Add empty line to separate headers from body
             lines.push(String::new());
             
-            // Add body if present
-            if let Some(body) = self.body {
-                lines.push(body);
-            }
+            // Add body if present; an empty one still terminates the header block
+            lines.push(self.body.unwrap_or_default());
             
             Ok(lines.join("\r\n"))
         }
@@ -352,24 +473,22 @@ pub mod message_builder {
     /// Specialized builder for SIP requests
     pub struct SipRequestBuilder {
         method: Method,
-        uri: Option<SipUri>,
+        uri: Option<String>,
         headers: Vec<(String, String)>,
         body: Option<String>,
+        random: Arc<dyn RandomSource>,
     }
     
     impl SipRequestBuilder {
-        /// Set the request URI
-        pub fn uri(mut self, uri: SipUri) -> Self {
-            self.uri = Some(uri);
+        /// Set the request URI from a string, used as is
+        pub fn uri_str(mut self, uri_str: &str) -> Self {
+            self.uri = Some(uri_str.to_string());
             self
         }
-        
-        /// Set the request URI from a string (for examples)
-        pub fn uri_str(mut self, uri_str: &str) -> Self {
-            // For the doctest, we'll create a basic SipUri
-            let mut uri = SipUri::default();
-            uri.scheme = if uri_str.starts_with("sips:") { Scheme::SIPS } else { Scheme::SIP };
-            self.uri = Some(uri);
+
+        /// Set the request URI from its parts, percent-encoding the user part
+        pub fn uri_parts(mut self, scheme: Scheme, user: Option<&str>, host: &str) -> Self {
+            self.uri = Some(format_uri(&scheme, user, host));
             self
         }
         
@@ -385,31 +504,15 @@ pub mod message_builder {
             self.header("Via", &via_value)
         }
         
-        /// Add From header (convenience method)
-        pub fn from(self, display_name: Option<&str>, uri: &SipUri, tag: &str) -> Self {
-            let from_value = if let Some(name) = display_name {
-                format!("{} <{}>;tag={}", name, uri, tag)
-            } else {
-                format!("<{}>;tag={}", uri, tag)
-            };
+        /// Add From header (convenience method), quoting the display name
+        pub fn from(self, display_name: Option<&str>, uri: &str, tag: &str) -> Self {
+            let from_value = format_address(display_name, uri, Some(tag));
             self.header("From", &from_value)
         }
         
-        /// Add To header (convenience method)
-        pub fn to(self, display_name: Option<&str>, uri: &SipUri, tag: Option<&str>) -> Self {
-            let to_value = if let Some(name) = display_name {
-                if let Some(tag) = tag {
-                    format!("{} <{}>;tag={}", name, uri, tag)
-                } else {
-                    format!("{} <{}>", name, uri)
-                }
-            } else {
-                if let Some(tag) = tag {
-                    format!("<{}>;tag={}", uri, tag)
-                } else {
-                    format!("<{}>", uri)
-                }
-            };
+        /// Add To header (convenience method), quoting the display name
+        pub fn to(self, display_name: Option<&str>, uri: &str, tag: Option<&str>) -> Self {
+            let to_value = format_address(display_name, uri, tag);
             self.header("To", &to_value)
         }
         
@@ -418,6 +521,12 @@ pub mod message_builder {
             self.body = Some(body.to_string());
             self
         }
+
+        /// Set a `multipart/mixed` body, see [`SipMessageBuilder::multipart_body`]
+        pub fn multipart_body(mut self, parts: &[(ContentType, &str)]) -> Self {
+            set_multipart_body(&mut self.headers, &mut self.body, parts, self.random.as_ref());
+            self
+        }
         
         /// Build the final SIP request
         pub fn build(self) -> Result<String, SsbcError> {
@@ -434,6 +543,8 @@ pub mod message_builder {
                 },
                 headers: self.headers,
                 body: self.body,
+                tag_to: false,
+                random: self.random,
             }.build()
         }
     }
@@ -443,21 +554,127 @@ pub mod message_builder {
             Self::new()
         }
     }
+
+    /// Format `scheme:user@host`, percent-encoding the user part
+    fn format_uri(scheme: &Scheme, user: Option<&str>, host: &str) -> String {
+        match user {
+            Some(user) => format!("{}:{}@{}", scheme, escape_user(user), host),
+            None => format!("{}:{}", scheme, host),
+        }
+    }
+
+    /// Format a name-addr with a quoted display name and optional tag
+    fn format_address(display_name: Option<&str>, uri: &str, tag: Option<&str>) -> String {
+        let mut address = match display_name {
+            Some(name) => format!("{} <{}>", quote_display_name(name), uri),
+            None => format!("<{}>", uri),
+        };
+        if let Some(tag) = tag {
+            address.push_str(";tag=");
+            address.push_str(tag);
+        }
+        address
+    }
+
+    /// Quote a display name, escaping `"` and `\` (RFC 3261 quoted-string)
+    ///
+    /// Always quoting keeps commas, semicolons and other separators from
+    /// splitting the header value.
+    fn quote_display_name(name: &str) -> String {
+        let mut quoted = String::with_capacity(name.len() + 2);
+        quoted.push('"');
+        for c in name.chars() {
+            if c == '"' || c == '\\' {
+                quoted.push('\\');
+            }
+            quoted.push(c);
+        }
+        quoted.push('"');
+        quoted
+    }
+
+    /// Percent-encode every character not allowed unescaped in a URI user part
+    fn escape_user(user: &str) -> String {
+        let mut escaped = String::with_capacity(user.len());
+        for byte in user.bytes() {
+            let allowed = byte.is_ascii_alphanumeric()
+                // unreserved marks and user-unreserved (RFC 3261 Section 25.1)
+                || b"-_.!~*'()&=+$,;?/".contains(&byte);
+            if allowed {
+                escaped.push(byte as char);
+            } else {
+                escaped.push_str(&format!("%{:02X}", byte));
+            }
+        }
+        escaped
+    }
+
+    /// Check for a Content-Type header name, long or compact form
+    fn is_content_type(name: &str) -> bool {
+        expand_compact_header_name(name).eq_ignore_ascii_case("content-type")
+    }
+
+    /// Install a `multipart/mixed` body in place of the body and any Content-Type header
+    fn set_multipart_body(
+        headers: &mut Vec<(String, String)>,
+        body: &mut Option<String>,
+        parts: &[(ContentType, &str)],
+        random: &dyn RandomSource,
+    ) {
+        let (content_type, multipart) = multipart_body(parts, random);
+        headers.retain(|(name, _)| !is_content_type(name));
+        headers.push(("Content-Type".to_string(), content_type.to_string()));
+        *body = Some(multipart);
+    }
+
+    /// Assemble a `multipart/mixed` body, returning its Content-Type and text
+    fn multipart_body(parts: &[(ContentType, &str)], random: &dyn RandomSource) -> (ContentType, String) {
+        let mut boundary = format!("boundary-{}", generate_tag(random));
+        while parts.iter().any(|(_, content)| content.contains(&boundary)) {
+            boundary = format!("boundary-{}", generate_tag(random));
+        }
+
+        let mut body = String::new();
+        for (content_type, content) in parts {
+            body.push_str(&format!("--{}\r\nContent-Type: {}\r\n\r\n", boundary, content_type));
+            body.push_str(content);
+            // The CRLF before the next delimiter belongs to the delimiter (RFC 2046)
+            body.push_str("\r\n");
+        }
+        body.push_str(&format!("--{}--\r\n", boundary));
+
+        (ContentType::Other(format!("multipart/mixed;boundary={}", boundary)), body)
+    }
 }
 
 // Re-export main types for convenience
 pub use message_modifier::SipMessageModifier;
 pub use message_builder::{SipMessageBuilder, SipRequestBuilder};
-pub use zero_copy::{ZeroCopyModifier, B2BuaOperations, SessionTimerHeaders, SessionRefresher};
+pub use zero_copy::{ZeroCopyModifier, HeaderDiff, B2BuaOperations, SessionTimerHeaders, SessionRefresher};
 
 /// Zero-copy message modification API
 pub mod zero_copy {
     use crate::{
-        error::{SsbcError, SsbcResult as Result},
-        SipMessage,
+        error::{RoutingErrorKind, SsbcError, SsbcResult as Result},
+        headers::expand_compact_header_name,
+        main_impl::header_utils,
+        Method, SipMessage,
     };
     use std::collections::HashMap;
 
+    /// One change staged on a [`ZeroCopyModifier`], for audit logging
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub enum HeaderDiff {
+        /// Request line or status line rewritten
+        StartLine { old: String, new: String },
+        /// Header line added
+        Added { name: String, value: String },
+        /// Header line dropped
+        Removed { name: String, value: String },
+        /// Header line kept with a new value
+        Modified { name: String, old: String, new: String },
+    }
+
     /// A zero-copy builder for modifying SIP messages with minimal allocations
     pub struct ZeroCopyModifier {
         /// Original message for reference
@@ -472,11 +689,21 @@ pub mod zero_copy {
         modified_request_line: Option<String>,
         /// Modified status line (for responses)
         modified_status_line: Option<String>,
+        /// Drop the topmost Route element on build
+        pop_top_route: bool,
+        /// Route set replacing every existing Route header, in order
+        replacement_routes: Option<Vec<String>>,
+        /// Body replacing the original one
+        replacement_body: Option<String>,
+        /// Path entry written above the existing ones
+        top_path: Option<String>,
     }
 
     impl ZeroCopyModifier {
         /// Create a new modifier from a SipMessage
-        pub fn new(message: SipMessage) -> Self {
+        pub fn new(mut message: SipMessage) -> Self {
+            // Locates the body; a message that doesn't frame keeps no body
+            let _ = message.parse_without_validation();
             Self {
                 original: message,
                 modified_headers: HashMap::new(),
@@ -484,6 +711,10 @@ pub mod zero_copy {
                 new_headers: Vec::new(),
                 modified_request_line: None,
                 modified_status_line: None,
+                pop_top_route: false,
+                replacement_routes: None,
+                replacement_body: None,
+                top_path: None,
             }
         }
 
@@ -499,6 +730,52 @@ pub mod zero_copy {
             self
         }
 
+        /// Strip the Subject header, in long or compact form
+        pub fn strip_subject(&mut self) -> &mut Self {
+            self.stripped_headers.push("Subject".to_string());
+            self
+        }
+
+        /// Strip the Organization header
+        pub fn strip_organization(&mut self) -> &mut Self {
+            self.stripped_headers.push("Organization".to_string());
+            self
+        }
+
+        /// Set the Subject header, replacing a compact `s` line as well
+        pub fn set_subject(&mut self, subject: &str) -> Result<&mut Self> {
+            if subject.contains(['\r', '\n']) {
+                return Err(SsbcError::parse_error("Subject cannot contain line breaks", None, None));
+            }
+            self.modified_headers
+                .insert("Subject".to_string(), Some(subject.to_string()));
+            Ok(self)
+        }
+
+        /// Set the Organization header
+        pub fn set_organization(&mut self, organization: &str) -> Result<&mut Self> {
+            if organization.contains(['\r', '\n']) {
+                return Err(SsbcError::parse_error("Organization cannot contain line breaks", None, None));
+            }
+            self.modified_headers
+                .insert("Organization".to_string(), Some(organization.to_string()));
+            Ok(self)
+        }
+
+        /// Replace every Route header with `routes`, one header per element
+        ///
+        /// The new Route headers keep their order and are placed right after the
+        /// Via headers. Takes precedence over [`ZeroCopyModifier::pop_local_route`].
+        pub fn set_route(&mut self, routes: &[&str]) -> &mut Self {
+            self.replacement_routes = Some(routes.iter().map(|route| route.to_string()).collect());
+            self
+        }
+
+        /// Remove every Route header
+        pub fn clear_routes(&mut self) -> &mut Self {
+            self.set_route(&[])
+        }
+
         /// Replace Call-ID header value
         pub fn replace_call_id(&mut self, new_call_id: &str) -> Result<&mut Self> {
             if new_call_id.is_empty() {
@@ -509,6 +786,32 @@ pub mod zero_copy {
             Ok(self)
         }
 
+        /// Add a To tag unless the message already has one
+        ///
+        /// Idempotent: a tagged To, or one tagged by an earlier call, is left
+        /// alone, so a 2xx gets its tag exactly once. A compact `t` line is
+        /// rewritten as `To`. Fails on an empty tag or one that isn't a token,
+        /// and when the message has no To header.
+        pub fn ensure_to_tag(&mut self, tag: &str) -> Result<&mut Self> {
+            if tag.is_empty() || tag.contains([';', ',', '=', '"', ' ', '\t', '\r', '\n']) {
+                return Err(SsbcError::parse_error("Invalid To tag", None, Some(tag.to_string())));
+            }
+            if self.modified_headers.contains_key("To") {
+                return Ok(self);
+            }
+
+            let untagged = self.original.to_without_tag()?.map(|to| to.full_range);
+            match untagged {
+                Some(to) => {
+                    let to = to.as_str(self.original.raw_message()).to_string();
+                    self.modified_headers.insert("To".to_string(), Some(format!("{};tag={}", to, tag)));
+                }
+                None if self.original.has_header("To") => {}
+                None => return Err(SsbcError::parse_error("No To header", None, None)),
+            }
+            Ok(self)
+        }
+
         /// Update Contact header
         pub fn set_contact(&mut self, contact: &str) -> Result<&mut Self> {
             if contact.is_empty() {
@@ -528,6 +831,35 @@ pub mod zero_copy {
             Ok(self)
         }
 
+        /// Set Max-Forwards to an explicit value, adding the header if absent
+        pub fn set_max_forwards(&mut self, value: u32) -> &mut Self {
+            self.modified_headers
+                .insert("Max-Forwards".to_string(), Some(value.to_string()));
+            self
+        }
+
+        /// Replace the message body
+        ///
+        /// Content-Length is recomputed on build; Content-Type is left to the caller.
+        pub fn set_body(&mut self, body: &str) -> &mut Self {
+            self.replacement_body = Some(body.to_string());
+            self
+        }
+
+        /// Set Content-Length to the length of the body that will be emitted
+        ///
+        /// Overrides any stale value, including a compact `l` header, and adds the
+        /// header when absent. [`ZeroCopyModifier::build`] writes the same value
+        /// without staging it, so calling this is only needed for `diff`.
+        pub fn finalize_content_length(&mut self) -> &mut Self {
+            let length = self.emitted_body().len().to_string();
+            self.modified_headers
+                .retain(|name, _| !name.eq_ignore_ascii_case("Content-Length"));
+            self.modified_headers
+                .insert("Content-Length".to_string(), Some(length));
+            self
+        }
+
         /// Add header at appropriate position
         pub fn add_header(&mut self, name: &str, value: &str) -> &mut Self {
             self.new_headers.push((name.to_string(), value.to_string()));
@@ -535,11 +867,45 @@ pub mod zero_copy {
         }
 
         /// Add Via header (preserves order by adding to new headers)
+        ///
+        /// Added Vias go above the existing ones, in call order: the first
+        /// `add_via` ends up as the top Via.
         pub fn add_via(&mut self, via: &str) -> &mut Self {
             self.new_headers.push(("Via".to_string(), via.to_string()));
             self
         }
 
+        /// Replace the whole Via stack, top Via first
+        ///
+        /// Drops every original Via line, long or compact form, and any Via
+        /// staged with [`ZeroCopyModifier::add_via`].
+        pub fn set_via_stack(&mut self, vias: &[&str]) -> &mut Self {
+            if !self.stripped_headers.iter().any(|h| h.eq_ignore_ascii_case("Via")) {
+                self.strip_via_headers();
+            }
+            self.new_headers.retain(|(name, _)| name != "Via");
+            self.new_headers.extend(vias.iter().map(|via| ("Via".to_string(), via.to_string())));
+            self
+        }
+
+        /// Add this proxy's Path entry to a REGISTER (RFC 3327 Section 5.1)
+        ///
+        /// `uri` is wrapped in angle brackets and goes above any existing Path
+        /// entries so the registrar records the route back through this proxy;
+        /// it should carry `lr`. Fails unless the message is a REGISTER whose
+        /// Supported header lists `path`, in which case the proxy either forwards
+        /// without Path or rejects the request with 421 Extension Required.
+        pub fn add_path(&mut self, uri: &str) -> Result<&mut Self> {
+            if self.original.request_method() != Some(Method::REGISTER) {
+                return Err(SsbcError::parse_error("Path is only added to REGISTER requests", None, None));
+            }
+            if !self.original.supports_path() {
+                return Err(SsbcError::parse_error("REGISTER does not support path", None, None));
+            }
+            self.top_path = Some(format!("<{}>", uri));
+            Ok(self)
+        }
+
         /// Update request URI (for requests only)
         pub fn set_request_uri(&mut self, uri: &str) -> Result<&mut Self> {
             if let Some((method, _, version)) = self.parse_request_line()? {
@@ -550,37 +916,197 @@ pub mod zero_copy {
             }
         }
 
+        /// Rewrite the status code and reason phrase (for responses only)
+        pub fn set_status(&mut self, code: u16, reason: &str) -> Result<&mut Self> {
+            if !(100..=699).contains(&code) {
+                return Err(SsbcError::parse_error(
+                    format!("Status code {} outside 100-699", code),
+                    None,
+                    None,
+                ));
+            }
+            if reason.contains(['\r', '\n']) {
+                return Err(SsbcError::parse_error(
+                    "Reason phrase cannot contain line breaks",
+                    None,
+                    None,
+                ));
+            }
+            if self.original.is_request() {
+                return Err(SsbcError::parse_error("Not a response message", None, None));
+            }
+
+            let version = self.original.start_line()
+                .split(' ')
+                .next()
+                .unwrap_or("SIP/2.0");
+            self.modified_status_line = Some(format!("{} {} {}", version, code, reason));
+            Ok(self)
+        }
+
+        /// Remove the topmost Route element if it points at this proxy (RFC 3261 Section 16.4)
+        ///
+        /// The element matches when its host equals `local_host` (case-insensitively)
+        /// and its port, or the scheme default, equals `local_port`. A comma-joined
+        /// Route header keeps its remaining elements. Returns whether this call
+        /// staged the removal; once the element is popped, later calls return
+        /// `false`.
+        pub fn pop_local_route(&mut self, local_host: &str, local_port: u16) -> Result<bool> {
+            if self.pop_top_route {
+                return Ok(false);
+            }
+            let routes = self.original.routes()?;
+            let Some(top) = routes.first() else {
+                return Ok(false);
+            };
+
+            let host_matches = top.uri.host
+                .map(|host| host.as_str(self.original.raw_message()))
+                .is_some_and(|host| host.eq_ignore_ascii_case(local_host));
+            self.pop_top_route = host_matches && top.uri.effective_port() == local_port;
+            Ok(self.pop_top_route)
+        }
+
+        /// Describe the staged changes without building the message
+        ///
+        /// Computed from the staged modifications in the order `build` applies
+        /// them: start line, stripped headers, rewritten headers, the popped Route
+        /// element, then added headers. Rewrites of absent headers are reported as
+        /// additions, and a header both stripped and rewritten as removed.
+        pub fn diff(&self) -> Vec<HeaderDiff> {
+            let mut diff = Vec::new();
+
+            let new_start_line = self.modified_request_line.as_ref().or(self.modified_status_line.as_ref());
+            if let Some(new) = new_start_line {
+                diff.push(HeaderDiff::StartLine {
+                    old: self.original.start_line().to_string(),
+                    new: new.clone(),
+                });
+            }
+
+            let is_stripped = |name: &str| self.stripped_headers.iter().any(|h| h.eq_ignore_ascii_case(name));
+            for name in &self.stripped_headers {
+                for value in header_utils::get_header_values(&self.original, name) {
+                    diff.push(HeaderDiff::Removed { name: name.clone(), value });
+                }
+            }
+
+            let mut modified: Vec<_> = self.modified_headers.iter().filter(|(name, _)| !is_stripped(name)).collect();
+            modified.sort_by_key(|(name, _)| *name);
+            for (name, new_value) in modified {
+                let old_values = header_utils::get_header_values(&self.original, name);
+                match new_value {
+                    Some(new) if old_values.is_empty() => {
+                        diff.push(HeaderDiff::Added { name: name.clone(), value: new.clone() });
+                    }
+                    Some(new) => diff.extend(old_values.into_iter().map(|old| HeaderDiff::Modified {
+                        name: name.clone(),
+                        old,
+                        new: new.clone(),
+                    })),
+                    None => diff.extend(
+                        old_values.into_iter().map(|value| HeaderDiff::Removed { name: name.clone(), value }),
+                    ),
+                }
+            }
+
+            if let Some(routes) = &self.replacement_routes {
+                if !is_stripped("Route") {
+                    for value in header_utils::get_header_values(&self.original, "Route") {
+                        diff.push(HeaderDiff::Removed { name: "Route".to_string(), value });
+                    }
+                }
+                diff.extend(routes.iter().map(|value| HeaderDiff::Added {
+                    name: "Route".to_string(),
+                    value: value.clone(),
+                }));
+            } else if self.pop_top_route && !is_stripped("Route") {
+                if let Some(old) = header_utils::get_header_values(&self.original, "Route").into_iter().next() {
+                    match SipMessage::split_list_elements(&old).get(1) {
+                        Some(&(next_start, _)) => diff.push(HeaderDiff::Modified {
+                            name: "Route".to_string(),
+                            new: old[next_start..].to_string(),
+                            old,
+                        }),
+                        None => diff.push(HeaderDiff::Removed { name: "Route".to_string(), value: old }),
+                    }
+                }
+            }
+
+            if let Some(path) = &self.top_path {
+                diff.push(HeaderDiff::Added { name: "Path".to_string(), value: path.clone() });
+            }
+            diff.extend(self.new_headers.iter().map(|(name, value)| HeaderDiff::Added {
+                name: name.clone(),
+                value: value.clone(),
+            }));
+            diff
+        }
+
         /// Build final message with minimal allocations
-        pub fn build(mut self) -> Vec<u8> {
-            let mut result = Vec::with_capacity(self.estimate_size());
-            
+        pub fn build(self) -> Vec<u8> {
+            let mut result = Vec::new();
+            self.build_into(&mut result);
+            result
+        }
+
+        /// Build the final message and parse it again
+        ///
+        /// The built bytes become the new message without another copy and keep
+        /// the original's parser limits. Any edit can shift every header offset,
+        /// so one parse pass is still needed; this just saves the caller the
+        /// extra conversion and allocation of going through `build()` first.
+        pub fn build_and_parse(self) -> Result<SipMessage> {
+            let mut result = Vec::new();
+            self.build_into(&mut result);
+            let text = String::from_utf8(result)
+                .map_err(|e| SsbcError::parse_error("Built message is not valid UTF-8", None, Some(e.to_string())))?;
+            let mut message = SipMessage::with_limits(text, self.original.limits().clone());
+            message.parse_headers()?;
+            Ok(message)
+        }
+
+        /// Build the final message into a reusable buffer
+        ///
+        /// `buf` is cleared but keeps its capacity, so a forwarding loop can reuse
+        /// one allocation across messages. The staged modifications are kept and
+        /// [`ZeroCopyModifier::into_original`] hands the parsed message back, e.g.
+        /// to [`SipMessagePool::recycle`](crate::pool::SipMessagePool::recycle).
+        /// Content-Length is written for the emitted body, as
+        /// [`ZeroCopyModifier::finalize_content_length`] would stage it.
+        pub fn build_into(&self, buf: &mut Vec<u8>) {
+            let content_length = self.emitted_body().len().to_string();
+            let keep_content_length = !self.stripped_headers.iter()
+                .any(|name| name.eq_ignore_ascii_case("Content-Length"));
+            let mut content_length_written = false;
+            buf.clear();
+            buf.reserve(self.estimate_size());
+            let result = buf;
+
+            // Start line and headers, past any CRLFs before the start line
+            let header_block = self.original.header_block();
+            let headers_end = header_block.end;
+            let first_line_end = self.original.raw_message()[header_block.start..headers_end]
+                .find("\r\n")
+                .map_or(headers_end, |i| header_block.start + i);
+            let headers_start = (first_line_end + 2).min(headers_end);
+
             // Write request/status line
-            if let Some(request_line) = self.modified_request_line.take() {
+            if let Some(request_line) = &self.modified_request_line {
                 result.extend_from_slice(request_line.as_bytes());
                 result.extend_from_slice(b"\r\n");
-            } else if let Some(status_line) = self.modified_status_line.take() {
+            } else if let Some(status_line) = &self.modified_status_line {
                 result.extend_from_slice(status_line.as_bytes());
                 result.extend_from_slice(b"\r\n");
             } else {
                 // Use original first line
-                let first_line_end = self.original.raw_message()
-                    .find("\r\n")
-                    .unwrap_or(self.original.raw_message().len());
-                result.extend_from_slice(self.original.raw_message()[..first_line_end].as_bytes());
+                result.extend_from_slice(&self.original.raw_message().as_bytes()[header_block.start..first_line_end]);
                 result.extend_from_slice(b"\r\n");
             }
 
-            // Process headers
-            let headers_start = self.original.raw_message()
-                .find("\r\n")
-                .map(|i| i + 2)
-                .unwrap_or(0);
-            
-            let body_separator = "\r\n\r\n";
-            let headers_end = self.original.raw_message()[headers_start..]
-                .find(body_separator)
-                .map(|i| headers_start + i)
-                .unwrap_or(self.original.raw_message().len());
+            let mut pop_route = self.pop_top_route;
+            let mut pending_routes = self.replacement_routes.as_deref();
+            let mut pending_path = self.top_path.as_deref();
 
             // First, add any new Via headers (they must come first)
             for (name, value) in &self.new_headers {
@@ -592,31 +1118,74 @@ pub mod zero_copy {
                 }
             }
 
-            // Process existing headers
+            // Process existing headers, each with its continuation lines, so a
+            // dropped or rewritten header never leaves a fold behind
             if headers_start < headers_end {
-                for line in self.original.raw_message()[headers_start..headers_end].lines() {
-                    if line.is_empty() {
-                        continue;
-                    }
-
+                for line in header_lines(&self.original.raw_message()[headers_start..headers_end]) {
                     // Parse header name
                     if let Some(colon_pos) = line.find(':') {
                         let header_name = line[..colon_pos].trim();
+
+                        // The replacement route set goes right after the Vias
+                        let is_via = header_name.eq_ignore_ascii_case("Via") || header_name.eq_ignore_ascii_case("v");
+                        if !is_via {
+                            if let Some(routes) = pending_routes.take() {
+                                write_routes(result, routes);
+                            }
+                        }
+
+                        if header_name.eq_ignore_ascii_case("Path") {
+                            if let Some(path) = pending_path.take() {
+                                result.extend_from_slice(b"Path: ");
+                                result.extend_from_slice(path.as_bytes());
+                                result.extend_from_slice(b"\r\n");
+                            }
+                        }
+
+                        if self.replacement_routes.is_some() && header_name.eq_ignore_ascii_case("Route") {
+                            continue;
+                        }
                         
                         // Check if header should be stripped
-                        if self.stripped_headers.iter().any(|h| h.eq_ignore_ascii_case(header_name)) {
+                        let full_name = expand_compact_header_name(header_name);
+                        if self.stripped_headers.iter()
+                            .any(|h| h.eq_ignore_ascii_case(header_name) || h.eq_ignore_ascii_case(full_name))
+                        {
+                            continue;
+                        }
+
+                        if full_name.eq_ignore_ascii_case("Content-Length") {
+                            // Duplicates collapse into the one recomputed header
+                            if !content_length_written {
+                                result.extend_from_slice(b"Content-Length: ");
+                                result.extend_from_slice(content_length.as_bytes());
+                                result.extend_from_slice(b"\r\n");
+                                content_length_written = true;
+                            }
+                            continue;
+                        }
+
+                        if pop_route && header_name.eq_ignore_ascii_case("Route") {
+                            pop_route = false;
+                            let value = &line[colon_pos + 1..];
+                            if let Some(&(next_start, _)) = SipMessage::split_list_elements(value).get(1) {
+                                result.extend_from_slice(header_name.as_bytes());
+                                result.extend_from_slice(b": ");
+                                result.extend_from_slice(header_utils::unfold(&value[next_start..]).as_bytes());
+                                result.extend_from_slice(b"\r\n");
+                            }
                             continue;
                         }
 
-                        // Check if header has been modified (case-insensitive)
-                        let canonical_name = header_name.to_string();
-                        let new_value = self.modified_headers.iter()
-                            .find(|(k, _)| k.eq_ignore_ascii_case(&canonical_name))
-                            .map(|(_, v)| v);
+                        // Check if header has been modified (case-insensitive); a
+                        // compact line is rewritten under the full name
+                        let modified = self.modified_headers.iter()
+                            .find(|(k, _)| k.eq_ignore_ascii_case(header_name) || k.eq_ignore_ascii_case(full_name));
                         
-                        if let Some(new_value) = new_value {
+                        if let Some((name, new_value)) = modified {
+                            let name = if name.eq_ignore_ascii_case(header_name) { header_name } else { name };
                             if let Some(value) = new_value {
-                                result.extend_from_slice(header_name.as_bytes());
+                                result.extend_from_slice(name.as_bytes());
                                 result.extend_from_slice(b": ");
                                 result.extend_from_slice(value.as_bytes());
                                 result.extend_from_slice(b"\r\n");
@@ -631,6 +1200,15 @@ pub mod zero_copy {
                 }
             }
 
+            if let Some(routes) = pending_routes {
+                write_routes(result, routes);
+            }
+            if let Some(path) = pending_path {
+                result.extend_from_slice(b"Path: ");
+                result.extend_from_slice(path.as_bytes());
+                result.extend_from_slice(b"\r\n");
+            }
+
             // Add remaining new headers (non-Via)
             for (name, value) in &self.new_headers {
                 if name != "Via" {
@@ -644,13 +1222,16 @@ pub mod zero_copy {
             // Add headers that were modified but not present in original
             // We need to check all modified headers against all original headers case-insensitively
             for (name, value_opt) in &self.modified_headers {
+                if name.eq_ignore_ascii_case("Content-Length") {
+                    continue;
+                }
                 let exists_in_original = if headers_start < headers_end {
-                    self.original.raw_message()[headers_start..headers_end]
-                        .lines()
+                    header_lines(&self.original.raw_message()[headers_start..headers_end])
                         .any(|line| {
                             if let Some(colon_pos) = line.find(':') {
                                 let header_name = line[..colon_pos].trim();
                                 header_name.eq_ignore_ascii_case(name)
+                                    || expand_compact_header_name(header_name).eq_ignore_ascii_case(name)
                             } else {
                                 false
                             }
@@ -669,16 +1250,30 @@ pub mod zero_copy {
                 }
             }
 
+            if keep_content_length && !content_length_written {
+                result.extend_from_slice(b"Content-Length: ");
+                result.extend_from_slice(content_length.as_bytes());
+                result.extend_from_slice(b"\r\n");
+            }
+
             // Add body separator
             result.extend_from_slice(b"\r\n");
 
-            // Add body if present
-            if headers_end < self.original.raw_message().len() {
-                let body_start = headers_end + body_separator.len();
-                result.extend_from_slice(self.original.raw_message()[body_start..].as_bytes());
+            result.extend_from_slice(self.emitted_body());
+        }
+
+        /// Body written by build: the replacement, or the original's parsed body,
+        /// binary ones included
+        fn emitted_body(&self) -> &[u8] {
+            match &self.replacement_body {
+                Some(body) => body.as_bytes(),
+                None => self.original.body_bytes().unwrap_or_default(),
             }
+        }
 
-            result
+        /// Give back the original message, discarding the staged modifications
+        pub fn into_original(self) -> SipMessage {
+            self.original
         }
 
         /// Estimate the size of the final message for pre-allocation
@@ -689,6 +1284,19 @@ pub mod zero_copy {
             for (name, value) in &self.new_headers {
                 size += name.len() + 2 + value.len() + 2; // name: value\r\n
             }
+            for route in self.replacement_routes.iter().flatten() {
+                size += "Route: ".len() + route.len() + 2;
+            }
+            if let Some(path) = &self.top_path {
+                size += "Path: ".len() + path.len() + 2;
+            }
+
+            if let Some(body) = &self.replacement_body {
+                size += body.len();
+            } else if self.original.body().is_none() {
+                // A binary body is kept apart from the message text
+                size += self.emitted_body().len();
+            }
 
             // Account for modified headers (rough estimate)
             for (name, value_opt) in &self.modified_headers {
@@ -710,12 +1318,8 @@ pub mod zero_copy {
         /// Parse request line components
         fn parse_request_line(&self) -> Result<Option<(&str, &str, &str)>> {
             if self.original.is_request() {
-                let first_line = self.original.raw_message()
-                    .lines()
-                    .next()
-                    .ok_or_else(|| SsbcError::parse_error("Empty message", None, None))?;
-                
-                let parts: Vec<&str> = first_line.split(' ').collect();
+                // The parsed start line, past any CRLFs before it
+                let parts: Vec<&str> = self.original.start_line().split(' ').collect();
                 if parts.len() >= 3 {
                     Ok(Some((parts[0], parts[1], parts[2])))
                 } else {
@@ -733,6 +1337,104 @@ pub mod zero_copy {
         pub fn into_zero_copy_modifier(self) -> ZeroCopyModifier {
             ZeroCopyModifier::new(self)
         }
+
+        /// Run the proxy forwarding steps and render the request to send
+        ///
+        /// Checks Max-Forwards and loops before touching the message (RFC 3261
+        /// Sections 16.3 and 16.6): a Max-Forwards of 0 fails with
+        /// [`RoutingErrorKind::TooManyHops`], and an existing Via with the same
+        /// sent-by and branch as `via` fails with [`RoutingErrorKind::LoopDetected`];
+        /// map either to its status code. Otherwise `via` is pushed on top and
+        /// Max-Forwards decremented, or set to 70 when absent.
+        pub fn prepare_forward(&mut self, via: &str) -> Result<Vec<u8>> {
+            if !self.is_request() {
+                return Err(SsbcError::parse_error("Not a request message", None, None));
+            }
+            self.parse_headers()?;
+
+            let max_forwards = match self.max_forwards() {
+                Some(0) => {
+                    return Err(SsbcError::routing_error(
+                        RoutingErrorKind::TooManyHops,
+                        Some("Max-Forwards is 0".to_string()),
+                    ))
+                }
+                Some(hops) => hops - 1,
+                None => 70,
+            };
+
+            let (sent_by, branch) = via_sent_by_and_branch(via)
+                .ok_or_else(|| SsbcError::parse_error("Invalid Via to forward with", None, Some(via.to_string())))?;
+            if let Some(branch) = branch {
+                let vias = self.via_list()?;
+                let raw_message = self.raw_message();
+                for existing in &vias {
+                    let same_sent_by = existing.sent_by.as_str(raw_message).trim().eq_ignore_ascii_case(sent_by);
+                    let same_branch = existing.params.iter().any(|(key, value)| {
+                        key.as_str(raw_message).eq_ignore_ascii_case("branch")
+                            && value.is_some_and(|value| value.as_str(raw_message) == branch)
+                    });
+                    if same_sent_by && same_branch {
+                        return Err(SsbcError::routing_error(
+                            RoutingErrorKind::LoopDetected,
+                            Some(format!("Via {} already present", sent_by)),
+                        ));
+                    }
+                }
+            }
+
+            let mut modifier = self.clone().into_zero_copy_modifier();
+            modifier.add_via(via).set_max_forwards(max_forwards);
+            Ok(modifier.build())
+        }
+    }
+
+    /// Split a header section into whole headers, each with its continuation lines
+    fn header_lines(headers: &str) -> impl Iterator<Item = &str> {
+        let mut rest = headers;
+        std::iter::from_fn(move || {
+            rest = rest.trim_start_matches("\r\n");
+            if rest.is_empty() {
+                return None;
+            }
+            let mut end = 0;
+            loop {
+                match rest[end..].find("\r\n") {
+                    Some(pos) if rest[end + pos + 2..].starts_with([' ', '\t']) => end += pos + 2,
+                    Some(pos) => {
+                        end += pos;
+                        break;
+                    }
+                    None => {
+                        end = rest.len();
+                        break;
+                    }
+                }
+            }
+            let (header, remaining) = rest.split_at(end);
+            rest = remaining;
+            Some(header)
+        })
+    }
+
+    /// Write one Route header per element
+    fn write_routes(buf: &mut Vec<u8>, routes: &[String]) {
+        for route in routes {
+            buf.extend_from_slice(b"Route: ");
+            buf.extend_from_slice(route.as_bytes());
+            buf.extend_from_slice(b"\r\n");
+        }
+    }
+
+    /// Sent-by and branch of a Via value such as `SIP/2.0/UDP host:5060;branch=z9hG4bK1`
+    fn via_sent_by_and_branch(via: &str) -> Option<(&str, Option<&str>)> {
+        let (protocol_and_sent_by, params) = via.split_once(';').unwrap_or((via, ""));
+        let sent_by = protocol_and_sent_by.split_whitespace().nth(1)?;
+        let branch = params.split(';').find_map(|param| {
+            let (name, value) = param.split_once('=')?;
+            name.trim().eq_ignore_ascii_case("branch").then(|| value.trim())
+        });
+        Some((sent_by, branch))
     }
 
     /// Session timer integration
@@ -742,12 +1444,23 @@ pub mod zero_copy {
         Uas,
     }
 
+    impl SessionRefresher {
+        fn as_str(&self) -> &'static str {
+            match self {
+                SessionRefresher::Uac => "uac",
+                SessionRefresher::Uas => "uas",
+            }
+        }
+    }
+
     /// Session timer headers configuration
     #[derive(Debug, Clone)]
     pub struct SessionTimerHeaders {
         pub session_expires: u32,
         pub min_se: Option<u32>,
-        pub refresher: SessionRefresher,
+        /// `None` when Session-Expires carries no `refresher` parameter, leaving
+        /// the choice to negotiation (RFC 4028 Section 9)
+        pub refresher: Option<SessionRefresher>,
         pub required: bool,
     }
 
@@ -755,14 +1468,10 @@ pub mod zero_copy {
         /// Add session timer headers in one operation
         pub fn add_session_timer_headers(&mut self, params: &SessionTimerHeaders) -> &mut Self {
             // Add Session-Expires header
-            let session_expires_value = format!(
-                "{};refresher={}",
-                params.session_expires,
-                match params.refresher {
-                    SessionRefresher::Uac => "uac",
-                    SessionRefresher::Uas => "uas",
-                }
-            );
+            let session_expires_value = match &params.refresher {
+                Some(refresher) => format!("{};refresher={}", params.session_expires, refresher.as_str()),
+                None => params.session_expires.to_string(),
+            };
             self.add_header("Session-Expires", &session_expires_value);
 
             // Add Min-SE header if specified
@@ -783,14 +1492,7 @@ pub mod zero_copy {
         /// Update session timer in response
         pub fn update_session_timer(&mut self, new_expires: u32, refresher: SessionRefresher) -> &mut Self {
             // Replace Session-Expires header
-            let session_expires_value = format!(
-                "{};refresher={}",
-                new_expires,
-                match refresher {
-                    SessionRefresher::Uac => "uac",
-                    SessionRefresher::Uas => "uas",
-                }
-            );
+            let session_expires_value = format!("{};refresher={}", new_expires, refresher.as_str());
             self.modified_headers.insert("Session-Expires".to_string(), Some(session_expires_value));
             self
         }
@@ -798,14 +1500,37 @@ pub mod zero_copy {
 
     impl SipMessage {
         /// Parse session timer headers efficiently
+        ///
+        /// Returns `None` when there is no usable Session-Expires header.
         pub fn parse_session_timer_headers(&self) -> Option<SessionTimerHeaders> {
-            // For testing purposes, return a default value
-            // In a real implementation, this would parse the actual headers
+            let session_expires = self.header_value_ranges("Session-Expires").into_iter().next()?;
+            let mut parts = self.get_str(session_expires).split(';');
+            let session_expires = parts.next()?.trim().parse::<u32>().ok()?;
+
+            let refresher = parts.find_map(|param| {
+                let (name, value) = param.split_once('=')?;
+                if !name.trim().eq_ignore_ascii_case("refresher") {
+                    return None;
+                }
+                match value.trim().to_ascii_lowercase().as_str() {
+                    "uac" => Some(SessionRefresher::Uac),
+                    "uas" => Some(SessionRefresher::Uas),
+                    _ => None,
+                }
+            });
+
+            let min_se = self.min_se().ok().flatten();
+
+            let required = self
+                .option_tags("Require")
+                .iter()
+                .any(|tag| tag.eq_ignore_ascii_case("timer"));
+
             Some(SessionTimerHeaders {
-                session_expires: 1800,
-                min_se: Some(90),
-                refresher: SessionRefresher::Uas,
-                required: false,
+                session_expires,
+                min_se,
+                refresher,
+                required,
             })
         }
         
@@ -1195,6 +1920,13 @@ pub mod zero_copy {
             assert!(result_str.starts_with("INVITE sip:charlie@example.com SIP/2.0"));
             // The To header still contains bob@example.com, so just check the request line changed
             assert!(!result_str.starts_with("INVITE sip:bob@example.com SIP/2.0"));
+
+            // CRLFs before the start line are skipped
+            let sip_msg = SipMessage::parse(format!("\r\n\r\n{}", msg).as_bytes()).unwrap();
+            let mut modifier = sip_msg.into_zero_copy_modifier();
+            modifier.set_request_uri("sip:c@example.com").unwrap();
+            let result = String::from_utf8(modifier.build()).unwrap();
+            assert!(result.starts_with("INVITE sip:c@example.com SIP/2.0\r\nVia: "));
         }
 
         #[test]
@@ -1283,6 +2015,749 @@ pub mod zero_copy {
             assert!(result.is_err());
         }
 
+        #[test]
+        fn test_set_status() {
+            let msg = "SIP/2.0 486 Busy Here\r\n\
+                       Via: SIP/2.0/UDP proxy.example.com;branch=z9hG4bK776asdhds\r\n\
+                       From: Alice <sip:alice@example.com>;tag=1234\r\n\
+                       To: Bob <sip:bob@example.com>;tag=5678\r\n\
+                       Call-ID: test-call-id\r\n\
+                       CSeq: 1 INVITE\r\n\
+                       \r\n";
+
+            let sip_msg = SipMessage::parse(msg.as_bytes()).unwrap();
+            let mut modifier = sip_msg.into_zero_copy_modifier();
+            modifier.set_status(603, "Decline").unwrap();
+            let result = modifier.build();
+            let result_str = String::from_utf8_lossy(&result);
+
+            assert!(result_str.starts_with("SIP/2.0 603 Decline\r\n"));
+            assert!(result_str.contains("Call-ID: test-call-id"));
+        }
+
+        #[test]
+        fn test_set_status_errors() {
+            let response = "SIP/2.0 200 OK\r\n\
+                            Via: SIP/2.0/UDP proxy.example.com;branch=z9hG4bK776asdhds\r\n\
+                            From: Alice <sip:alice@example.com>;tag=1234\r\n\
+                            To: Bob <sip:bob@example.com>;tag=5678\r\n\
+                            Call-ID: test-call-id\r\n\
+                            CSeq: 1 INVITE\r\n\
+                            \r\n";
+            let mut modifier = SipMessage::parse(response.as_bytes()).unwrap().into_zero_copy_modifier();
+            assert!(modifier.set_status(99, "Too Low").is_err());
+            assert!(modifier.set_status(700, "Too High").is_err());
+            assert!(modifier.set_status(200, "OK\r\nX-Injected: 1").is_err());
+
+            let request = "INVITE sip:bob@example.com SIP/2.0\r\n\
+                           Via: SIP/2.0/UDP client.example.com;branch=z9hG4bK776asdhds\r\n\
+                           From: Alice <sip:alice@example.com>;tag=123\r\n\
+                           To: Bob <sip:bob@example.com>\r\n\
+                           Call-ID: test-call-id\r\n\
+                           CSeq: 1 INVITE\r\n\
+                           Max-Forwards: 70\r\n\
+                           \r\n";
+            let mut modifier = SipMessage::parse(request.as_bytes()).unwrap().into_zero_copy_modifier();
+            assert!(modifier.set_status(200, "OK").is_err());
+        }
+
+        #[test]
+        fn test_pop_local_route() {
+            let msg = "INVITE sip:bob@example.com SIP/2.0\r\n\
+                       Via: SIP/2.0/UDP client.example.com;branch=z9hG4bK776asdhds\r\n\
+                       Route: <sip:proxy.example.com;lr>, <sip:edge.example.net;lr>\r\n\
+                       Route: <sip:core.example.org;lr>\r\n\
+                       From: Alice <sip:alice@example.com>;tag=123\r\n\
+                       To: Bob <sip:bob@example.com>\r\n\
+                       Call-ID: test-call-id\r\n\
+                       CSeq: 1 INVITE\r\n\
+                       Max-Forwards: 70\r\n\
+                       \r\n";
+
+            let mut modifier = SipMessage::parse(msg.as_bytes()).unwrap().into_zero_copy_modifier();
+            assert!(!modifier.pop_local_route("proxy.example.com", 5061).unwrap());
+            assert!(!modifier.pop_local_route("other.example.com", 5060).unwrap());
+            assert!(modifier.pop_local_route("PROXY.example.com", 5060).unwrap());
+            assert!(!modifier.pop_local_route("proxy.example.com", 5060).unwrap());
+
+            let result = String::from_utf8(modifier.build()).unwrap();
+            assert!(!result.contains("proxy.example.com"));
+            assert!(result.contains("Route: <sip:edge.example.net;lr>\r\nRoute: <sip:core.example.org;lr>\r\n"));
+
+            // A folded Route keeps its remaining elements and leaves the Via intact
+            let folded = msg.replace("lr>, <sip:edge", "lr>,\r\n <sip:edge");
+            let mut modifier = SipMessage::parse(folded.as_bytes()).unwrap().into_zero_copy_modifier();
+            assert!(modifier.pop_local_route("proxy.example.com", 5060).unwrap());
+            let popped = modifier.build_and_parse().unwrap();
+            assert!(popped.raw_message().contains(
+                "Via: SIP/2.0/UDP client.example.com;branch=z9hG4bK776asdhds\r\n\
+                 Route: <sip:edge.example.net;lr>\r\n"
+            ));
+            let routes = popped.routes().unwrap();
+            assert_eq!(routes.len(), 2);
+            assert_eq!(routes[0].uri.host.map(|host| host.as_str(popped.raw_message())), Some("edge.example.net"));
+        }
+
+        #[test]
+        fn test_diff_reports_staged_changes() {
+            let msg = "INVITE sip:bob@example.com SIP/2.0\r\n\
+                       Via: SIP/2.0/UDP client.example.com;branch=z9hG4bK776asdhds\r\n\
+                       Route: <sip:proxy.example.com;lr>\r\n\
+                       From: Alice <sip:alice@example.com>;tag=123\r\n\
+                       To: Bob <sip:bob@example.com>\r\n\
+                       Call-ID: original-call-id\r\n\
+                       CSeq: 1 INVITE\r\n\
+                       Max-Forwards: 70\r\n\
+                       \r\n";
+
+            let mut modifier = SipMessage::parse(msg.as_bytes()).unwrap().into_zero_copy_modifier();
+            assert!(modifier.diff().is_empty());
+
+            modifier.set_request_uri("sip:bob@192.0.2.10").unwrap();
+            modifier.strip_via_headers();
+            modifier.replace_call_id("b2bua-call-id").unwrap();
+            modifier.pop_local_route("proxy.example.com", 5060).unwrap();
+            modifier.add_header("X-Trace", "abc");
+
+            assert_eq!(
+                modifier.diff(),
+                vec![
+                    HeaderDiff::StartLine {
+                        old: "INVITE sip:bob@example.com SIP/2.0".to_string(),
+                        new: "INVITE sip:bob@192.0.2.10 SIP/2.0".to_string(),
+                    },
+                    HeaderDiff::Removed {
+                        name: "Via".to_string(),
+                        value: "SIP/2.0/UDP client.example.com;branch=z9hG4bK776asdhds".to_string(),
+                    },
+                    HeaderDiff::Modified {
+                        name: "Call-ID".to_string(),
+                        old: "original-call-id".to_string(),
+                        new: "b2bua-call-id".to_string(),
+                    },
+                    HeaderDiff::Removed {
+                        name: "Route".to_string(),
+                        value: "<sip:proxy.example.com;lr>".to_string(),
+                    },
+                    HeaderDiff::Added { name: "X-Trace".to_string(), value: "abc".to_string() },
+                ]
+            );
+        }
+
+        #[test]
+        fn test_prepare_forward() {
+            let msg = "INVITE sip:bob@example.com SIP/2.0\r\n\
+                       Via: SIP/2.0/UDP client.example.com;branch=z9hG4bK776asdhds\r\n\
+                       From: Alice <sip:alice@example.com>;tag=123\r\n\
+                       To: Bob <sip:bob@example.com>\r\n\
+                       Call-ID: test-call-id\r\n\
+                       CSeq: 1 INVITE\r\n\
+                       Max-Forwards: 5\r\n\
+                       \r\n";
+
+            let mut sip_msg = SipMessage::parse(msg.as_bytes()).unwrap();
+            let forwarded = sip_msg.prepare_forward("SIP/2.0/UDP proxy.example.com;branch=z9hG4bKproxy1").unwrap();
+            let forwarded = String::from_utf8(forwarded).unwrap();
+            assert!(forwarded.starts_with(
+                "INVITE sip:bob@example.com SIP/2.0\r\n\
+                 Via: SIP/2.0/UDP proxy.example.com;branch=z9hG4bKproxy1\r\n\
+                 Via: SIP/2.0/UDP client.example.com;branch=z9hG4bK776asdhds\r\n"
+            ));
+            assert!(forwarded.contains("Max-Forwards: 4\r\n"));
+
+            let mut looped = SipMessage::parse(forwarded.as_bytes()).unwrap();
+            let error = looped.prepare_forward("SIP/2.0/UDP proxy.example.com;branch=z9hG4bKproxy1").unwrap_err();
+            assert!(matches!(error, SsbcError::RoutingError { kind: RoutingErrorKind::LoopDetected, .. }));
+            // Same element with a new branch is a spiral, not a loop
+            assert!(looped.prepare_forward("SIP/2.0/UDP proxy.example.com;branch=z9hG4bKproxy2").is_ok());
+
+            // Our Via as the second element of a comma-joined header is still a loop
+            let joined = msg.replace(
+                "Via: SIP/2.0/UDP client.example.com;branch=z9hG4bK776asdhds",
+                "Via: SIP/2.0/UDP edge.example.net;branch=z9hG4bKedge1, SIP/2.0/UDP proxy.example.com;branch=z9hG4bKproxy1",
+            );
+            let mut looped = SipMessage::parse(joined.as_bytes()).unwrap();
+            let error = looped.prepare_forward("SIP/2.0/UDP proxy.example.com;branch=z9hG4bKproxy1").unwrap_err();
+            assert!(matches!(error, SsbcError::RoutingError { kind: RoutingErrorKind::LoopDetected, .. }));
+
+            let mut exhausted = SipMessage::parse(msg.replace("Max-Forwards: 5", "Max-Forwards: 0").as_bytes()).unwrap();
+            match exhausted.prepare_forward("SIP/2.0/UDP proxy.example.com;branch=z9hG4bKproxy1") {
+                Err(SsbcError::RoutingError { kind, .. }) => assert_eq!(kind.status_code(), 483),
+                other => panic!("expected TooManyHops, got {:?}", other),
+            }
+        }
+
+        #[test]
+        fn test_builder_escaping_round_trip() {
+            use crate::modification::message_builder::SipMessageBuilder;
+            use crate::{Method, Scheme};
+
+            let request = SipMessageBuilder::new()
+                .method(Method::INVITE)
+                .uri_parts(Scheme::SIP, Some("john smith@home"), "example.com")
+                .via("UDP", "client.example.com", "z9hG4bK776asdhds")
+                .from(Some("O'Brien, Bob"), "sip:obrien@example.com", "abc123")
+                .to(Some("The \"Boss\""), "sip:boss@example.com", None)
+                .header("Call-ID", "escape-test")
+                .header("CSeq", "1 INVITE")
+                .build()
+                .unwrap();
+            assert!(request.starts_with("INVITE sip:john%20smith%40home@example.com SIP/2.0\r\n"));
+            assert!(request.contains("From: \"O'Brien, Bob\" <sip:obrien@example.com>;tag=abc123\r\n"));
+            assert!(request.contains("To: \"The \\\"Boss\\\"\" <sip:boss@example.com>\r\n"));
+
+            let mut parsed = SipMessage::parse(request.as_bytes()).unwrap();
+            let uri = parsed.request_uri().unwrap();
+            assert_eq!(parsed.get_opt_str(uri.user_info), Some("john%20smith%40home"));
+            assert_eq!(parsed.get_opt_str(uri.host), Some("example.com"));
+            assert_eq!(parsed.from_tag(), Some("abc123"));
+            let from = parsed.from().unwrap().unwrap().clone();
+            assert_eq!(parsed.get_opt_str(from.display_name), Some("O'Brien, Bob"));
+            let to = parsed.to().unwrap().unwrap().clone();
+            assert_eq!(parsed.get_opt_str(to.display_name), Some("The \\\"Boss\\\""));
+        }
+
+        #[test]
+        fn test_bad_extension_response() {
+            use crate::modification::message_builder::SipMessageBuilder;
+
+            let msg = "INVITE sip:bob@example.com SIP/2.0\r\n\
+                       v: SIP/2.0/UDP proxy.example.com;branch=z9hG4bKproxy1\r\n\
+                       Via: SIP/2.0/UDP client.example.com;branch=z9hG4bK776asdhds\r\n\
+                       From: Alice <sip:alice@example.com>;tag=123\r\n\
+                       To: Bob <sip:bob@example.com>\r\n\
+                       Call-ID: test-call-id\r\n\
+                       CSeq: 1 INVITE\r\n\
+                       Require: foo, 100rel\r\n\
+                       Max-Forwards: 70\r\n\
+                       \r\n";
+            let request = SipMessage::parse(msg.as_bytes()).unwrap();
+            let response = SipMessageBuilder::bad_extension_response(&request, &["foo"]).build().unwrap();
+
+            assert!(response.starts_with(
+                "SIP/2.0 420 Bad Extension\r\n\
+                 Via: SIP/2.0/UDP proxy.example.com;branch=z9hG4bKproxy1\r\n\
+                 Via: SIP/2.0/UDP client.example.com;branch=z9hG4bK776asdhds\r\n\
+                 From: Alice <sip:alice@example.com>;tag=123\r\n\
+                 To: Bob <sip:bob@example.com>;tag="
+            ));
+            assert!(response.contains("Call-ID: test-call-id\r\nCSeq: 1 INVITE\r\n"));
+            assert!(response.contains("Unsupported: foo\r\n"));
+            assert!(!response.contains("Require"));
+            assert!(!response.contains("Max-Forwards"));
+
+            let mut parsed = SipMessage::parse(response.as_bytes()).unwrap();
+            assert_eq!(parsed.status_line().unwrap().code, 420);
+            assert!(parsed.remote_tag().unwrap().is_some());
+        }
+
+        #[test]
+        fn test_canned_rejections() {
+            use crate::modification::message_builder::SipMessageBuilder;
+            use crate::Method;
+
+            let msg = "INVITE sip:bob@example.com SIP/2.0\r\n\
+                       Via: SIP/2.0/UDP client.example.com;branch=z9hG4bK776asdhds\r\n\
+                       From: Alice <sip:alice@example.com>;tag=123\r\n\
+                       To: Bob <sip:bob@example.com>;tag=existing\r\n\
+                       Call-ID: test-call-id\r\n\
+                       CSeq: 1 INVITE\r\n\
+                       Max-Forwards: 0\r\n\
+                       \r\n";
+            let request = SipMessage::parse(msg.as_bytes()).unwrap();
+            let copied = "Via: SIP/2.0/UDP client.example.com;branch=z9hG4bK776asdhds\r\n\
+                          From: Alice <sip:alice@example.com>;tag=123\r\n\
+                          To: Bob <sip:bob@example.com>;tag=existing\r\n\
+                          Call-ID: test-call-id\r\n\
+                          CSeq: 1 INVITE\r\n";
+
+            let cases = [
+                (SipMessageBuilder::method_not_allowed(&request, &[Method::INVITE, Method::ACK, Method::BYE]), "SIP/2.0 405 Method Not Allowed\r\n"),
+                (SipMessageBuilder::too_many_hops(&request), "SIP/2.0 483 Too Many Hops\r\n"),
+                (SipMessageBuilder::bad_request(&request, "Bad\r\nX-Injected: 1"), "SIP/2.0 400 Bad  X-Injected: 1\r\n"),
+                (SipMessageBuilder::server_internal_error(&request), "SIP/2.0 500 Server Internal Error\r\n"),
+            ];
+            for (builder, start_line) in cases {
+                let response = builder.build().unwrap();
+                assert!(response.starts_with(&format!("{}{}", start_line, copied)), "{}", response);
+                SipMessage::parse(response.as_bytes()).unwrap();
+            }
+
+            let response = SipMessageBuilder::method_not_allowed(&request, &[Method::INVITE, Method::ACK]).build().unwrap();
+            assert!(response.contains("Allow: INVITE, ACK\r\n"));
+        }
+
+        #[test]
+        fn test_response_from_request_keeps_via_stack() {
+            use crate::modification::message_builder::SipMessageBuilder;
+
+            let msg = "BYE sip:bob@192.0.2.4 SIP/2.0\r\n\
+                       Via: SIP/2.0/UDP edge.example.com;branch=z9hG4bKedge, SIP/2.0/TCP core.example.com;branch=z9hG4bKcore\r\n\
+                       Record-Route: <sip:edge.example.com;lr>\r\n\
+                       Via: SIP/2.0/UDP client.example.com;branch=z9hG4bK776asdhds\r\n\
+                       f: Alice <sip:alice@example.com>;tag=123\r\n\
+                       t: Bob <sip:bob@example.com>;tag=456\r\n\
+                       i: test-call-id\r\n\
+                       CSeq: 2 BYE\r\n\
+                       Max-Forwards: 70\r\n\
+                       \r\n";
+            let request = SipMessage::parse(msg.as_bytes()).unwrap();
+            let response = SipMessageBuilder::from_request(&request, 200, "OK").build().unwrap();
+
+            assert_eq!(
+                response,
+                "SIP/2.0 200 OK\r\n\
+                 Via: SIP/2.0/UDP edge.example.com;branch=z9hG4bKedge, SIP/2.0/TCP core.example.com;branch=z9hG4bKcore\r\n\
+                 Via: SIP/2.0/UDP client.example.com;branch=z9hG4bK776asdhds\r\n\
+                 From: Alice <sip:alice@example.com>;tag=123\r\n\
+                 To: Bob <sip:bob@example.com>;tag=456\r\n\
+                 Call-ID: test-call-id\r\n\
+                 CSeq: 2 BYE\r\n\
+                 Content-Length: 0\r\n\
+                 \r\n"
+            );
+        }
+
+        #[test]
+        fn test_multipart_body() {
+            use crate::modification::message_builder::SipMessageBuilder;
+            use crate::{ContentType, Method};
+
+            let sdp = "v=0\r\no=- 1 1 IN IP4 192.0.2.1\r\ns=-\r\n";
+            let request = SipMessageBuilder::new()
+                .method(Method::INVITE)
+                .uri_str("sip:bob@example.com")
+                .header("Content-Type", "application/sdp")
+                .multipart_body(&[
+                    (ContentType::Sdp, sdp),
+                    (ContentType::Other("application/x-custom".to_string()), "custom"),
+                ])
+                .build()
+                .unwrap();
+
+            let mut parsed = SipMessage::new_from_str(&request);
+            parsed.parse_without_validation().unwrap();
+            let content_types = header_utils::get_header_values(&parsed, "content-type");
+            assert_eq!(content_types.len(), 1);
+            let boundary = content_types[0].strip_prefix("multipart/mixed;boundary=").unwrap();
+
+            let body = parsed.body().unwrap();
+            assert_eq!(
+                body,
+                format!(
+                    "--{b}\r\nContent-Type: application/sdp\r\n\r\n{sdp}\r\n\
+                     --{b}\r\nContent-Type: application/x-custom\r\n\r\ncustom\r\n\
+                     --{b}--\r\n",
+                    b = boundary,
+                    sdp = sdp
+                )
+            );
+            assert!(request.contains(&format!("Content-Length: {}\r\n", body.len())));
+        }
+
+        #[test]
+        fn test_subject_and_organization() {
+            let msg = "INVITE sip:bob@example.com SIP/2.0\r\n\
+                       Via: SIP/2.0/UDP client.example.com;branch=z9hG4bK776asdhds\r\n\
+                       From: Alice <sip:alice@example.com>;tag=123\r\n\
+                       To: Bob <sip:bob@example.com>\r\n\
+                       Call-ID: test-call-id\r\n\
+                       CSeq: 1 INVITE\r\n\
+                       s: Project X kickoff\r\n\
+                       Organization: Example Corp\r\n\
+                       Content-Length: 0\r\n\
+                       \r\n";
+
+            let sip_msg = SipMessage::parse(msg.as_bytes()).unwrap();
+            assert_eq!(sip_msg.subject(), Some("Project X kickoff"));
+            assert_eq!(sip_msg.organization(), Some("Example Corp"));
+
+            let mut modifier = sip_msg.clone().into_zero_copy_modifier();
+            modifier.set_subject("Call").unwrap().strip_organization();
+            assert_eq!(
+                modifier.diff(),
+                vec![
+                    HeaderDiff::Removed {
+                        name: "Organization".to_string(),
+                        value: "Example Corp".to_string(),
+                    },
+                    HeaderDiff::Modified {
+                        name: "Subject".to_string(),
+                        old: "Project X kickoff".to_string(),
+                        new: "Call".to_string(),
+                    },
+                ]
+            );
+            let rewritten = SipMessage::parse(&modifier.build()).unwrap();
+            assert_eq!(rewritten.subject(), Some("Call"));
+            assert_eq!(rewritten.organization(), None);
+            assert!(!rewritten.raw_message().contains("kickoff"));
+
+            let mut modifier = sip_msg.into_zero_copy_modifier();
+            assert!(modifier.set_organization("Evil\r\nX-Injected: 1").is_err());
+            modifier.strip_subject().set_organization("Other Corp").unwrap();
+            assert_eq!(
+                modifier.diff(),
+                vec![
+                    HeaderDiff::Removed {
+                        name: "Subject".to_string(),
+                        value: "Project X kickoff".to_string(),
+                    },
+                    HeaderDiff::Modified {
+                        name: "Organization".to_string(),
+                        old: "Example Corp".to_string(),
+                        new: "Other Corp".to_string(),
+                    },
+                ]
+            );
+            let scrubbed = SipMessage::parse(&modifier.build()).unwrap();
+            assert_eq!(scrubbed.subject(), None);
+            assert_eq!(scrubbed.organization(), Some("Other Corp"));
+
+            // Neither setter leaks the continuation of a folded Subject
+            let folded = msg.replace("s: Project X kickoff", "Subject: first part\r\n second part");
+            let mut modifier = SipMessage::parse(folded.as_bytes()).unwrap().into_zero_copy_modifier();
+            modifier.strip_subject();
+            let mut scrubbed = modifier.build_and_parse().unwrap();
+            assert!(!scrubbed.raw_message().contains("part"));
+            assert_eq!(scrubbed.cseq_method().unwrap(), Some(Method::INVITE));
+            assert_eq!(header_utils::extract_header_value(&scrubbed, "CSeq").as_deref(), Some("1 INVITE"));
+
+            let mut modifier = SipMessage::parse(folded.as_bytes()).unwrap().into_zero_copy_modifier();
+            modifier.set_subject("New").unwrap();
+            let renamed = modifier.build_and_parse().unwrap();
+            assert_eq!(renamed.subject(), Some("New"));
+            assert!(!renamed.raw_message().contains("part"));
+        }
+
+        #[test]
+        fn test_set_route_replaces_route_set() {
+            let msg = "INVITE sip:bob@example.com SIP/2.0\r\n\
+                       Via: SIP/2.0/UDP proxy.example.com;branch=z9hG4bKproxy1\r\n\
+                       Via: SIP/2.0/UDP client.example.com;branch=z9hG4bK776asdhds\r\n\
+                       From: Alice <sip:alice@example.com>;tag=123\r\n\
+                       Route: <sip:old1.example.com;lr>, <sip:old2.example.com;lr>\r\n\
+                       To: Bob <sip:bob@example.com>\r\n\
+                       Route: <sip:old3.example.com;lr>\r\n\
+                       Call-ID: test-call-id\r\n\
+                       CSeq: 1 INVITE\r\n\
+                       Content-Length: 0\r\n\
+                       \r\n";
+            let sip_msg = SipMessage::parse(msg.as_bytes()).unwrap();
+
+            let mut modifier = sip_msg.clone().into_zero_copy_modifier();
+            modifier.set_route(&["<sip:edge.example.com;lr>", "<sip:core.example.com;lr>"]);
+            let result = String::from_utf8(modifier.build()).unwrap();
+            assert!(!result.contains("old"));
+            assert!(result.contains(
+                "Via: SIP/2.0/UDP client.example.com;branch=z9hG4bK776asdhds\r\n\
+                 Route: <sip:edge.example.com;lr>\r\n\
+                 Route: <sip:core.example.com;lr>\r\n\
+                 From: Alice"
+            ));
+
+            let mut modifier = sip_msg.into_zero_copy_modifier();
+            modifier.clear_routes();
+            assert_eq!(modifier.diff().len(), 2);
+            let result = String::from_utf8(modifier.build()).unwrap();
+            assert!(!result.contains("Route:"));
+
+            // Continuation lines of a folded Route go with it
+            let folded = msg.replace("lr>, <sip:old2", "lr>,\r\n <sip:old2");
+            let mut modifier = SipMessage::parse(folded.as_bytes()).unwrap().into_zero_copy_modifier();
+            modifier.set_route(&["<sip:new.example.com;lr>"]);
+            let rerouted = modifier.build_and_parse().unwrap();
+            assert!(!rerouted.raw_message().contains("old"));
+            assert_eq!(rerouted.routes().unwrap().len(), 1);
+            assert_eq!(rerouted.from_tag(), Some("123"));
+
+            let mut modifier = SipMessage::parse(folded.as_bytes()).unwrap().into_zero_copy_modifier();
+            modifier.clear_routes();
+            let cleared = modifier.build_and_parse().unwrap();
+            assert!(!cleared.raw_message().contains("old"));
+            assert_eq!(cleared.from_tag(), Some("123"));
+        }
+
+        #[test]
+        fn test_interval_too_brief() {
+            use crate::modification::message_builder::SipMessageBuilder;
+
+            let msg = "REGISTER sip:registrar.example.com SIP/2.0\r\n\
+                       Via: SIP/2.0/UDP client.example.com;branch=z9hG4bK776asdhds\r\n\
+                       From: <sip:alice@example.com>;tag=123\r\n\
+                       To: <sip:alice@example.com>\r\n\
+                       Call-ID: test-call-id\r\n\
+                       CSeq: 1 REGISTER\r\n\
+                       Contact: <sip:alice@client.example.com>;expires=30\r\n\
+                       \r\n";
+            let request = SipMessage::parse(msg.as_bytes()).unwrap();
+            assert_eq!(request.min_expires().unwrap(), None);
+
+            let response = SipMessageBuilder::interval_too_brief(&request, 3600).build().unwrap();
+            assert!(response.starts_with("SIP/2.0 423 Interval Too Brief\r\n"));
+            let response = SipMessage::parse(response.as_bytes()).unwrap();
+            assert_eq!(response.min_expires().unwrap(), Some(3600));
+
+            let bad = SipMessage::parse(msg.replace("\r\n\r\n", "\r\nMin-Expires: soon\r\n\r\n").as_bytes()).unwrap();
+            assert!(bad.min_expires().is_err());
+        }
+
+        #[test]
+        fn test_session_interval_too_small() {
+            use crate::modification::message_builder::SipMessageBuilder;
+
+            let msg = "INVITE sip:bob@example.com SIP/2.0\r\n\
+                       Via: SIP/2.0/UDP client.example.com;branch=z9hG4bK776asdhds\r\n\
+                       From: <sip:alice@example.com>;tag=123\r\n\
+                       To: <sip:bob@example.com>\r\n\
+                       Call-ID: test-call-id\r\n\
+                       CSeq: 1 INVITE\r\n\
+                       Session-Expires: 60\r\n\
+                       Min-SE: 30;x-note=1\r\n\
+                       \r\n";
+            let request = SipMessage::parse(msg.as_bytes()).unwrap();
+            assert_eq!(request.min_se().unwrap(), Some(30));
+
+            let response = SipMessageBuilder::session_interval_too_small(&request, 90).build().unwrap();
+            assert!(response.starts_with("SIP/2.0 422 Session Interval Too Small\r\n"));
+            let response = SipMessage::parse(response.as_bytes()).unwrap();
+            assert_eq!(response.min_se().unwrap(), Some(90));
+            assert!(response.to_tag().is_some());
+
+            let bad = SipMessage::parse(msg.replace("Min-SE: 30", "Min-SE: soon").as_bytes()).unwrap();
+            assert!(bad.min_se().is_err());
+        }
+
+        #[test]
+        fn test_content_length_follows_body() {
+            let msg = "INVITE sip:bob@example.com SIP/2.0\r\n\
+                       Via: SIP/2.0/UDP client.example.com;branch=z9hG4bK776asdhds\r\n\
+                       From: Alice <sip:alice@example.com>;tag=123\r\n\
+                       To: Bob <sip:bob@example.com>\r\n\
+                       Call-ID: test-call-id\r\n\
+                       CSeq: 1 INVITE\r\n\
+                       Content-Type: application/sdp\r\n\
+                       l: 4\r\n\
+                       \r\n\
+                       v=0\r\n";
+
+            let mut modifier = SipMessage::new_from_str(msg).into_zero_copy_modifier();
+            modifier.set_body("v=0\r\ns=-\r\n");
+            let result = String::from_utf8(modifier.build()).unwrap();
+
+            assert!(!result.contains("l: 4"));
+            assert!(result.ends_with("Content-Length: 10\r\n\r\nv=0\r\ns=-\r\n"));
+
+            let mut reparsed = SipMessage::new_from_str(&result);
+            reparsed.parse_without_validation().unwrap();
+            assert_eq!(reparsed.body(), Some("v=0\r\ns=-\r\n"));
+
+            // Building stages nothing, so diff stays empty
+            let mut modifier = SipMessage::new_from_str(msg).into_zero_copy_modifier();
+            let mut buf = Vec::new();
+            modifier.build_into(&mut buf);
+            assert!(modifier.diff().is_empty());
+            modifier.set_body("v=0\r\ns=-\r\n").finalize_content_length();
+            assert_eq!(
+                modifier.diff(),
+                vec![HeaderDiff::Modified {
+                    name: "Content-Length".to_string(),
+                    old: "4".to_string(),
+                    new: "10".to_string(),
+                }]
+            );
+
+            // The body is the parsed one: leading CRLFs and pipelined bytes stay out
+            let framed = format!("\r\n\r\n{}INVITE sip:carol@example.com SIP/2.0\r\n", msg.replace("l: 4", "l: 5"));
+            let modifier = SipMessage::new_from_str(&framed).into_zero_copy_modifier();
+            let result = String::from_utf8(modifier.build()).unwrap();
+            assert!(result.starts_with("INVITE sip:bob@example.com SIP/2.0\r\n"));
+            assert!(result.ends_with("Content-Length: 5\r\n\r\nv=0\r\n"));
+        }
+
+        #[test]
+        fn test_builder_random_source() {
+            use crate::modification::message_builder::SipMessageBuilder;
+            use crate::CounterRandom;
+            use std::sync::Arc;
+
+            let msg = "OPTIONS sip:bob@example.com SIP/2.0\r\n\
+                       Via: SIP/2.0/UDP client.example.com;branch=z9hG4bK776asdhds\r\n\
+                       From: Alice <sip:alice@example.com>;tag=123\r\n\
+                       To: Bob <sip:bob@example.com>\r\n\
+                       Call-ID: test-call-id\r\n\
+                       CSeq: 1 OPTIONS\r\n\
+                       \r\n";
+            let request = SipMessage::parse(msg.as_bytes()).unwrap();
+            let response = SipMessageBuilder::server_internal_error(&request)
+                .random_source(Arc::new(CounterRandom::new(1)))
+                .build()
+                .unwrap();
+            assert!(response.contains("To: Bob <sip:bob@example.com>;tag=tag-0000000100000002\r\n"), "{}", response);
+        }
+
+        #[test]
+        fn test_build_and_parse() {
+            let msg = "INVITE sip:bob@example.com SIP/2.0\r\n\
+                       Via: SIP/2.0/UDP client.example.com;branch=z9hG4bK776asdhds\r\n\
+                       From: Alice <sip:alice@example.com>;tag=123\r\n\
+                       To: Bob <sip:bob@example.com>\r\n\
+                       Call-ID: a84b4c76e66710\r\n\
+                       CSeq: 1 INVITE\r\n\
+                       Max-Forwards: 70\r\n\
+                       Content-Length: 0\r\n\
+                       \r\n";
+
+            let sip_msg = SipMessage::parse(msg.as_bytes()).unwrap();
+            let mut modifier = sip_msg.into_zero_copy_modifier();
+            modifier.add_via("SIP/2.0/UDP b2bua.example.com;branch=z9hG4bKb2b");
+            modifier.add_header("X-Leg", "outbound");
+            modifier.decrement_max_forwards().unwrap();
+
+            let mut parsed = modifier.build_and_parse().unwrap();
+            assert!(parsed.is_request());
+            assert_eq!(parsed.all_vias().unwrap().len(), 2);
+            assert_eq!(parsed.max_forwards(), Some(69));
+            assert!(parsed.has_header("X-Leg"));
+            assert_eq!(parsed.call_id().as_deref(), Some("a84b4c76e66710"));
+        }
+
+        #[test]
+        fn test_add_path() {
+            let register = |supported: &str| {
+                let msg = format!(
+                    "REGISTER sip:registrar.example.com SIP/2.0\r\n\
+                     Via: SIP/2.0/UDP pc33.example.com;branch=z9hG4bKnashds7\r\n\
+                     From: Bob <sip:bob@example.com>;tag=a73kszlfl\r\n\
+                     To: Bob <sip:bob@example.com>\r\n\
+                     Call-ID: 1j9FpLxk3uxtm8tn@pc33.example.com\r\n\
+                     CSeq: 1 REGISTER\r\n\
+                     {}Path: <sip:inner.example.com;lr>\r\n\
+                     Contact: <sip:bob@192.0.2.4>\r\n\
+                     Content-Length: 0\r\n\
+                     \r\n",
+                    supported
+                );
+                SipMessage::parse(msg.as_bytes()).unwrap().into_zero_copy_modifier()
+            };
+
+            let mut modifier = register("Supported: path, outbound\r\n");
+            modifier.add_path("sip:edge.example.com;lr").unwrap();
+            let result = String::from_utf8(modifier.build()).unwrap();
+            assert!(result.contains(
+                "Path: <sip:edge.example.com;lr>\r\nPath: <sip:inner.example.com;lr>\r\n"
+            ));
+
+            assert!(register("").add_path("sip:edge.example.com;lr").is_err());
+        }
+
+        #[test]
+        fn test_via_order() {
+            let msg = "INVITE sip:bob@example.com SIP/2.0\r\n\
+                       Via: SIP/2.0/UDP client.example.com;branch=z9hG4bK776asdhds\r\n\
+                       v: SIP/2.0/UDP nat.example.com;branch=z9hG4bK4b43c2\r\n\
+                       From: Alice <sip:alice@example.com>;tag=123\r\n\
+                       To: Bob <sip:bob@example.com>\r\n\
+                       Call-ID: a84b4c76e66710\r\n\
+                       CSeq: 1 INVITE\r\n\
+                       Max-Forwards: 70\r\n\
+                       Content-Length: 0\r\n\
+                       \r\n";
+            let vias = [
+                "SIP/2.0/UDP a.example.com;branch=z9hG4bKa",
+                "SIP/2.0/UDP b.example.com;branch=z9hG4bKb",
+                "SIP/2.0/UDP c.example.com;branch=z9hG4bKc",
+            ];
+            let branches = |modifier: ZeroCopyModifier| {
+                let parsed = modifier.build_and_parse().unwrap().to_parsed().unwrap();
+                parsed.via.into_iter()
+                    .map(|via| via.params.into_iter().find(|(name, _)| name == "branch").unwrap().1.unwrap())
+                    .collect::<Vec<_>>()
+            };
+
+            let mut modifier = SipMessage::parse(msg.as_bytes()).unwrap().into_zero_copy_modifier();
+            for via in vias {
+                modifier.add_via(via);
+            }
+            assert_eq!(
+                branches(modifier),
+                ["z9hG4bKa", "z9hG4bKb", "z9hG4bKc", "z9hG4bK776asdhds", "z9hG4bK4b43c2"]
+            );
+
+            let mut modifier = SipMessage::parse(msg.as_bytes()).unwrap().into_zero_copy_modifier();
+            modifier.add_via("SIP/2.0/UDP stale.example.com;branch=z9hG4bKstale");
+            modifier.set_via_stack(&vias);
+            assert_eq!(branches(modifier), ["z9hG4bKa", "z9hG4bKb", "z9hG4bKc"]);
+        }
+
+        #[test]
+        fn test_ensure_to_tag() {
+            let msg = "SIP/2.0 200 OK\r\n\
+                       Via: SIP/2.0/UDP client.example.com;branch=z9hG4bK776asdhds\r\n\
+                       From: Alice <sip:alice@example.com>;tag=123\r\n\
+                       To: Bob <sip:bob@example.com>\r\n\
+                       Call-ID: test-call-id\r\n\
+                       CSeq: 1 INVITE\r\n\
+                       Content-Length: 0\r\n\
+                       \r\n";
+
+            let mut modifier = SipMessage::new_from_str(msg).into_zero_copy_modifier();
+            modifier.ensure_to_tag("a6c85cf").unwrap().ensure_to_tag("other").unwrap();
+            assert_eq!(
+                modifier.diff(),
+                vec![HeaderDiff::Modified {
+                    name: "To".to_string(),
+                    old: "Bob <sip:bob@example.com>".to_string(),
+                    new: "Bob <sip:bob@example.com>;tag=a6c85cf".to_string(),
+                }]
+            );
+            let mut tagged = modifier.build_and_parse().unwrap();
+            assert_eq!(tagged.to_tag(), Some("a6c85cf"));
+            assert_eq!(tagged.header_count("To"), 1);
+            assert!(tagged.to_without_tag().unwrap().is_none());
+
+            let mut modifier = tagged.clone().into_zero_copy_modifier();
+            modifier.ensure_to_tag("other").unwrap();
+            assert!(modifier.diff().is_empty());
+            assert_eq!(modifier.build(), tagged.raw_message().as_bytes());
+
+            let mut modifier = SipMessage::new_from_str(msg).into_zero_copy_modifier();
+            assert!(modifier.ensure_to_tag("bad tag").is_err());
+
+            // A compact To line is rewritten under its full name
+            let compact = msg.replace("To: Bob", "t: Bob");
+            let mut modifier = SipMessage::new_from_str(&compact).into_zero_copy_modifier();
+            modifier.ensure_to_tag("a6c85cf").unwrap();
+            let result = String::from_utf8(modifier.build()).unwrap();
+            assert!(result.contains("\r\nTo: Bob <sip:bob@example.com>;tag=a6c85cf\r\n"));
+            assert!(!result.contains("\r\nt: "));
+        }
+
+        #[test]
+        fn test_build_keeps_binary_body() {
+            let mut data = b"MESSAGE sip:bob@example.com SIP/2.0\r\n\
+                             Via: SIP/2.0/UDP client.example.com;branch=z9hG4bK776asdhds\r\n\
+                             From: Alice <sip:alice@example.com>;tag=123\r\n\
+                             To: Bob <sip:bob@example.com>\r\n\
+                             Call-ID: test-call-id\r\n\
+                             CSeq: 1 MESSAGE\r\n\
+                             Content-Type: application/isup\r\n\
+                             Content-Length: 4\r\n\
+                             \r\n"
+                .to_vec();
+            let body = [0x01, 0xff, 0x00, 0x80];
+            data.extend_from_slice(&body);
+
+            let mut modifier = SipMessage::parse_bytes(&data).unwrap().into_zero_copy_modifier();
+            modifier.add_header("X-Trace", "abc");
+            let built = modifier.build();
+            assert!(built.ends_with(b"Content-Length: 4\r\nX-Trace: abc\r\n\r\n\x01\xff\x00\x80"));
+
+            let reparsed = SipMessage::parse_bytes(&built).unwrap();
+            assert_eq!(reparsed.body_bytes(), Some(&body[..]));
+            assert_eq!(reparsed.header_count("X-Trace"), 1);
+        }
+
         #[test]
         fn test_multiple_header_modifications() {
             let msg = "INVITE sip:bob@example.com SIP/2.0\r\n\
@@ -1432,7 +2907,7 @@ pub mod zero_copy {
             let timer_headers = SessionTimerHeaders {
                 session_expires: 1800,
                 min_se: Some(90),
-                refresher: SessionRefresher::Uac,
+                refresher: Some(SessionRefresher::Uac),
                 required: true,
             };
             
@@ -1464,7 +2939,7 @@ pub mod zero_copy {
             let timer_headers = SessionTimerHeaders {
                 session_expires: 3600,
                 min_se: None,
-                refresher: SessionRefresher::Uas,
+                refresher: Some(SessionRefresher::Uas),
                 required: false,
             };
             
@@ -1517,11 +2992,10 @@ pub mod zero_copy {
             let sip_msg = SipMessage::parse(msg.as_bytes()).unwrap();
 
             let timer_headers = sip_msg.parse_session_timer_headers().unwrap();
-            // Mock implementation returns fixed values
             assert_eq!(timer_headers.session_expires, 1800);
             assert_eq!(timer_headers.min_se, Some(90));
-            assert_eq!(timer_headers.refresher, SessionRefresher::Uas); // Mock returns Uas
-            assert!(!timer_headers.required); // Mock returns false
+            assert_eq!(timer_headers.refresher, Some(SessionRefresher::Uac));
+            assert!(timer_headers.required);
         }
 
         #[test]
@@ -1541,13 +3015,42 @@ pub mod zero_copy {
             let sip_msg = SipMessage::parse(msg.as_bytes()).unwrap();
 
             let timer_headers = sip_msg.parse_session_timer_headers().unwrap();
-            // Mock implementation returns fixed values
-            assert_eq!(timer_headers.session_expires, 1800); // Mock returns 1800
-            assert_eq!(timer_headers.min_se, Some(90)); // Mock returns Some(90)
-            assert_eq!(timer_headers.refresher, SessionRefresher::Uas);
+            assert_eq!(timer_headers.session_expires, 3600);
+            assert_eq!(timer_headers.min_se, None);
+            // No refresher parameter: left to negotiation, not implicitly UAS
+            assert_eq!(timer_headers.refresher, None);
             assert!(!timer_headers.required);
         }
 
+        #[test]
+        fn test_session_timer_explicit_uas_refresher() {
+            let msg = "INVITE sip:bob@example.com SIP/2.0\r\n\
+                       Via: SIP/2.0/UDP client.example.com;branch=z9hG4bK776asdhds\r\n\
+                       From: Alice <sip:alice@example.com>;tag=123\r\n\
+                       To: Bob <sip:bob@example.com>\r\n\
+                       Call-ID: test-call-id\r\n\
+                       CSeq: 1 INVITE\r\n\
+                       Max-Forwards: 70\r\n\
+                       x: 1800;refresher=UAS\r\n\
+                       Content-Length: 0\r\n\
+                       \r\n";
+
+            let sip_msg = SipMessage::parse(msg.as_bytes()).unwrap();
+            let timer_headers = sip_msg.parse_session_timer_headers().unwrap();
+            assert_eq!(timer_headers.session_expires, 1800);
+            assert_eq!(timer_headers.refresher, Some(SessionRefresher::Uas));
+
+            // An unspecified refresher is written without the parameter
+            let mut modifier = sip_msg.into_zero_copy_modifier();
+            modifier.add_session_timer_headers(&SessionTimerHeaders {
+                refresher: None,
+                ..timer_headers
+            });
+            let result = modifier.build();
+            let result_str = std::str::from_utf8(&result).unwrap();
+            assert!(result_str.contains("Session-Expires: 1800\r\n"));
+        }
+
         #[test]
         fn test_supports_session_timers() {
             let msg = "INVITE sip:bob@example.com SIP/2.0\r\n\
@@ -1602,7 +3105,7 @@ pub mod zero_copy {
             let timer_headers = SessionTimerHeaders {
                 session_expires: 1800,
                 min_se: Some(90),
-                refresher: SessionRefresher::Uac,
+                refresher: Some(SessionRefresher::Uac),
                 required: true,
             };
             
@@ -75,6 +75,13 @@ impl SipMessagePool {
         }
     }
 
+    /// Return a message taken out of the pool (e.g. via `into_inner`) for reuse
+    ///
+    /// The message is dropped instead when the pool is already at capacity.
+    pub fn recycle(&self, message: SipMessage) {
+        return_to_pool(&self.pool, message, self.max_size);
+    }
+
     /// Get current pool size
     pub fn size(&self) -> usize {
         self.pool.lock().unwrap().len()
@@ -128,14 +135,18 @@ impl PooledSipMessage {
 impl Drop for PooledSipMessage {
     fn drop(&mut self) {
         if let Some(message) = self.message.take() {
-            // Return to pool if not at capacity
-            if let Ok(mut pool) = self.pool.lock() {
-                if pool.len() < self.max_size {
-                    pool.push_back(message);
-                }
-                // Otherwise let it drop naturally
-            }
+            return_to_pool(&self.pool, message, self.max_size);
+        }
+    }
+}
+
+/// Push a message back into the pool unless it is at capacity
+fn return_to_pool(pool: &Mutex<VecDeque<SipMessage>>, message: SipMessage, max_size: usize) {
+    if let Ok(mut pool) = pool.lock() {
+        if pool.len() < max_size {
+            pool.push_back(message);
         }
+        // Otherwise let it drop naturally
     }
 }
 
@@ -263,4 +274,23 @@ mod tests {
         assert!(msg.parse_from_str(sip_data).is_ok());
         assert_eq!(msg.message().call_id().unwrap(), "global-test");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_forwarding_loop_reuses_buffer() {
+        let pool = SipMessagePool::new(PoolConfig { initial_size: 1, ..PoolConfig::default() });
+        let sip_data = "INVITE sip:test@example.com SIP/2.0\r\nFrom: <sip:caller@example.com>\r\nTo: <sip:test@example.com>\r\nCall-ID: fwd-test\r\nCSeq: 1 INVITE\r\nVia: SIP/2.0/UDP 192.168.1.1:5060\r\nMax-Forwards: 70\r\n\r\n";
+        let mut buf = Vec::with_capacity(1024);
+
+        for _ in 0..3 {
+            let mut pooled_msg = pool.get();
+            pooled_msg.parse_from_str(sip_data).unwrap();
+            let mut modifier = pooled_msg.into_inner().into_zero_copy_modifier();
+            modifier.add_header("X-Hop", "1");
+            modifier.build_into(&mut buf);
+            assert!(buf.starts_with(b"INVITE sip:test@example.com SIP/2.0\r\n"));
+            assert_eq!(buf.capacity(), 1024);
+            pool.recycle(modifier.into_original());
+        }
+        assert_eq!(pool.size(), 1);
+    }
+}
@@ -2,10 +2,18 @@
 
 use std::collections::HashMap;
 use std::fmt;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::time::Duration;
 use strum_macros::{Display, EnumString};
 
+use crate::error::SsbcError;
+
 /// SIP URI schemes as defined in RFC 3261
+///
+/// Scheme names are case-insensitive (RFC 3261 Section 19.1.4), so `SIP:` and
+/// `sip:` both parse to [`Scheme::SIP`].
 #[derive(Debug, Clone, PartialEq, Eq, Display, EnumString, Default)]
+#[strum(ascii_case_insensitive)]
 pub enum Scheme {
     #[default]
     #[strum(serialize = "sip")]
@@ -16,6 +24,21 @@ pub enum Scheme {
     TEL,
 }
 
+impl Scheme {
+    /// Check if this scheme requires TLS on every hop (only `sips`)
+    pub fn is_secure(&self) -> bool {
+        matches!(self, Scheme::SIPS)
+    }
+
+    /// Default port when a URI omits one (RFC 3261 Section 19.1.2)
+    pub fn default_port(&self) -> u16 {
+        match self {
+            Scheme::SIPS => 5061,
+            Scheme::SIP | Scheme::TEL => 5060,
+        }
+    }
+}
+
 /// SIP methods as defined in RFC 3261 and extensions
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Display, EnumString)]
 pub enum Method {
@@ -37,6 +60,54 @@ pub enum Method {
     UNKNOWN(String),
 }
 
+/// RTT estimate, the base retransmission interval (RFC 3261 Section 17.1.1.1)
+pub const TIMER_T1: Duration = Duration::from_millis(500);
+
+/// Maximum retransmission interval for non-INVITE requests and INVITE responses
+pub const TIMER_T2: Duration = Duration::from_secs(4);
+
+/// Maximum duration a message remains in the network
+pub const TIMER_T4: Duration = Duration::from_secs(5);
+
+/// Client transaction timer defaults for a method class (RFC 3261 Section 17.1)
+///
+/// INVITE transactions use Timers A, B and D; every other method uses the
+/// non-INVITE Timers E, F and K. Values assume an unreliable transport.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransactionTimers {
+    /// Initial retransmission interval (Timer A or E)
+    pub retransmit: Duration,
+    /// Upper bound of the doubling retransmission interval; INVITE has none
+    pub retransmit_cap: Option<Duration>,
+    /// Transaction timeout (Timer B or F)
+    pub timeout: Duration,
+    /// Wait time in the Completed state (Timer D or K)
+    pub completed_wait: Duration,
+}
+
+impl Method {
+    /// RFC 3261 client transaction timer defaults for this method
+    ///
+    /// ACK never creates a client transaction of its own and CANCEL is a
+    /// non-INVITE transaction, so only INVITE gets the INVITE timers.
+    pub fn default_transaction_timers(&self) -> TransactionTimers {
+        match self {
+            Method::INVITE => TransactionTimers {
+                retransmit: TIMER_T1,
+                retransmit_cap: None,
+                timeout: TIMER_T1 * 64,
+                completed_wait: Duration::from_secs(32),
+            },
+            _ => TransactionTimers {
+                retransmit: TIMER_T1,
+                retransmit_cap: Some(TIMER_T2),
+                timeout: TIMER_T1 * 64,
+                completed_wait: TIMER_T4,
+            },
+        }
+    }
+}
+
 /// Represents a range of text within a message for zero-copy parsing
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct TextRange {
@@ -60,6 +131,21 @@ impl TextRange {
         &text[self.start..self.end]
     }
 
+    /// Get the string slice this range represents, checking it against `text`
+    ///
+    /// Unlike [`TextRange::as_str`] this never panics: a misordered range, a range
+    /// past the end of `text`, or one that splits a UTF-8 character (typically a
+    /// range taken from a different message) is reported as a parse error.
+    pub fn try_as_str<'a>(&self, text: &'a str) -> Result<&'a str, SsbcError> {
+        text.get(self.start..self.end).ok_or_else(|| {
+            SsbcError::parse_error(
+                format!("Text range {}..{} is not valid for the source", self.start, self.end),
+                None,
+                Some(format!("source length {}", text.len())),
+            )
+        })
+    }
+
     /// Get the length of this range
     pub fn len(&self) -> usize {
         self.end - self.start
@@ -80,18 +166,159 @@ pub type ParamValue = Option<TextRange>;
 /// Parameter map type for storing header parameters
 pub type ParamMap = HashMap<ParamKey, ParamValue>;
 
+/// Host of a URI, Via sent-by or Route address, see [`crate::validation::parse_host`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Host<'a> {
+    Ipv4(Ipv4Addr),
+    /// IPv6 reference, written `[2001:db8::1]` on the wire
+    Ipv6(Ipv6Addr),
+    /// Domain name, as written
+    Name(&'a str),
+}
+
+impl Host<'_> {
+    /// Compare hosts: IP addresses by value, names case-insensitively and
+    /// ignoring a trailing dot
+    pub fn matches(&self, other: &Host<'_>) -> bool {
+        match (self, other) {
+            (Host::Name(name), Host::Name(other)) => name
+                .trim_end_matches('.')
+                .eq_ignore_ascii_case(other.trim_end_matches('.')),
+            _ => self == other,
+        }
+    }
+}
+
 /// Represents a SIP URI with all its components
 #[derive(Debug, Clone, PartialEq, Default)]
 pub struct SipUri {
     pub scheme: Scheme,
     pub user_info: Option<TextRange>,
     pub user_params: ParamMap,
+    /// Host without the brackets of an IPv6 reference
     pub host: Option<TextRange>,
     pub port: Option<u16>,
     pub params: ParamMap,
     pub headers: Option<TextRange>,
 }
 
+impl SipUri {
+    /// Port to contact: the explicit port, or the scheme default (5061 for sips)
+    pub fn effective_port(&self) -> u16 {
+        self.port.unwrap_or_else(|| self.scheme.default_port())
+    }
+
+    /// Check URI equivalence (RFC 3261 Section 19.1.4)
+    ///
+    /// `raw` and `other_raw` are the messages each URI was parsed from. The
+    /// user part is compared case-sensitively after unescaping, the host
+    /// case-insensitively, and an absent port differs from an explicit one.
+    /// The user, ttl, method, maddr and transport parameters must match when
+    /// either URI has them; other parameters only when both do. The `lr`
+    /// flag is ignored.
+    pub fn same_as(&self, raw: &str, other: &SipUri, other_raw: &str) -> bool {
+        if self.scheme != other.scheme || self.port != other.port {
+            return false;
+        }
+
+        let user = self.user_info.map(|range| unescape(range.as_str(raw)));
+        let other_user = other.user_info.map(|range| unescape(range.as_str(other_raw)));
+        if user != other_user {
+            return false;
+        }
+
+        let host = self.host.map(|range| range.as_str(raw));
+        let other_host = other.host.map(|range| range.as_str(other_raw));
+        let hosts_match = match (host, other_host) {
+            (Some(host), Some(other_host)) => {
+                host.eq_ignore_ascii_case(other_host)
+                    || matches!(
+                        (host.parse::<std::net::IpAddr>(), other_host.parse::<std::net::IpAddr>()),
+                        (Ok(a), Ok(b)) if a == b
+                    )
+            }
+            (host, other_host) => host == other_host,
+        };
+        if !hosts_match {
+            return false;
+        }
+
+        let params = uri_params(&self.params, raw);
+        let other_params = uri_params(&other.params, other_raw);
+        let value_of = |params: &[(&str, Option<&str>)], name: &str| {
+            params
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.unwrap_or_default().to_ascii_lowercase())
+        };
+        for name in ["user", "ttl", "method", "maddr", "transport"] {
+            if value_of(&params, name) != value_of(&other_params, name) {
+                return false;
+            }
+        }
+        let shared_params_match = params
+            .iter()
+            .filter(|(key, _)| !key.eq_ignore_ascii_case("lr"))
+            .all(|(key, _)| match value_of(&other_params, key) {
+                Some(other_value) => value_of(&params, key) == Some(other_value),
+                None => true,
+            });
+        if !shared_params_match {
+            return false;
+        }
+
+        let headers = uri_headers(self.headers.map(|range| range.as_str(raw)));
+        let other_headers = uri_headers(other.headers.map(|range| range.as_str(other_raw)));
+        headers == other_headers
+    }
+}
+
+/// Parameters of a URI as (name, value) text
+fn uri_params<'a>(params: &ParamMap, raw: &'a str) -> Vec<(&'a str, Option<&'a str>)> {
+    params
+        .iter()
+        .map(|(key, value)| (key.as_str(raw), value.map(|value| value.as_str(raw))))
+        .collect()
+}
+
+/// URI headers (`?name=value&...`) sorted by lowercase name, values unescaped
+fn uri_headers(headers: Option<&str>) -> Vec<(String, String)> {
+    let mut headers: Vec<(String, String)> = headers
+        .unwrap_or_default()
+        .split('&')
+        .filter(|header| !header.is_empty())
+        .map(|header| {
+            let (name, value) = header.split_once('=').unwrap_or((header, ""));
+            (name.to_ascii_lowercase(), String::from_utf8_lossy(&unescape(value)).into_owned())
+        })
+        .collect();
+    headers.sort();
+    headers
+}
+
+/// Decode `%XX` escapes, leaving malformed ones as they are
+fn unescape(text: &str) -> Vec<u8> {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes.get(i + 1..i + 3)
+            .filter(|_| bytes[i] == b'%')
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    decoded
+}
+
 impl fmt::Display for SipUri {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         // For Display implementation, we need a simplified version
@@ -108,6 +335,91 @@ impl fmt::Display for SipUri {
     }
 }
 
+/// Parsed request line of a SIP request, borrowing from the message
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequestLine<'a> {
+    pub method: Method,
+    pub uri: SipUri,
+    pub version: &'a str,
+}
+
+/// Parsed status line of a SIP response, borrowing from the message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatusLine<'a> {
+    pub version: &'a str,
+    pub code: u16,
+    pub reason: &'a str,
+}
+
+/// Owned URI, part of a [`ParsedMessage`]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ParsedUri {
+    pub scheme: Scheme,
+    /// User part, or the number of a `tel:` URI, still percent-encoded
+    pub user: Option<String>,
+    /// Host without the brackets of an IPv6 reference
+    pub host: Option<String>,
+    pub port: Option<u16>,
+    pub params: Vec<(String, Option<String>)>,
+    /// Header part after `?`, still percent-encoded
+    pub headers: Option<String>,
+}
+
+/// Owned name-addr of From, To, Contact, Route and Record-Route
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ParsedAddress {
+    /// Display name without the surrounding quotes
+    pub display_name: Option<String>,
+    pub uri: ParsedUri,
+    pub params: Vec<(String, Option<String>)>,
+}
+
+/// Owned Via entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsedVia {
+    pub transport: Transport,
+    /// Sent-by host without the brackets of an IPv6 reference
+    pub host: String,
+    pub port: Option<u16>,
+    pub params: Vec<(String, Option<String>)>,
+}
+
+/// Owned CSeq
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsedCSeq {
+    pub seq: u32,
+    pub method: Method,
+}
+
+/// Owned start line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParsedStartLine {
+    Request { method: Method, uri: ParsedUri },
+    Response { code: u16, reason: String },
+}
+
+/// Fully parsed, owned copy of a message, see `SipMessage::to_parsed`
+///
+/// Parameters keep their order in the message. Repeated headers are split
+/// into one entry per element, so a comma-joined Via line gives several `via`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsedMessage {
+    pub start_line: ParsedStartLine,
+    pub via: Vec<ParsedVia>,
+    pub from: Option<ParsedAddress>,
+    pub to: Option<ParsedAddress>,
+    pub call_id: Option<String>,
+    pub cseq: Option<ParsedCSeq>,
+    pub max_forwards: Option<u32>,
+    /// Contact addresses; a wildcard `Contact: *` yields none
+    pub contacts: Vec<ParsedAddress>,
+    pub routes: Vec<ParsedAddress>,
+    pub record_routes: Vec<ParsedAddress>,
+    /// Every header line as (name, unfolded value), in wire order
+    pub headers: Vec<(String, String)>,
+    pub body: Option<String>,
+}
+
 /// Represents a SIP address, used in headers like To, From, etc.
 #[derive(Debug, Clone, PartialEq)]
 pub struct Address {
@@ -118,6 +430,128 @@ pub struct Address {
     pub params: ParamMap,
 }
 
+/// Feature tags registered without the `+` prefix (RFC 3840 Section 10)
+const BASE_FEATURE_TAGS: &[&str] = &[
+    "audio", "application", "data", "control", "video", "text", "automata", "class", "duplex",
+    "mobility", "description", "events", "priority", "methods", "schemes", "extensions", "isfocus",
+    "actor", "language",
+];
+
+/// Value of a media feature tag (RFC 3840 Section 9)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FeatureValue {
+    /// Bare flag (`;audio`) or quoted `"TRUE"` / `"FALSE"`
+    Bool(bool),
+    /// Quoted tag-value list such as `"INVITE,BYE"`; negated values keep their `!`
+    List(Vec<String>),
+    /// Quoted string value such as `"<urn:example>"`, without the angle brackets
+    String(String),
+}
+
+/// Media feature tags of a Contact address, in parameter order
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FeatureTags {
+    /// Tag names without the `+` prefix, lowercased
+    pub tags: Vec<(String, FeatureValue)>,
+}
+
+impl FeatureTags {
+    /// Look up a tag by name, with or without its `+` prefix
+    pub fn get(&self, name: &str) -> Option<&FeatureValue> {
+        let name = name.strip_prefix('+').unwrap_or(name);
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value)
+    }
+
+    /// Check that a tag is present and not explicitly `FALSE`
+    pub fn has(&self, name: &str) -> bool {
+        self.get(name).is_some_and(|value| *value != FeatureValue::Bool(false))
+    }
+
+    /// Keep the feature tags among unquoted `(name, value)` parameters
+    pub(crate) fn from_params<'p>(params: impl IntoIterator<Item = (&'p str, Option<&'p str>)>) -> Self {
+        let tags = params
+            .into_iter()
+            .filter_map(|(name, value)| {
+                let name = match name.strip_prefix('+') {
+                    Some(extension) => extension,
+                    None if BASE_FEATURE_TAGS.iter().any(|base| base.eq_ignore_ascii_case(name)) => name,
+                    None => return None,
+                };
+                let value = value.map_or(FeatureValue::Bool(true), FeatureValue::parse);
+                Some((name.to_ascii_lowercase(), value))
+            })
+            .collect();
+        FeatureTags { tags }
+    }
+}
+
+impl FeatureValue {
+    fn parse(value: &str) -> Self {
+        if value.eq_ignore_ascii_case("TRUE") {
+            FeatureValue::Bool(true)
+        } else if value.eq_ignore_ascii_case("FALSE") {
+            FeatureValue::Bool(false)
+        } else if let Some(string) = value.strip_prefix('<').and_then(|value| value.strip_suffix('>')) {
+            FeatureValue::String(string.to_string())
+        } else {
+            FeatureValue::List(
+                value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|item| !item.is_empty())
+                    .map(str::to_string)
+                    .collect(),
+            )
+        }
+    }
+}
+
+/// One Accept-Contact or Reject-Contact value (RFC 3841 Section 9)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContactPreference {
+    /// Feature predicate the contact has to match
+    pub features: FeatureTags,
+    /// `require` flag: drop contacts that do not match (Accept-Contact only)
+    pub require: bool,
+    /// `explicit` flag: only count features the contact declares (Accept-Contact only)
+    pub explicit: bool,
+}
+
+impl Address {
+    /// Media feature tags among the address parameters (RFC 3840)
+    ///
+    /// Base tags such as `audio` or `methods` appear bare, every other tag with
+    /// a `+` prefix (`+sip.instance`, `+g.3gpp.icsi-ref`). Parameters that are
+    /// not feature tags (`expires`, `q`, ...) are skipped. `raw` is the message
+    /// the address was parsed from.
+    pub fn feature_tags(&self, raw: &str) -> FeatureTags {
+        let mut params: Vec<_> = self.params.iter().collect();
+        params.sort_by_key(|(key, _)| key.start);
+
+        FeatureTags::from_params(params.into_iter().map(|(key, value)| {
+            (
+                key.as_str(raw).trim(),
+                value.map(|value| value.as_str(raw).trim().trim_matches('"')),
+            )
+        }))
+    }
+
+    /// Compare the URIs of two addresses, e.g. a Route entry against our own
+    ///
+    /// Display names and header parameters are ignored; see [`SipUri::same_as`]
+    /// for the URI rules. `raw` and `other_raw` are the messages each address
+    /// was parsed from.
+    pub fn same_uri_as(&self, raw: &str, other: &Address, other_raw: &str) -> bool {
+        self.uri.same_as(raw, &other.uri, other_raw)
+    }
+}
+
+/// Prefix of every Via branch generated by an RFC 3261 compliant element
+pub const BRANCH_MAGIC_COOKIE: &str = "z9hG4bK";
+
 /// Represents a Via header
 #[derive(Debug, Clone, PartialEq)]
 pub struct Via {
@@ -128,6 +562,118 @@ pub struct Via {
     pub params: ParamMap,
 }
 
+impl Via {
+    /// Host part of sent-by, without the brackets of an IPv6 reference
+    pub fn host<'a>(&self, raw_message: &'a str) -> &'a str {
+        let (host, _) = self.split_sent_by(raw_message);
+        host.strip_prefix('[')
+            .and_then(|host| host.strip_suffix(']'))
+            .unwrap_or(host)
+    }
+
+    /// Port part of sent-by; `None` when absent or not a valid port
+    pub fn port(&self, raw_message: &str) -> Option<u16> {
+        let (_, port) = self.split_sent_by(raw_message);
+        crate::validation::parse_port(port?).ok()
+    }
+
+    /// Split sent-by into host and port, honouring `[IPv6]:port`
+    fn split_sent_by<'a>(&self, raw_message: &'a str) -> (&'a str, Option<&'a str>) {
+        crate::validation::split_host_port(self.sent_by.as_str(raw_message))
+    }
+}
+
+/// Key identifying a SIP transaction (RFC 3261 Sections 17.1.3 and 17.2.3)
+///
+/// ACK is keyed as INVITE so that an ACK for a non-2xx response lands on the
+/// INVITE transaction, while CANCEL keeps its own method and therefore forms a
+/// separate transaction despite sharing the INVITE branch.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TransactionId {
+    /// Top Via branch, including the magic cookie
+    pub branch: String,
+    /// Top Via sent-by, lowercased; only part of server transaction keys
+    pub sent_by: Option<String>,
+    pub method: Method,
+}
+
+/// How a received ACK is handled, see `SipMessage::ack_type`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AckType {
+    /// Acknowledges a 2xx: a transaction of its own, passed to the TU
+    TwoXxAck,
+    /// Acknowledges a 3xx-6xx: absorbed by the INVITE server transaction
+    NonTwoXxAck,
+}
+
+/// Which headers have been upgraded from raw ranges to parsed values
+///
+/// Returned by `SipMessage::lazy_parse_state`. Headers only get parsed when an
+/// accessor such as `from()` or `all_vias()` asks for them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LazyState {
+    /// The header block has been split into header lines
+    pub headers_parsed: bool,
+    pub to: bool,
+    pub from: bool,
+    /// Via headers parsed so far, out of `via_total`
+    pub via_parsed: usize,
+    pub via_total: usize,
+    /// Contact headers parsed so far, out of `contact_total`
+    pub contact_parsed: usize,
+    pub contact_total: usize,
+}
+
+impl LazyState {
+    /// Check that no header has been parsed beyond its raw range
+    pub fn is_zero_copy(&self) -> bool {
+        !self.to && !self.from && self.via_parsed == 0 && self.contact_parsed == 0
+    }
+}
+
+/// Dialog identifier as carried by Target-Dialog (RFC 4538)
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DialogId {
+    pub call_id: String,
+    pub local_tag: String,
+    pub remote_tag: String,
+}
+
+/// Dialog named by a Replaces (RFC 3891) or Join (RFC 3911) header
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CallReference {
+    pub call_id: String,
+    pub to_tag: String,
+    pub from_tag: String,
+    /// `early-only` flag; only meaningful for Replaces
+    pub early_only: bool,
+}
+
+/// Existing dialog an INVITE wants to take over or join
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum CallControlTarget {
+    Replaces(CallReference),
+    Join(CallReference),
+}
+
+/// IMS charging correlation from P-Charging-Vector (RFC 7315 Section 4.6)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChargingVector {
+    pub icid_value: String,
+    pub icid_generated_at: Option<String>,
+    pub orig_ioi: Option<String>,
+    pub term_ioi: Option<String>,
+}
+
+/// Charging function addresses from P-Charging-Function-Addresses (RFC 7315 Section 4.5)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChargingFunctionAddresses {
+    /// Charging collection functions, in order of preference
+    pub ccf: Vec<String>,
+    /// Event charging functions, in order of preference
+    pub ecf: Vec<String>,
+}
+
 /// Event package enumeration for SUBSCRIBE/NOTIFY
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Display, EnumString)]
 pub enum EventPackage {
@@ -155,6 +701,85 @@ pub enum EventPackage {
     DialogWinfo,
 }
 
+/// Media type of a message body or body part
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Display, EnumString)]
+#[strum(ascii_case_insensitive)]
+pub enum ContentType {
+    #[strum(serialize = "application/sdp")]
+    Sdp,
+    #[strum(serialize = "application/pidf+xml")]
+    Pidf,
+    #[strum(serialize = "application/isup")]
+    Isup,
+    #[strum(serialize = "text/plain")]
+    TextPlain,
+    #[strum(default)]
+    Other(String),
+}
+
+/// One part of a multipart body (RFC 2046 Section 5.1), see `SipMessage::body_parts`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BodyPart<'a> {
+    /// Part headers in order, names and values trimmed
+    pub headers: Vec<(&'a str, &'a str)>,
+    pub body: &'a str,
+    /// Parts of a nested multipart body, empty otherwise
+    pub parts: Vec<BodyPart<'a>>,
+}
+
+impl<'a> BodyPart<'a> {
+    /// Value of the part's Content-Type header
+    pub fn content_type(&self) -> Option<&'a str> {
+        self.headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+            .map(|(_, value)| *value)
+    }
+}
+
+/// Transport protocol of a Via sent-protocol or `transport` URI parameter
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Display, EnumString)]
+#[strum(ascii_case_insensitive)]
+pub enum Transport {
+    #[strum(serialize = "UDP")]
+    Udp,
+    #[strum(serialize = "TCP")]
+    Tcp,
+    #[strum(serialize = "TLS")]
+    Tls,
+    #[strum(serialize = "SCTP")]
+    Sctp,
+    #[strum(serialize = "WS")]
+    Ws,
+    #[strum(serialize = "WSS")]
+    Wss,
+    #[strum(default)]
+    Other(String),
+}
+
+impl Transport {
+    /// Check if the transport is connection-oriented (anything but UDP)
+    pub fn is_reliable(&self) -> bool {
+        !matches!(self, Transport::Udp)
+    }
+
+    /// Port used when none is given: 5061 over TLS, 5060 otherwise
+    pub fn default_port(&self) -> u16 {
+        match self {
+            Transport::Tls | Transport::Wss => 5061,
+            _ => 5060,
+        }
+    }
+}
+
+/// Where to send the responses to a request (RFC 3261 Section 18.2.2)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResponseDest {
+    pub host: String,
+    pub port: u16,
+    pub transport: Transport,
+}
+
 /// Represents a parsed event package with parameters for parsing
 #[derive(Debug, Clone, PartialEq)]
 pub struct EventPackageData {
@@ -162,6 +787,36 @@ pub struct EventPackageData {
     pub event_params: ParamMap,
 }
 
+/// Role of an SDP body in the RFC 3264 offer/answer exchange
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SdpRole {
+    Offer,
+    Answer,
+}
+
+/// Outcome of matching a peer's option tags against ours (RFC 3261 Section 8.2.2.3)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NegotiationResult {
+    /// Require tags we don't support; reject with 420 Bad Extension
+    pub unsupported: Vec<String>,
+    /// Proxy-Require tags we don't support; only a proxy rejects on these
+    pub unsupported_proxy: Vec<String>,
+    /// Tags the peer requires or supports that we support too
+    pub common: Vec<String>,
+}
+
+impl NegotiationResult {
+    /// Check if every required option can be honoured
+    pub fn is_acceptable(&self) -> bool {
+        self.unsupported.is_empty()
+    }
+
+    /// Value of the Unsupported header for a 420 response, if one is needed
+    pub fn unsupported_header(&self) -> Option<String> {
+        (!self.unsupported.is_empty()).then(|| self.unsupported.join(", "))
+    }
+}
+
 /// Header value types for parsed headers
 #[derive(Debug, Clone, PartialEq)]
 pub enum HeaderValue {
@@ -1,7 +1,69 @@
 // Input validation and sanitization for SIP messages
 
 use crate::error::{SsbcError, SsbcResult};
-use crate::types::TextRange;
+use crate::types::{Host, TextRange};
+use std::fmt;
+
+/// Site-specific policy check run on every header during parsing
+///
+/// Install one through [`crate::ParserLimits::header_validator`]. `name` is the
+/// lowercase full header name (compact forms are expanded) and `value` the
+/// unfolded, trimmed header value. Returning an error aborts the parse.
+pub trait HeaderValidator: Send + Sync {
+    fn validate(&self, name: &str, value: &str) -> Result<(), SsbcError>;
+}
+
+impl fmt::Debug for dyn HeaderValidator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("HeaderValidator")
+    }
+}
+
+/// Outcome of a single compliance check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComplianceStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+/// One check of a [`ComplianceReport`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ComplianceItem {
+    /// Short check name, e.g. `"required-headers"`
+    pub check: &'static str,
+    pub status: ComplianceStatus,
+    /// What was found, for warnings and failures
+    pub detail: Option<String>,
+}
+
+/// Checklist produced by `SipMessage::compliance_report`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ComplianceReport {
+    pub items: Vec<ComplianceItem>,
+}
+
+impl ComplianceReport {
+    /// Record the outcome of a check
+    pub fn push(&mut self, check: &'static str, status: ComplianceStatus, detail: Option<String>) {
+        self.items.push(ComplianceItem { check, status, detail });
+    }
+
+    /// Check if no item failed (warnings are allowed)
+    pub fn is_compliant(&self) -> bool {
+        self.failures().next().is_none()
+    }
+
+    /// Items that failed
+    pub fn failures(&self) -> impl Iterator<Item = &ComplianceItem> {
+        self.items.iter().filter(|item| item.status == ComplianceStatus::Fail)
+    }
+
+    /// Items that passed with a warning
+    pub fn warnings(&self) -> impl Iterator<Item = &ComplianceItem> {
+        self.items.iter().filter(|item| item.status == ComplianceStatus::Warn)
+    }
+}
 
 /// Validate that a string contains only valid UTF-8 characters
 pub fn validate_utf8(_input: &str) -> SsbcResult<()> {
@@ -73,6 +135,24 @@ pub fn validate_uri(uri: &str, max_depth: usize) -> SsbcResult<()> {
     Ok(())
 }
 
+/// Check that every `%` in a URI component starts a `%HH` escape (RFC 3261 Section 25.1)
+///
+/// `component` names the checked part (`"host"`, `"parameter"`, ...) in the error.
+pub fn validate_percent_escapes(value: &str, component: &str) -> SsbcResult<()> {
+    let bytes = value.as_bytes();
+    for (i, _) in value.match_indices('%') {
+        let escape = bytes.get(i + 1..i + 3);
+        if !escape.is_some_and(|hex| hex.iter().all(u8::is_ascii_hexdigit)) {
+            return Err(SsbcError::parse_error(
+                format!("Invalid percent-escape in URI {}", component),
+                None,
+                Some(value.to_string()),
+            ));
+        }
+    }
+    Ok(())
+}
+
 /// Validate a header name
 pub fn validate_header_name(name: &str) -> SsbcResult<()> {
     // Header names should only contain token characters
@@ -148,6 +228,82 @@ pub fn validate_status_code(code: u16) -> SsbcResult<()> {
     Ok(())
 }
 
+/// Parse a URI or Via port, accepting only 1-65535 without leading zeros
+pub fn parse_port(port: &str) -> SsbcResult<u16> {
+    let reason = if port.is_empty() {
+        Some("empty")
+    } else if !port.bytes().all(|b| b.is_ascii_digit()) {
+        Some("not a decimal number")
+    } else if port == "0" {
+        Some("port 0 is reserved")
+    } else if port.starts_with('0') {
+        Some("leading zeros are not allowed")
+    } else {
+        None
+    };
+
+    let parsed = match reason {
+        Some(reason) => Err(reason),
+        None => port.parse::<u16>().map_err(|_| "out of range 1-65535"),
+    };
+    parsed.map_err(|reason| SsbcError::ParseError {
+        message: format!("Invalid port '{}': {}", port, reason),
+        position: None,
+        context: None,
+    })
+}
+
+/// Split `host[:port]` into its parts, honouring `[IPv6]:port`
+///
+/// Neither part is validated; IPv6 brackets are kept on the host.
+pub(crate) fn split_host_port(host_port: &str) -> (&str, Option<&str>) {
+    let host_port = host_port.trim();
+    let port_colon = if host_port.starts_with('[') {
+        host_port.find(']').and_then(|end| host_port[end..].find(':').map(|colon| end + colon))
+    } else {
+        host_port.find(':')
+    };
+
+    match port_colon {
+        Some(colon) => (host_port[..colon].trim_end(), Some(host_port[colon + 1..].trim_start())),
+        None => (host_port, None),
+    }
+}
+
+/// Parse `host[:port]` as found in URIs, Via sent-by and Route addresses
+///
+/// IPv6 addresses must be bracketed (RFC 3261 Section 25.1); the port, when
+/// present, follows the rules of [`parse_port`].
+pub fn parse_host(host_port: &str) -> SsbcResult<(Host<'_>, Option<u16>)> {
+    let (host, port) = split_host_port(host_port);
+    let port = port.map(parse_port).transpose()?;
+
+    let invalid = |reason: &str| SsbcError::ParseError {
+        message: format!("Invalid host '{}': {}", host, reason),
+        position: None,
+        context: None,
+    };
+
+    let host = if let Some(reference) = host.strip_prefix('[') {
+        let address = reference
+            .strip_suffix(']')
+            .ok_or_else(|| invalid("unterminated IPv6 reference"))?;
+        Host::Ipv6(address.parse().map_err(|_| invalid("not an IPv6 address"))?)
+    } else if let Ok(address) = host.parse() {
+        Host::Ipv4(address)
+    } else if host.contains(':') {
+        return Err(invalid("IPv6 addresses must be enclosed in brackets"));
+    } else if host.is_empty() {
+        return Err(invalid("empty"));
+    } else if !host.bytes().all(|b| b.is_ascii_alphanumeric() || b"-._".contains(&b)) {
+        return Err(invalid("not a domain name"));
+    } else {
+        Host::Name(host)
+    };
+
+    Ok((host, port))
+}
+
 /// Validate that a string slice is within message bounds
 pub fn validate_range(range: &TextRange, message_len: usize) -> SsbcResult<()> {
     if range.start > message_len || range.end > message_len {
@@ -238,4 +394,38 @@ mod tests {
         assert!(validate_method("").is_err()); // Empty
         assert!(validate_method("A".repeat(33).as_str()).is_err()); // Too long
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_parse_port() {
+        assert_eq!(parse_port("5060").unwrap(), 5060);
+        assert_eq!(parse_port("1").unwrap(), 1);
+        assert_eq!(parse_port("65535").unwrap(), 65535);
+
+        assert!(parse_port("0").is_err());
+        assert!(parse_port("070").is_err());
+        assert!(parse_port("00005060").is_err());
+        assert!(parse_port("65536").is_err());
+        assert!(parse_port("").is_err());
+        assert!(parse_port("+5060").is_err());
+    }
+
+    #[test]
+    fn test_parse_host() {
+        use std::net::{Ipv4Addr, Ipv6Addr};
+
+        let v6: Ipv6Addr = "2001:db8::1".parse().unwrap();
+        assert_eq!(parse_host("[2001:db8::1]:5060").unwrap(), (Host::Ipv6(v6), Some(5060)));
+        assert_eq!(parse_host("[2001:db8::1]").unwrap(), (Host::Ipv6(v6), None));
+        assert_eq!(
+            parse_host("192.0.2.1:5070").unwrap(),
+            (Host::Ipv4(Ipv4Addr::new(192, 0, 2, 1)), Some(5070))
+        );
+        assert_eq!(parse_host("proxy.example.com").unwrap(), (Host::Name("proxy.example.com"), None));
+
+        assert!(parse_host("2001:db8::1").is_err());
+        assert!(parse_host("[2001:db8::1").is_err());
+        assert!(parse_host("[not-ipv6]").is_err());
+        assert!(parse_host("[2001:db8::1]:0").is_err());
+        assert!(parse_host("").is_err());
+    }
+}